@@ -0,0 +1,672 @@
+//! Importing an Anki collection: its notes become a deck and its review
+//! log becomes each item's progress, so switching to ljp does not start
+//! over. Only the first card of each note is read, which asks the note's
+//! first field for its second.
+//!
+//! Exporting goes the other way: sets become an Anki package of Basic
+//! notes whose cards keep the schedule ljp had for them, so they do not
+//! all arrive as new.
+use anyhow::{Context, Result};
+use chrono::{DateTime, Days, Local, NaiveDate, Utc};
+use rusqlite::{params, Connection, OpenFlags};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use crate::{
+    fsrs::{self, Fsrs},
+    leitner::Leitner,
+    quiz::escape_html,
+    schedule::ItemSchedule,
+    sets::deck::csv_field,
+    store::{ImportedProgress, ItemStats},
+    Grade, StudyItem,
+};
+
+/// The extensions of Anki collection files.
+pub const EXTENSIONS: [&str; 2] = ["anki2", "anki21"];
+
+/// Anki separates a note's fields with the unit separator.
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// A review from the collection's log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Review {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    pub grade: Grade,
+}
+
+/// A note's first card with its reviews, oldest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Card {
+    pub front: String,
+    pub back: String,
+    pub reviews: Vec<Review>,
+}
+
+impl Card {
+    /// Replays the reviews through FSRS and Leitner boxes, so whichever
+    /// `--algo` is used next picks up where Anki left off.
+    pub fn progress(&self) -> ImportedProgress {
+        let mut fsrs = Fsrs::new(fsrs::DEFAULT_PARAMS, 1);
+        let mut leitner = Leitner::new(1);
+        for review in &self.reviews {
+            fsrs.review_at(0, review.grade, review.at);
+            leitner.review(0, review.grade);
+        }
+        ImportedProgress {
+            reviews: self.reviews.len() as u32,
+            lapses: self.reviews.iter().filter(|r| !r.grade.passed()).count() as u32,
+            last_review: self
+                .reviews
+                .last()
+                .and_then(|review| DateTime::from_timestamp(review.at as i64, 0))
+                .map(|at| at.naive_utc()),
+            memory: fsrs.memory(0),
+            leitner_box: leitner.boxes()[0],
+        }
+    }
+}
+
+/// Is `path` named like an Anki collection?
+pub fn is_collection(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext))
+}
+
+/// Maps Anki's answer buttons; 0 marks a manual reschedule, which is no
+/// review at all.
+fn grade(ease: i64) -> Option<Grade> {
+    match ease {
+        1 => Some(Grade::Again),
+        2 => Some(Grade::Hard),
+        3 => Some(Grade::Good),
+        4 => Some(Grade::Easy),
+        _ => None,
+    }
+}
+
+/// Reduces a field's HTML to the text it shows.
+fn plain_text(field: &str) -> String {
+    let mut text = String::new();
+    let mut rest = field;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        match rest[start..].find('>') {
+            Some(end) => {
+                text.push(' ');
+                rest = &rest[start + end + 1..];
+            }
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    text.push_str(rest);
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Reads the first card of every note in the collection at `path` with
+/// its reviews. Notes whose first two fields are not both filled in, or
+/// repeat an earlier note's, are left out.
+pub fn read(path: &Path) -> Result<Vec<Card>> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open Anki collection {}", path.display()))?;
+
+    let mut reviews: HashMap<i64, Vec<Review>> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT cid, id, ease FROM revlog ORDER BY id")
+        .context("Failed to read the Anki review log")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get(2)?))
+    })?;
+    for row in rows {
+        let (card, id, ease) = row.context("Failed to read the Anki review log")?;
+        if let Some(grade) = grade(ease) {
+            reviews.entry(card).or_default().push(Review {
+                // Review ids are the time they were done, in milliseconds.
+                at: (id / 1000).max(0) as u64,
+                grade,
+            });
+        }
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT cards.id, notes.flds FROM cards JOIN notes ON notes.id = cards.nid
+             WHERE cards.ord = 0 ORDER BY notes.id",
+        )
+        .context("Failed to read Anki notes")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut cards: Vec<Card> = Vec::new();
+    for row in rows {
+        let (id, fields) = row.context("Failed to read Anki notes")?;
+        let mut fields = fields.split(FIELD_SEPARATOR).map(plain_text);
+        let (Some(front), Some(back)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if front.is_empty()
+            || back.is_empty()
+            || cards.iter().any(|c| c.front == front && c.back == back)
+        {
+            continue;
+        }
+        cards.push(Card {
+            front,
+            back,
+            reviews: reviews.remove(&id).unwrap_or_default(),
+        });
+    }
+    Ok(cards)
+}
+
+/// Writes `cards` as a CSV deck.
+pub fn deck(cards: &[Card]) -> String {
+    cards
+        .iter()
+        .map(|card| format!("{},{}\n", csv_field(&card.front), csv_field(&card.back)))
+        .collect()
+}
+
+/// The items `cards` become in a deck named `set`, with their progress.
+pub fn progress(cards: &[Card], set: &str) -> Vec<(StudyItem, ImportedProgress)> {
+    cards
+        .iter()
+        .map(|card| {
+            let item = StudyItem {
+                set: set.to_string(),
+                ..StudyItem::new(card.front.clone(), card.back.clone())
+            };
+            (item, card.progress())
+        })
+        .collect()
+}
+
+/// Counts the reviews of `cards` per local day, oldest first.
+pub fn days(cards: &[Card]) -> Vec<(NaiveDate, u32)> {
+    let mut days: Vec<(NaiveDate, u32)> = Vec::new();
+    for review in cards.iter().flat_map(|card| &card.reviews) {
+        let Some(at) = DateTime::from_timestamp(review.at as i64, 0) else {
+            continue;
+        };
+        let day = at.with_timezone(&Local).date_naive();
+        match days.binary_search_by_key(&day, |&(d, _)| d) {
+            Ok(index) => days[index].1 += 1,
+            Err(index) => days.insert(index, (day, 1)),
+        }
+    }
+    days
+}
+
+/// The ease factor of cards without an FSRS difficulty, as Anki starts
+/// new cards with.
+const INITIAL_FACTOR: i64 = 2500;
+/// The ease factors FSRS difficulties 1 and 10 are mapped to.
+const EASIEST_FACTOR: i64 = 3000;
+const HARDEST_FACTOR: i64 = 1300;
+
+/// An item to export with what the progress store knows about it.
+#[derive(Debug, Clone)]
+pub struct ExportCard {
+    pub item: StudyItem,
+    pub schedule: Option<ItemSchedule>,
+    pub stats: Option<ItemStats>,
+}
+
+/// A card's scheduling columns in Anki's `cards` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheduling {
+    /// 0 for a new card and 2 for a review card, in both `type` and
+    /// `queue`.
+    pub kind: i64,
+    /// A new card's position, or the day a review card is due, counted
+    /// from the day the collection was created.
+    pub due: i64,
+    /// The interval in days.
+    pub ivl: i64,
+    /// The ease factor in permille.
+    pub factor: i64,
+    /// The FSRS memory state, which Anki 23.10 and later keep in the
+    /// card's `data`.
+    pub data: String,
+}
+
+impl Scheduling {
+    fn new(position: i64) -> Self {
+        Self {
+            kind: 0,
+            due: position,
+            ivl: 0,
+            factor: 0,
+            data: "{}".to_string(),
+        }
+    }
+}
+
+/// Maps what ljp knows about a card to Anki's columns, with days counted
+/// from `created`:
+///
+/// - an FSRS memory keeps its due date, its stability as the interval and
+///   its difficulty as the FSRS state, and as an ease factor from 300%
+///   for difficulty 1 down to 130% for 10;
+/// - a reviewed card in Leitner box n gets an interval of 2^(n-1) days
+///   from its last review;
+/// - anything else, including cards scheduled only by weight, is new, at
+///   `position` in the new queue.
+pub fn scheduling(card: &ExportCard, created: NaiveDate, position: i64) -> Scheduling {
+    let day = |date: NaiveDate| (date - created).num_days();
+    let Some(schedule) = &card.schedule else {
+        return Scheduling::new(position);
+    };
+    if let (Some(stability), Some(difficulty), Some(due)) =
+        (schedule.interval, schedule.ease, schedule.due)
+    {
+        let span = (EASIEST_FACTOR - HARDEST_FACTOR) as f64;
+        return Scheduling {
+            kind: 2,
+            due: day(due.with_timezone(&Local).date_naive()),
+            ivl: (stability.round() as i64).max(1),
+            factor: EASIEST_FACTOR - ((difficulty - 1.0) / 9.0 * span).round() as i64,
+            data: json!({ "s": stability, "d": difficulty }).to_string(),
+        };
+    }
+    let reviewed = card.stats.as_ref().is_some_and(|stats| stats.reviews > 0);
+    match schedule.leitner_box {
+        Some(b) if reviewed => {
+            let ivl = 1i64 << (b.max(1) - 1);
+            let last = card
+                .stats
+                .as_ref()
+                .and_then(|stats| stats.last_review)
+                .map(|at| at.and_utc().with_timezone(&Local).date_naive())
+                .unwrap_or(created);
+            Scheduling {
+                kind: 2,
+                due: day(last + Days::new(ivl as u64)),
+                ivl,
+                factor: INITIAL_FACTOR,
+                data: "{}".to_string(),
+            }
+        }
+        _ => Scheduling::new(position),
+    }
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE col (id INTEGER PRIMARY KEY, crt INTEGER NOT NULL, mod INTEGER NOT NULL,
+        scm INTEGER NOT NULL, ver INTEGER NOT NULL, dty INTEGER NOT NULL, usn INTEGER NOT NULL,
+        ls INTEGER NOT NULL, conf TEXT NOT NULL, models TEXT NOT NULL, decks TEXT NOT NULL,
+        dconf TEXT NOT NULL, tags TEXT NOT NULL);
+    CREATE TABLE notes (id INTEGER PRIMARY KEY, guid TEXT NOT NULL, mid INTEGER NOT NULL,
+        mod INTEGER NOT NULL, usn INTEGER NOT NULL, tags TEXT NOT NULL, flds TEXT NOT NULL,
+        sfld TEXT NOT NULL, csum INTEGER NOT NULL, flags INTEGER NOT NULL, data TEXT NOT NULL);
+    CREATE TABLE cards (id INTEGER PRIMARY KEY, nid INTEGER NOT NULL, did INTEGER NOT NULL,
+        ord INTEGER NOT NULL, mod INTEGER NOT NULL, usn INTEGER NOT NULL, type INTEGER NOT NULL,
+        queue INTEGER NOT NULL, due INTEGER NOT NULL, ivl INTEGER NOT NULL,
+        factor INTEGER NOT NULL, reps INTEGER NOT NULL, lapses INTEGER NOT NULL,
+        left INTEGER NOT NULL, odue INTEGER NOT NULL, odid INTEGER NOT NULL,
+        flags INTEGER NOT NULL, data TEXT NOT NULL);
+    CREATE TABLE revlog (id INTEGER PRIMARY KEY, cid INTEGER NOT NULL, usn INTEGER NOT NULL,
+        ease INTEGER NOT NULL, ivl INTEGER NOT NULL, lastIvl INTEGER NOT NULL,
+        factor INTEGER NOT NULL, time INTEGER NOT NULL, type INTEGER NOT NULL);
+    CREATE TABLE graves (usn INTEGER NOT NULL, oid INTEGER NOT NULL, type INTEGER NOT NULL);";
+
+/// The collection's notetypes, decks and deck options, as Anki's schema
+/// 11 keeps them in `col`.
+fn collection_json(deck: &str, model: i64, deck_id: i64, next_position: i64) -> [String; 4] {
+    let conf = json!({
+        "nextPos": next_position, "estTimes": true, "activeDecks": [deck_id],
+        "sortType": "noteFld", "timeLim": 0, "sortBackwards": false, "addToCur": true,
+        "curDeck": deck_id, "newSpread": 0, "dueCounts": true, "curModel": model,
+        "collapseTime": 1200,
+    });
+    let field = |name: &str, ord: i64| json!({ "name": name, "ord": ord, "sticky": false, "rtl": false, "font": "Arial", "size": 20 });
+    let models = json!({ model.to_string(): {
+        "id": model, "name": "Basic (ljp)", "type": 0, "mod": 0, "usn": 0, "sortf": 0,
+        "did": deck_id, "tags": [], "vers": [],
+        "flds": [field("Front", 0), field("Back", 1)],
+        "tmpls": [{
+            "name": "Card 1", "ord": 0, "qfmt": "{{Front}}",
+            "afmt": "{{FrontSide}}<hr id=answer>{{Back}}", "bqfmt": "", "bafmt": "",
+            "did": null,
+        }],
+        "css": ".card { font-family: arial; font-size: 20px; text-align: center; }",
+        "latexPre": "\\documentclass[12pt]{article}\n\\begin{document}\n",
+        "latexPost": "\\end{document}", "latexsvg": false,
+        "req": [[0, "any", [0]]],
+    }});
+    let deck_json = |id: i64, name: &str| {
+        json!({
+            "id": id, "name": name, "mod": 0, "usn": 0, "desc": "", "dyn": 0, "conf": 1,
+            "collapsed": false, "browserCollapsed": false, "extendNew": 0, "extendRev": 0,
+            "newToday": [0, 0], "revToday": [0, 0], "lrnToday": [0, 0], "timeToday": [0, 0],
+        })
+    };
+    let decks = json!({
+        "1": deck_json(1, "Default"),
+        deck_id.to_string(): deck_json(deck_id, deck),
+    });
+    let dconf = json!({ "1": {
+        "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60, "autoplay": true,
+        "timer": 0, "replayq": true, "dyn": false,
+        "new": { "delays": [1.0, 10.0], "ints": [1, 4, 0], "initialFactor": INITIAL_FACTOR,
+            "order": 1, "perDay": 20, "bury": false },
+        "rev": { "perDay": 200, "ease4": 1.3, "ivlFct": 1.0, "maxIvl": 36500, "bury": false,
+            "hardFactor": 1.2 },
+        "lapse": { "delays": [10.0], "mult": 0.0, "minInt": 1, "leechFails": 8,
+            "leechAction": 1 },
+    }});
+    [conf, models, decks, dconf].map(|value| value.to_string())
+}
+
+/// Anki's tag column: space-separated with a space on each end, so tags
+/// cannot have spaces of their own.
+fn tags(tags: &[String]) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+    let tags: Vec<String> = tags.iter().map(|tag| tag.replace(' ', "_")).collect();
+    format!(" {} ", tags.join(" "))
+}
+
+/// Writes `cards` into a new Anki collection at `path`, in a deck called
+/// `deck`, created at `now`.
+fn write_collection(
+    path: &Path,
+    deck: &str,
+    cards: &[ExportCard],
+    now: DateTime<Local>,
+) -> Result<()> {
+    let conn = Connection::open(path)
+        .with_context(|| format!("Failed to create Anki collection {}", path.display()))?;
+    conn.execute_batch(SCHEMA)
+        .context("Failed to create Anki collection")?;
+    let created = now.date_naive();
+    let crt = created
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map_or(now.timestamp(), |midnight| midnight.timestamp());
+    let ms = now.timestamp_millis();
+    let (model, deck_id) = (ms, ms + 1);
+
+    // New cards are queued heaviest first.
+    let mut order: Vec<usize> = (0..cards.len()).collect();
+    order.sort_by_key(|&i| {
+        std::cmp::Reverse(
+            cards[i]
+                .schedule
+                .as_ref()
+                .and_then(|schedule| schedule.weight),
+        )
+    });
+    let mut positions = vec![0; cards.len()];
+    for (position, &i) in order.iter().enumerate() {
+        positions[i] = position as i64 + 1;
+    }
+
+    let [conf, models, decks, dconf] =
+        collection_json(deck, model, deck_id, cards.len() as i64 + 1);
+    conn.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?2, 11, 0, 0, 0, ?3, ?4, ?5, ?6, '{}')",
+        params![crt, ms, conf, models, decks, dconf],
+    )
+    .context("Failed to write Anki collection")?;
+    for (i, card) in cards.iter().enumerate() {
+        let id = ms + 2 + i as i64;
+        let item = &card.item;
+        let fields = format!(
+            "{}{}{}",
+            escape_html(&item.front),
+            FIELD_SEPARATOR,
+            escape_html(&item.back)
+        );
+        conn.execute(
+            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7, 0, 0, '')",
+            params![
+                id,
+                format!("ljp-{}-{}", id, i),
+                model,
+                now.timestamp(),
+                tags(&item.tags),
+                fields,
+                item.front,
+            ],
+        )
+        .context("Failed to write Anki notes")?;
+        let sched = scheduling(card, created, positions[i]);
+        let (reps, lapses) = card
+            .stats
+            .as_ref()
+            .map_or((0, 0), |stats| (stats.reviews, stats.lapses));
+        conn.execute(
+            "INSERT INTO cards VALUES
+                 (?1, ?1, ?2, 0, ?3, 0, ?4, ?4, ?5, ?6, ?7, ?8, ?9, 0, 0, 0, 0, ?10)",
+            params![
+                id,
+                deck_id,
+                now.timestamp(),
+                sched.kind,
+                sched.due,
+                sched.ivl,
+                sched.factor,
+                reps,
+                lapses,
+                sched.data,
+            ],
+        )
+        .context("Failed to write Anki cards")?;
+    }
+    Ok(())
+}
+
+/// Writes `cards` to `path` as an Anki package (`.apkg`) with a deck
+/// called `deck`, keeping their schedules as [`scheduling`] describes.
+pub fn export(path: &Path, deck: &str, cards: &[ExportCard]) -> Result<()> {
+    let collection = std::env::temp_dir().join(format!(
+        "ljp-export-{}-{}.anki2",
+        std::process::id(),
+        Utc::now().timestamp_millis()
+    ));
+    let written = write_collection(&collection, deck, cards, Local::now())
+        .and_then(|()| fs::read(&collection).context("Failed to read the Anki collection"));
+    let _ = fs::remove_file(&collection);
+    let collection = written?;
+
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let package = |mut zip: ZipWriter<File>| -> Result<()> {
+        zip.start_file("collection.anki2", SimpleFileOptions::default())?;
+        zip.write_all(&collection)?;
+        zip.start_file("media", SimpleFileOptions::default())?;
+        zip.write_all(b"{}")?;
+        zip.finish()?;
+        Ok(())
+    };
+    package(ZipWriter::new(file)).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Two notes as Anki stores them, one reviewed three times.
+    fn collection(path: &Path) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, flds TEXT NOT NULL);
+             CREATE TABLE cards (id INTEGER PRIMARY KEY, nid INTEGER NOT NULL, ord INTEGER NOT NULL);
+             CREATE TABLE revlog (id INTEGER PRIMARY KEY, cid INTEGER NOT NULL, ease INTEGER NOT NULL);
+             INSERT INTO notes VALUES (1, '<b>犬</b>' || char(31) || 'いぬ&nbsp;(dog)');
+             INSERT INTO notes VALUES (2, '猫' || char(31) || 'ねこ');
+             INSERT INTO cards VALUES (10, 1, 0), (11, 1, 1), (20, 2, 0);
+             INSERT INTO revlog VALUES
+                 (1700000000000, 10, 3),
+                 (1700086400000, 10, 1),
+                 (1700090000000, 10, 0),
+                 (1700172800000, 10, 4),
+                 (1700172900000, 11, 3);",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reads_the_first_card_of_each_note_with_its_reviews() {
+        let path = std::env::temp_dir().join(format!("ljp-anki-{}.anki2", std::process::id()));
+        let _ = fs::remove_file(&path);
+        collection(&path);
+        let cards = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(cards.len(), 2);
+        assert_eq!(
+            (cards[0].front.as_str(), cards[0].back.as_str()),
+            ("犬", "いぬ (dog)")
+        );
+        let grades: Vec<Grade> = cards[0].reviews.iter().map(|r| r.grade).collect();
+        assert_eq!(grades, [Grade::Good, Grade::Again, Grade::Easy]);
+        assert!(cards[1].reviews.is_empty());
+        assert_eq!(deck(&cards), "犬,いぬ (dog)\n猫,ねこ\n");
+    }
+
+    #[test]
+    fn reviews_are_replayed_into_progress() {
+        let card = Card {
+            front: "犬".to_string(),
+            back: "いぬ".to_string(),
+            reviews: vec![
+                Review {
+                    at: 1_700_000_000,
+                    grade: Grade::Good,
+                },
+                Review {
+                    at: 1_700_086_400,
+                    grade: Grade::Again,
+                },
+                Review {
+                    at: 1_700_172_800,
+                    grade: Grade::Easy,
+                },
+            ],
+        };
+        let progress = card.progress();
+        assert_eq!((progress.reviews, progress.lapses), (3, 1));
+        assert_eq!(progress.leitner_box, 3);
+        assert_eq!(progress.memory.unwrap().last_review, 1_700_172_800);
+        assert_eq!(
+            progress.last_review.unwrap().and_utc().timestamp(),
+            1_700_172_800
+        );
+        assert_eq!(days(&[card]).len(), 3);
+    }
+
+    #[test]
+    fn exports_keep_each_schedulers_state() {
+        let now = Local::now();
+        let created = now.date_naive();
+        let reviewed = |reviews| ItemStats {
+            reviews,
+            lapses: 1,
+            last_review: Some(now.naive_utc()),
+        };
+        let card = |front: &str, schedule: ItemSchedule, stats| ExportCard {
+            item: StudyItem::new(front, "back"),
+            schedule: Some(schedule),
+            stats,
+        };
+        let cards = [
+            card(
+                "<fsrs>",
+                ItemSchedule {
+                    ease: Some(5.5),
+                    interval: Some(6.4),
+                    due: Some((now + chrono::TimeDelta::days(3)).with_timezone(&Utc)),
+                    ..ItemSchedule::default()
+                },
+                Some(reviewed(4)),
+            ),
+            card(
+                "leitner",
+                ItemSchedule {
+                    leitner_box: Some(3),
+                    ..ItemSchedule::default()
+                },
+                Some(reviewed(2)),
+            ),
+            card(
+                "light",
+                ItemSchedule {
+                    weight: Some(1),
+                    ..ItemSchedule::default()
+                },
+                None,
+            ),
+            card(
+                "heavy",
+                ItemSchedule {
+                    weight: Some(9),
+                    ..ItemSchedule::default()
+                },
+                None,
+            ),
+        ];
+
+        let fsrs = scheduling(&cards[0], created, 1);
+        assert_eq!((fsrs.kind, fsrs.due, fsrs.ivl), (2, 3, 6));
+        assert_eq!(fsrs.factor, 2150);
+        assert_eq!(fsrs.data, r#"{"d":5.5,"s":6.4}"#);
+        let leitner = scheduling(&cards[1], created, 2);
+        assert_eq!((leitner.kind, leitner.due, leitner.ivl), (2, 4, 4));
+
+        let path =
+            std::env::temp_dir().join(format!("ljp-export-test-{}.anki2", std::process::id()));
+        let _ = fs::remove_file(&path);
+        write_collection(&path, "ljp", &cards, now).unwrap();
+        let read_back = read(&path).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        let mut stmt = conn
+            .prepare("SELECT type, queue, due, reps FROM cards ORDER BY id")
+            .unwrap();
+        let columns: Vec<(i64, i64, i64, i64)> = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        drop(stmt);
+        drop(conn);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_back[0].front, "<fsrs>");
+        assert_eq!(read_back.len(), 4);
+        assert_eq!(columns[0], (2, 2, 3, 4));
+        assert_eq!(columns[1], (2, 2, 4, 2));
+        // New cards are queued heaviest first.
+        assert_eq!(columns[2], (0, 0, 2, 0));
+        assert_eq!(columns[3], (0, 0, 1, 0));
+    }
+
+    #[test]
+    fn html_is_reduced_to_its_text() {
+        assert_eq!(plain_text("a<br>b &amp; <i>c</i>"), "a b & c");
+        assert_eq!(plain_text("1 < 2"), "1 < 2");
+    }
+}
@@ -0,0 +1,272 @@
+//! Audio bundles: recordings a deck ships with, so `--mode listen` works
+//! offline. A deck names its bundle with `# audio: …`, a directory or zip
+//! relative to the deck. Clips sit at the top of the bundle, named after
+//! the item's id if its script gives one, or else its front, with one of
+//! the [`EXTENSIONS`], e.g. `食べる.mp3`.
+//!
+//! Bundles are indexed when the deck loads, and each item with a clip gets
+//! it as media: the clip's path, or `bundle.zip#clip` for a zip. Clips in
+//! a zip are extracted under `audio/bundles` in the data directory when
+//! first played.
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+use crate::{
+    paths,
+    speech::{Player, Speaker},
+    StudyItem,
+};
+
+/// File extensions recognised as clips.
+pub const EXTENSIONS: [&str; 5] = ["mp3", "ogg", "opus", "wav", "m4a"];
+
+/// The clips in a bundle, by the id or front they are for.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    path: PathBuf,
+    zip: bool,
+    clips: HashMap<String, String>,
+}
+
+impl Bundle {
+    /// Indexes the clips in the directory or zip at `path`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let zip = !path.is_dir();
+        let names: Vec<String> = if zip {
+            let file = File::open(path)
+                .with_context(|| format!("Failed to open audio bundle {}", path.display()))?;
+            ZipArchive::new(file)
+                .with_context(|| format!("{} is not an audio bundle", path.display()))?
+                .file_names()
+                .map(str::to_string)
+                .collect()
+        } else {
+            fs::read_dir(path)
+                .with_context(|| format!("Failed to read audio bundle {}", path.display()))?
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .collect()
+        };
+        let clips = names
+            .into_iter()
+            .filter(|name| !name.contains('/'))
+            .filter_map(|name| {
+                let (key, extension) = name.rsplit_once('.')?;
+                is_clip(extension).then(|| (key.to_string(), name.clone()))
+            })
+            .collect();
+        Ok(Self {
+            path: path.to_path_buf(),
+            zip,
+            clips,
+        })
+    }
+
+    /// The media reference of `item`'s clip, if the bundle has one.
+    pub fn clip(&self, item: &StudyItem) -> Option<String> {
+        let key = if item.id.is_empty() {
+            &item.front
+        } else {
+            &item.id
+        };
+        let name = self.clips.get(key)?;
+        Some(match self.zip {
+            true => format!("{}#{}", self.path.display(), name),
+            false => self.path.join(name).display().to_string(),
+        })
+    }
+
+    /// Gives each of `items` its clip as media, returning the items the
+    /// bundle has no clip for.
+    pub fn attach<'a>(&self, items: &'a mut [StudyItem]) -> Vec<&'a StudyItem> {
+        let mut missing = Vec::new();
+        for item in items.iter_mut() {
+            match self.clip(item) {
+                Some(clip) if !item.media.contains(&clip) => item.media.push(clip),
+                Some(_) => {}
+                None => missing.push(&*item),
+            }
+        }
+        missing
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn is_clip(extension: &str) -> bool {
+    EXTENSIONS
+        .iter()
+        .any(|clip| clip.eq_ignore_ascii_case(extension))
+}
+
+/// Where clips from zips are extracted to.
+pub fn cache_dir() -> Option<PathBuf> {
+    paths::data_dir().map(|dir| dir.join("audio").join("bundles"))
+}
+
+/// The first of `item`'s media that is a clip, as a file that can be
+/// played. Clips in zips are extracted under `cache`.
+pub fn clip_file(item: &StudyItem, cache: &Path) -> Result<Option<PathBuf>> {
+    for media in &item.media {
+        let is_audio = Path::new(media)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(is_clip);
+        if !is_audio {
+            continue;
+        }
+        if let Some((zip, name)) = media.rsplit_once('#') {
+            if Path::new(zip).is_file() {
+                return extract(Path::new(zip), name, cache).map(Some);
+            }
+        }
+        if Path::new(media).is_file() {
+            return Ok(Some(PathBuf::from(media)));
+        }
+    }
+    Ok(None)
+}
+
+/// Extracts clip `name` from the bundle at `zip` under `cache`, unless it
+/// was since the bundle last changed.
+fn extract(zip: &Path, name: &str, cache: &Path) -> Result<PathBuf> {
+    let dir = cache.join(zip.file_stem().unwrap_or_default());
+    let dest = dir.join(name);
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    if modified(&dest).is_some() && modified(&dest) >= modified(zip) {
+        return Ok(dest);
+    }
+    let file = File::open(zip)
+        .with_context(|| format!("Failed to open audio bundle {}", zip.display()))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("{} is not an audio bundle", zip.display()))?;
+    let mut entry = archive
+        .by_name(name)
+        .with_context(|| format!("{} has no clip {}", zip.display(), name))?;
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut out =
+        File::create(&dest).with_context(|| format!("Failed to write {}", dest.display()))?;
+    io::copy(&mut entry, &mut out)
+        .with_context(|| format!("Failed to extract {}", dest.display()))?;
+    Ok(dest)
+}
+
+/// Plays items' bundled clips, leaving the rest to another speaker.
+pub struct ClipSpeaker {
+    player: Player,
+    cache: PathBuf,
+    fallback: Option<Box<dyn Speaker>>,
+}
+
+impl ClipSpeaker {
+    /// Plays clips with `player`, extracting those in zips under `cache`.
+    pub fn new(player: Player, cache: PathBuf, fallback: Option<Box<dyn Speaker>>) -> Self {
+        Self {
+            player,
+            cache,
+            fallback,
+        }
+    }
+}
+
+impl Speaker for ClipSpeaker {
+    fn speak(&mut self, text: &str) -> Result<()> {
+        match &mut self.fallback {
+            Some(fallback) => fallback.speak(text),
+            None => anyhow::bail!(
+                "No recording of {}, and no text-to-speech program; install espeak-ng or pass --tts",
+                text
+            ),
+        }
+    }
+
+    fn speak_item(&mut self, item: &StudyItem) -> Result<()> {
+        match clip_file(item, &self.cache) {
+            Ok(Some(path)) => return self.player.play(&path),
+            Ok(None) => {}
+            Err(e) if self.fallback.is_some() => eprintln!("Warning: {:#}", e),
+            Err(e) => return Err(e),
+        }
+        match &mut self.fallback {
+            Some(fallback) => fallback.speak_item(item),
+            None => self.speak(&item.front),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn bundle_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ljp-audio-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn clips_are_found_by_id_then_front() {
+        let dir = bundle_dir("dir");
+        for name in ["食べる.mp3", "verb-2.ogg", "notes.txt"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let bundle = Bundle::open(&dir).unwrap();
+        let mut items = vec![
+            StudyItem::new("食べる", "たべる"),
+            StudyItem {
+                id: "verb-2".to_string(),
+                ..StudyItem::new("飲む", "のむ")
+            },
+            StudyItem::new("notes", "x"),
+        ];
+
+        let missing: Vec<String> = bundle
+            .attach(&mut items)
+            .into_iter()
+            .map(|item| item.front.clone())
+            .collect();
+        assert_eq!(missing, ["notes"]);
+        assert_eq!(
+            items[0].media,
+            [dir.join("食べる.mp3").display().to_string()]
+        );
+        assert_eq!(
+            clip_file(&items[1], &dir).unwrap(),
+            Some(dir.join("verb-2.ogg"))
+        );
+        // Attaching again adds nothing.
+        bundle.attach(&mut items);
+        assert_eq!(items[0].media.len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn zipped_clips_are_extracted_to_play() {
+        let dir = bundle_dir("zip");
+        let path = dir.join("verbs.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("食べる.mp3", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"clip").unwrap();
+        zip.finish().unwrap();
+
+        let bundle = Bundle::open(&path).unwrap();
+        let mut items = vec![StudyItem::new("食べる", "たべる")];
+        assert!(bundle.attach(&mut items).is_empty());
+        assert_eq!(items[0].media, [format!("{}#食べる.mp3", path.display())]);
+        let extracted = clip_file(&items[0], &dir.join("cache")).unwrap().unwrap();
+        assert_eq!(extracted, dir.join("cache/verbs/食べる.mp3"));
+        assert_eq!(fs::read_to_string(&extracted).unwrap(), "clip");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+//! `ljp backup` and `ljp restore`: zip archives of the config file and the
+//! data directory, i.e. decks, plugins, profiles, progress and history.
+//!
+//! An archive holds `config.toml` and the data directory under `data/`.
+//! Archives are kept in the `backups` directory under the data directory,
+//! which is itself left out of them.
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::{
+    fs::{self, File},
+    io,
+    path::{Component, Path, PathBuf},
+};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::{config, paths};
+
+/// The archive entry the config file is kept as.
+const CONFIG_ENTRY: &str = "config.toml";
+/// The archive directory the data directory is kept under.
+const DATA_ENTRY: &str = "data";
+/// The directory under the data directory that archives are kept in.
+const BACKUPS: &str = "backups";
+
+/// Where the data directory and config file are.
+#[derive(Debug, Clone)]
+pub struct Locations {
+    pub data: PathBuf,
+    pub config: Option<PathBuf>,
+}
+
+impl Locations {
+    /// The data directory and config file in use.
+    pub fn current() -> Result<Self> {
+        Ok(Self {
+            data: paths::data_dir().context("Could not determine the data directory")?,
+            config: config::default_path(),
+        })
+    }
+
+    /// Where archives are kept.
+    pub fn backups_dir(&self) -> PathBuf {
+        self.data.join(BACKUPS)
+    }
+
+    /// A new timestamped archive path in the backups directory, with
+    /// `label` saying why it was made, if not by hand.
+    pub fn archive_path(&self, label: Option<&str>) -> PathBuf {
+        let stamp = Local::now().format("%Y%m%d-%H%M%S");
+        let name = match label {
+            Some(label) => format!("ljp-{}-{}.zip", stamp, label),
+            None => format!("ljp-{}.zip", stamp),
+        };
+        self.backups_dir().join(name)
+    }
+}
+
+/// Writes an archive of `locations` to `dest`, returning how many files it
+/// holds.
+pub fn create(locations: &Locations, dest: &Path) -> Result<usize> {
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let file =
+        File::create(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let mut files = 0;
+
+    if let Some(config) = locations.config.as_deref().filter(|path| path.is_file()) {
+        add(&mut zip, config, CONFIG_ENTRY)?;
+        files += 1;
+    }
+    let mut pending = vec![locations.data.clone()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut entries: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        entries.sort();
+        for path in entries {
+            if path == locations.backups_dir() || path == dest {
+                continue;
+            }
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.is_file() {
+                let relative = path.strip_prefix(&locations.data)?;
+                let name = Path::new(DATA_ENTRY).join(relative);
+                add(&mut zip, &path, &entry_name(&name))?;
+                files += 1;
+            }
+        }
+    }
+
+    zip.finish()
+        .with_context(|| format!("Failed to write {}", dest.display()))?;
+    Ok(files)
+}
+
+/// Archive entries use `/` whatever the platform.
+fn entry_name(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn add(zip: &mut ZipWriter<File>, path: &Path, name: &str) -> Result<()> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    zip.start_file(name, SimpleFileOptions::default())
+        .context("Failed to write backup")?;
+    io::copy(&mut file, zip).with_context(|| format!("Failed to back up {}", path.display()))?;
+    Ok(())
+}
+
+/// Where archive entry `name` is restored to, or `None` for an entry that
+/// does not belong in an archive.
+fn destination(locations: &Locations, name: &Path) -> Option<PathBuf> {
+    if name == Path::new(CONFIG_ENTRY) {
+        return locations.config.clone();
+    }
+    let relative = name.strip_prefix(DATA_ENTRY).ok()?;
+    let safe = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    (safe && relative.components().next().is_some()).then(|| locations.data.join(relative))
+}
+
+/// Puts back the files in `archive`, overwriting the current ones, after
+/// archiving the current state in the backups directory in case the
+/// restore is regretted. Files the archive does not have are left alone.
+/// Returns the safety archive's path and how many files were restored.
+pub fn restore(locations: &Locations, archive: &Path) -> Result<(PathBuf, usize)> {
+    let file = File::open(archive)
+        .with_context(|| format!("Failed to open backup {}", archive.display()))?;
+    let mut zip = ZipArchive::new(file)
+        .with_context(|| format!("{} is not a backup archive", archive.display()))?;
+
+    // Check every entry before anything is overwritten.
+    let mut restores = Vec::new();
+    for index in 0..zip.len() {
+        let entry = zip.by_index(index).context("Failed to read backup")?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry
+            .enclosed_name()
+            .with_context(|| format!("Unsafe path {} in backup", entry.name()))?;
+        let dest = destination(locations, &name)
+            .with_context(|| format!("Unexpected file {} in backup", entry.name()))?;
+        restores.push((index, dest));
+    }
+    if restores.is_empty() {
+        anyhow::bail!("{} has nothing to restore", archive.display());
+    }
+
+    let safety = locations.archive_path(Some("pre-restore"));
+    create(locations, &safety)?;
+    for (index, dest) in &restores {
+        let mut entry = zip.by_index(*index).context("Failed to read backup")?;
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let mut out =
+            File::create(dest).with_context(|| format!("Failed to write {}", dest.display()))?;
+        io::copy(&mut entry, &mut out)
+            .with_context(|| format!("Failed to restore {}", dest.display()))?;
+    }
+    Ok((safety, restores.len()))
+}
+
+/// Backs up before the progress store at `db`, at schema `version`, is
+/// migrated. A store in the data directory gets a full archive that `ljp
+/// restore` can roll back to; one elsewhere is copied beside itself.
+pub fn before_migration(db: &Path, version: u32) -> Result<PathBuf> {
+    let label = format!("pre-migration-v{}", version);
+    let locations = Locations::current().ok();
+    if let Some(locations) = locations.filter(|locations| db.starts_with(&locations.data)) {
+        let dest = locations.archive_path(Some(&label));
+        create(&locations, &dest)?;
+        return Ok(dest);
+    }
+    let mut name = db.as_os_str().to_owned();
+    name.push(format!(".{}.bak", label));
+    let dest = PathBuf::from(name);
+    fs::copy(db, &dest).with_context(|| format!("Failed to back up {}", db.display()))?;
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locations(name: &str) -> Locations {
+        let root = std::env::temp_dir().join(format!("ljp-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("data/decks")).unwrap();
+        Locations {
+            data: root.join("data"),
+            config: Some(root.join("config.toml")),
+        }
+    }
+
+    #[test]
+    fn restoring_rolls_back_to_the_archive() {
+        let locations = locations("roundtrip");
+        let config = locations.config.clone().unwrap();
+        fs::write(&config, "data_dir = \"~/ljp\"\n").unwrap();
+        fs::write(locations.data.join("progress.db"), "old").unwrap();
+        fs::write(locations.data.join("decks/verbs.csv"), "食べる,taberu\n").unwrap();
+
+        let archive = locations.archive_path(None);
+        assert_eq!(create(&locations, &archive).unwrap(), 3);
+
+        fs::write(&config, "").unwrap();
+        fs::write(locations.data.join("progress.db"), "new").unwrap();
+        fs::remove_file(locations.data.join("decks/verbs.csv")).unwrap();
+        let (safety, restored) = restore(&locations, &archive).unwrap();
+
+        assert_eq!(restored, 3);
+        assert_eq!(
+            fs::read_to_string(&config).unwrap(),
+            "data_dir = \"~/ljp\"\n"
+        );
+        assert_eq!(
+            fs::read_to_string(locations.data.join("progress.db")).unwrap(),
+            "old"
+        );
+        assert!(locations.data.join("decks/verbs.csv").is_file());
+        // The state before the restore was kept, without older archives.
+        let safety = ZipArchive::new(File::open(&safety).unwrap()).unwrap();
+        assert_eq!(safety.len(), 2);
+        assert!(safety.file_names().all(|name| !name.contains(BACKUPS)));
+
+        fs::remove_dir_all(locations.data.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn entries_outside_the_layout_are_refused() {
+        let locations = locations("layout");
+        assert_eq!(
+            destination(&locations, Path::new("data/decks/a.csv")),
+            Some(locations.data.join("decks/a.csv"))
+        );
+        assert_eq!(destination(&locations, Path::new("data")), None);
+        assert_eq!(destination(&locations, Path::new("notes.txt")), None);
+        assert_eq!(destination(&locations, Path::new("data/../x")), None);
+        fs::remove_dir_all(locations.data.parent().unwrap()).unwrap();
+    }
+}
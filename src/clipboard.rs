@@ -0,0 +1,12 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::io::{self, Write};
+
+/// Copies `text` to the system clipboard with the OSC 52 escape sequence,
+/// which most terminals support (including over SSH) without any helper
+/// program installed.
+pub fn copy(text: &str) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush().context("Failed to flush stdout")
+}
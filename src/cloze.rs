@@ -0,0 +1,59 @@
+//! Cloze deletions: a front such as `私は{{寿司}}を食べる` is asked with the
+//! marked text blanked out, and the answer is the hidden text.
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::StudyItem;
+
+/// What a hidden part of the sentence is shown as, unless the marker has a
+/// hint of its own as in `{{寿司::food}}`.
+const BLANK: &str = "[...]";
+
+fn marker() -> &'static Regex {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    MARKER.get_or_init(|| Regex::new(r"\{\{(.+?)(?:::(.+?))?\}\}").expect("valid cloze regex"))
+}
+
+/// Expands `item` into one card per `{{…}}` marker in its front, each with
+/// that marker blanked and the others filled in. A non-empty back becomes
+/// the card's prompt, e.g. a translation of the sentence. Items without
+/// markers are returned unchanged.
+pub fn expand(item: StudyItem) -> Vec<StudyItem> {
+    let markers: Vec<_> = marker().captures_iter(&item.front).collect();
+    if markers.is_empty() {
+        return vec![item];
+    }
+
+    let prompt = if item.back.is_empty() {
+        item.prompt.clone()
+    } else {
+        Some(item.back.clone())
+    };
+    (0..markers.len())
+        .map(|hidden| {
+            let mut front = String::new();
+            let mut last = 0;
+            for (i, captures) in markers.iter().enumerate() {
+                let whole = captures.get(0).expect("match has a group 0");
+                front.push_str(&item.front[last..whole.start()]);
+                if i == hidden {
+                    match captures.get(2) {
+                        Some(hint) => front.push_str(&format!("[{}]", hint.as_str())),
+                        None => front.push_str(BLANK),
+                    }
+                } else {
+                    front.push_str(&captures[1]);
+                }
+                last = whole.end();
+            }
+            front.push_str(&item.front[last..]);
+
+            StudyItem {
+                front,
+                back: markers[hidden][1].to_string(),
+                prompt: prompt.clone(),
+                ..item.clone()
+            }
+        })
+        .collect()
+}
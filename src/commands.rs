@@ -0,0 +1,311 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use regex::Regex;
+use std::{cell::Cell, rc::Rc};
+
+use crate::{
+    dictionary,
+    engine::{Render, SessionEngine},
+    explain,
+    schedule::{self, Column},
+    StudyItem,
+};
+
+/// How many of this session's slowest items `\w` lists.
+const SLOWEST_SHOWN: usize = 5;
+/// How many dictionary entries `\d` shows.
+const ENTRIES_SHOWN: usize = 3;
+
+/// Describes `item` for `\i`: the set it comes from, its weight or box,
+/// how often it was missed this session, its mnemonic, tags, note and
+/// where its kana come from.
+pub fn item_info(engine: &SessionEngine, item: &StudyItem) -> Vec<String> {
+    let mut lines = vec![format!("{} / {}", item.front, item.back)];
+    if !item.set.is_empty() {
+        lines.push(format!("Set: {}", item.set));
+    }
+    let session = engine.session();
+    if let Some(index) = session
+        .items()
+        .iter()
+        .position(|other| other.front == item.front && other.back == item.back)
+    {
+        match session.boxes() {
+            Some(boxes) => lines.push(format!("Box: {}", boxes[index])),
+            None => lines.push(format!("Weight: {}", session.weights()[index])),
+        }
+        if let Some(memory) = session.memory(index) {
+            let due = DateTime::from_timestamp(memory.due() as i64, 0)
+                .map(|due| {
+                    due.with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            lines.push(format!(
+                "Ease: {:.1}, interval: {:.1} days, due {}",
+                memory.difficulty, memory.stability, due
+            ));
+        }
+    }
+    let misses = engine
+        .summary()
+        .missed
+        .iter()
+        .filter(|(missed, _)| missed.front == item.front && missed.back == item.back)
+        .count();
+    lines.push(format!("Missed this session: {}", misses));
+    if !item.alternates.is_empty() {
+        lines.push(format!("Also accepted: {}", item.alternates.join(", ")));
+    }
+    if let Some(mnemonic) = &item.mnemonic {
+        lines.push(format!("Mnemonic: {}", mnemonic));
+    }
+    if !item.tags.is_empty() {
+        lines.push(format!("Tags: {}", item.tags.join(", ")));
+    }
+    if let Some(note) = &item.note {
+        lines.push(format!("Note: {}", note));
+    }
+    if !item.media.is_empty() {
+        lines.push(format!("Media: {}", item.media.join(", ")));
+    }
+    lines.extend(explain::explain(&item.front));
+    lines
+}
+
+/// Runs a backslash command against the engine. The second argument is
+/// whatever followed the command name on the line, trimmed.
+pub type Handler = Rc<dyn Fn(&mut SessionEngine, &str) -> Result<Vec<Render>>>;
+
+struct CommandEntry {
+    name: String,
+    help: String,
+    handler: Handler,
+}
+
+/// The backslash commands available in a session. Modes and plugins add
+/// their own with `register`, and `\h` lists whatever is registered.
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: Vec<CommandEntry>,
+}
+
+impl CommandRegistry {
+    /// Returns a registry holding the commands every session supports.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::default();
+
+        registry.register("h", "Show this help message", |engine, _| {
+            Ok(vec![Render::Help(engine.commands().help())])
+        });
+        registry.register(
+            "w",
+            "Show each item's weight and schedule, sorted by a column if given",
+            |engine, args| {
+                let mut rows = engine.schedule();
+                if !args.is_empty() {
+                    match Column::from_str(args, true) {
+                        Ok(column) => schedule::sort(&mut rows, column),
+                        Err(_) => {
+                            return Ok(vec![Render::Notice(format!(
+                                "Cannot sort by '{}'; try one of {}.",
+                                args,
+                                Column::names().join(", ")
+                            ))])
+                        }
+                    }
+                }
+                let mut renders = vec![Render::Schedule(rows)];
+                let slowest: Vec<String> = engine
+                    .summary()
+                    .slowest(SLOWEST_SHOWN)
+                    .iter()
+                    .map(|(item, time)| format!("{} {:.1}s", item.front, time.as_secs_f64()))
+                    .collect();
+                if !slowest.is_empty() {
+                    renders.push(Render::Notice(format!("Slowest: {}", slowest.join(", "))));
+                }
+                Ok(renders)
+            },
+        );
+        registry.register(
+            "x",
+            "Explain where the current item comes from",
+            |engine, _| {
+                let Some(item) = engine.current() else {
+                    return Ok(Vec::new());
+                };
+                let lines = explain::explain(&item.front);
+                let notice = if lines.is_empty() {
+                    format!("No extra information for {}.", item.front)
+                } else {
+                    lines.join("\n")
+                };
+                Ok(vec![Render::Notice(notice)])
+            },
+        );
+        registry.register(
+            "s",
+            "Skip the current item without counting it wrong",
+            |engine, _| engine.skip(),
+        );
+        registry.register(
+            "a",
+            "Show the answer and count the item as missed",
+            |engine, _| engine.reveal(),
+        );
+        registry.register("u", "Undo the previous answer", |engine, _| engine.undo());
+        registry.register(
+            "i",
+            "Show what is known about the current item",
+            |engine, _| {
+                let Some(item) = engine.current().cloned() else {
+                    return Ok(Vec::new());
+                };
+                Ok(vec![Render::Notice(item_info(engine, &item).join("\n"))])
+            },
+        );
+        // The reset asked for, and how many answers had been given then;
+        // it is only done if asked for again before the next answer.
+        let pending: Cell<Option<(String, usize)>> = Cell::new(None);
+        registry.register(
+            "r",
+            "Reset weights, of items matching a regex if given",
+            move |engine, args| {
+                let pattern = match args {
+                    "" => None,
+                    _ => match Regex::new(args) {
+                        Ok(pattern) => Some(pattern),
+                        Err(e) => {
+                            return Ok(vec![Render::Notice(format!(
+                                "'{}' is not a valid regex: {}",
+                                args, e
+                            ))])
+                        }
+                    },
+                };
+                let matches = |item: &StudyItem| {
+                    pattern.as_ref().is_none_or(|pattern| {
+                        pattern.is_match(&item.front) || pattern.is_match(&item.back)
+                    })
+                };
+                let asked = (args.to_string(), engine.summary().answered);
+                if pending.take().as_ref() != Some(&asked) {
+                    let count = engine
+                        .session()
+                        .items()
+                        .iter()
+                        .filter(|item| matches(item))
+                        .count();
+                    pending.set(Some(asked));
+                    let command = format!("\\r {}", args);
+                    return Ok(vec![Render::Notice(format!(
+                        "This resets {} items. Type {} again to confirm.",
+                        count,
+                        command.trim_end()
+                    ))]);
+                }
+                let count = engine.reset(matches)?;
+                Ok(vec![Render::Notice(format!("Reset {} items.", count))])
+            },
+        );
+        registry.register(
+            "d",
+            "Look up a word, or the current item, in the dictionary",
+            |engine, args| {
+                let term = match (args, engine.current()) {
+                    ("", Some(item)) if item.front.is_ascii() => item.back.clone(),
+                    ("", Some(item)) => item.front.clone(),
+                    ("", None) => return Ok(Vec::new()),
+                    (term, _) => term.to_string(),
+                };
+                let entries = match dictionary::lookup(&term) {
+                    Ok(entries) => entries,
+                    Err(e) => return Ok(vec![Render::Notice(format!("{:#}", e))]),
+                };
+                if entries.is_empty() {
+                    return Ok(vec![Render::Notice(format!("No entries for {}.", term))]);
+                }
+                let lines: Vec<String> = entries
+                    .iter()
+                    .take(ENTRIES_SHOWN)
+                    .flat_map(|entry| {
+                        let senses = entry
+                            .senses
+                            .iter()
+                            .enumerate()
+                            .map(|(n, sense)| format!("  {}. {}", n + 1, sense));
+                        std::iter::once(entry.writings.join(", ")).chain(senses)
+                    })
+                    .collect();
+                Ok(vec![Render::Notice(lines.join("\n"))])
+            },
+        );
+        registry.register(
+            "m",
+            "Show the mnemonic for the current item",
+            |engine, _| {
+                let Some(item) = engine.current() else {
+                    return Ok(Vec::new());
+                };
+                let notice = match &item.mnemonic {
+                    Some(mnemonic) => mnemonic.clone(),
+                    None => format!("No mnemonic for {}.", item.front),
+                };
+                Ok(vec![Render::Notice(notice)])
+            },
+        );
+        registry.register("q", "Quit the study session", |engine, _| {
+            engine.finish();
+            Ok(vec![Render::Quit])
+        });
+
+        registry
+    }
+
+    /// Adds `\name`, replacing any command already registered under it.
+    pub fn register(
+        &mut self,
+        name: &str,
+        help: &str,
+        handler: impl Fn(&mut SessionEngine, &str) -> Result<Vec<Render>> + 'static,
+    ) {
+        let entry = CommandEntry {
+            name: name.to_string(),
+            help: help.to_string(),
+            handler: Rc::new(handler),
+        };
+        match self.commands.iter_mut().find(|c| c.name == name) {
+            Some(existing) => *existing = entry,
+            None => self.commands.push(entry),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Handler> {
+        self.commands
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| Rc::clone(&c.handler))
+    }
+
+    /// Returns the registered command names, without the backslash.
+    pub fn names(&self) -> Vec<String> {
+        self.commands.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Returns `(usage, description)` pairs for `\h`, in registration order.
+    pub fn help(&self) -> Vec<(String, String)> {
+        let mut lines: Vec<_> = self
+            .commands
+            .iter()
+            .map(|c| (format!("\\{}", c.name), c.help.clone()))
+            .collect();
+        lines.push((
+            "<answer>".to_string(),
+            "Enter your answer for the current item".to_string(),
+        ));
+        lines
+    }
+}
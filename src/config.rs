@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, env, fs, path::Path, path::PathBuf};
+
+use crate::{forvo::ForvoConfig, sync::SyncConfig, theme::Theme};
+
+/// Persistent defaults, read from `config.toml`. For example:
+///
+/// ```toml
+/// data_dir = "~/Sync/ljp"
+///
+/// [study]
+/// sets = "hiragana,katakana"
+/// mode = "choice"
+/// theme = "mine"
+///
+/// [themes.mine]
+/// front = "1;97"
+/// correct = "32"
+///
+/// [profiles.work]
+/// sets = "compounds"
+///
+/// [sync]
+/// backend = "dir"
+/// path = "~/Dropbox/ljp"
+///
+/// [forvo]
+/// key = "0123456789abcdef"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where progress, history, profiles and decks are kept.
+    pub data_dir: Option<PathBuf>,
+    /// Defaults for `ljp study` options, keyed by their long flag name.
+    pub study: toml::Table,
+    /// Themes usable with `--theme` alongside the built-in ones.
+    pub themes: HashMap<String, Theme>,
+    /// Per-profile overrides of `study`.
+    pub profiles: HashMap<String, toml::Table>,
+    /// Where `ljp sync` keeps the review history.
+    pub sync: Option<SyncConfig>,
+    /// Recordings from Forvo for `--mode listen`, in preference to
+    /// text-to-speech.
+    pub forvo: Option<ForvoConfig>,
+}
+
+/// Returns `$XDG_CONFIG_HOME/ljp/config.toml`, falling back to
+/// `~/.config/ljp/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("ljp").join("config.toml"))
+}
+
+impl Config {
+    /// Reads the config at `path`; a missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read config {}", path.display()))
+            }
+        };
+        toml::from_str(&data).with_context(|| format!("Invalid config {}", path.display()))
+    }
+
+    /// The `study` defaults with `profile`'s overrides applied, as
+    /// `(flag, value)` pairs ready to hand to the argument parser. Arrays
+    /// become comma-separated lists.
+    pub fn study_defaults(&self, profile: Option<&str>) -> Result<Vec<(String, String)>> {
+        let mut table = self.study.clone();
+        if let Some(overrides) = profile.and_then(|name| self.profiles.get(name)) {
+            table.extend(overrides.clone());
+        }
+        table
+            .into_iter()
+            .map(|(key, value)| {
+                let value = flag_value(&value)
+                    .with_context(|| format!("Unsupported value for '{}' in config", key))?;
+                Ok((key, value))
+            })
+            .collect()
+    }
+}
+
+fn flag_value(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(n) => Some(n.to_string()),
+        toml::Value::Float(x) => Some(x.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        toml::Value::Array(values) => values
+            .iter()
+            .map(flag_value)
+            .collect::<Option<Vec<_>>>()
+            .map(|values| values.join(",")),
+        _ => None,
+    }
+}
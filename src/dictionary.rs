@@ -0,0 +1,80 @@
+//! Word lookups in the online Jisho dictionary, fetched with `curl`.
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::process::Command;
+
+const API: &str = "https://jisho.org/api/v1/search/words";
+/// How long a lookup may take before it is given up on.
+const TIMEOUT_SECS: &str = "10";
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    data: Vec<Word>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Word {
+    japanese: Vec<Writing>,
+    senses: Vec<Sense>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Writing {
+    word: Option<String>,
+    reading: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sense {
+    english_definitions: Vec<String>,
+}
+
+/// One dictionary entry: how the word is written and read, and what it
+/// means.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Spellings with their readings, e.g. `食べる (たべる)`.
+    pub writings: Vec<String>,
+    /// One line of English definitions per sense.
+    pub senses: Vec<String>,
+}
+
+impl From<Word> for Entry {
+    fn from(word: Word) -> Self {
+        let writings = word
+            .japanese
+            .into_iter()
+            .filter_map(|writing| match (writing.word, writing.reading) {
+                (Some(word), Some(reading)) => Some(format!("{} ({})", word, reading)),
+                (Some(text), None) | (None, Some(text)) => Some(text),
+                (None, None) => None,
+            })
+            .collect();
+        let senses = word
+            .senses
+            .into_iter()
+            .map(|sense| sense.english_definitions.join("; "))
+            .collect();
+        Self { writings, senses }
+    }
+}
+
+/// Looks `term` up, which may be Japanese or English, best match first.
+pub fn lookup(term: &str) -> Result<Vec<Entry>> {
+    let output = Command::new("curl")
+        .args(["--silent", "--fail", "--get", "--max-time", TIMEOUT_SECS])
+        .arg("--data-urlencode")
+        .arg(format!("keyword={}", term))
+        .arg(API)
+        .output()
+        .context("Failed to run curl, which dictionary lookups need")?;
+    if !output.status.success() {
+        match output.status.code() {
+            Some(code) => anyhow::bail!("Dictionary lookup failed (curl error {})", code),
+            None => anyhow::bail!("Dictionary lookup was interrupted"),
+        }
+    }
+    let response: Response =
+        serde_json::from_slice(&output.stdout).context("Unexpected dictionary response")?;
+    Ok(response.data.into_iter().map(Entry::from).collect())
+}
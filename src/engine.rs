@@ -0,0 +1,1193 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+use crate::{
+    commands::CommandRegistry,
+    events::{Event, EventBus, Listener},
+    middleware::Middleware,
+    schedule::{self, Column, ItemSchedule},
+    Grade, StudyItem, StudySession,
+};
+
+#[derive(Debug, Default, Clone)]
+pub struct SessionSummary {
+    pub answered: usize,
+    pub correct: usize,
+    pub missed: Vec<(StudyItem, String)>,
+    /// Time from the first question to the end of the session.
+    pub elapsed: Duration,
+    /// How long each answer took, from showing the question to grading.
+    pub response_times: Vec<(StudyItem, Duration)>,
+}
+
+impl SessionSummary {
+    /// Returns up to `n` of the items missed this session with how often
+    /// each was missed, most missed first.
+    pub fn hardest(&self, n: usize) -> Vec<(&StudyItem, usize)> {
+        let mut counts: Vec<(&StudyItem, usize)> = Vec::new();
+        for (item, _) in &self.missed {
+            match counts
+                .iter_mut()
+                .find(|(counted, _)| counted.front == item.front)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((item, 1)),
+            }
+        }
+        counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        counts.truncate(n);
+        counts
+    }
+
+    /// Returns up to `n` of the items answered this session with their
+    /// mean response time, slowest first.
+    pub fn slowest(&self, n: usize) -> Vec<(&StudyItem, Duration)> {
+        let mut totals: Vec<(&StudyItem, Duration, u32)> = Vec::new();
+        for (item, time) in &self.response_times {
+            match totals
+                .iter_mut()
+                .find(|(timed, ..)| timed.front == item.front)
+            {
+                Some((_, total, count)) => {
+                    *total += *time;
+                    *count += 1;
+                }
+                None => totals.push((item, *time, 1)),
+            }
+        }
+        let mut means: Vec<_> = totals
+            .into_iter()
+            .map(|(item, total, count)| (item, total / count))
+            .collect();
+        means.sort_by_key(|&(_, mean)| std::cmp::Reverse(mean));
+        means.truncate(n);
+        means
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        if self.answered == 0 {
+            0.0
+        } else {
+            self.correct as f64 / self.answered as f64
+        }
+    }
+}
+
+/// Where a session stands, for frontends that show it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Progress {
+    /// The number of the current question, from 1.
+    pub index: usize,
+    /// How many questions the session has, if it ends after a set number.
+    pub total: Option<usize>,
+    /// The current item's weight.
+    pub weight: Option<u32>,
+}
+
+/// How each question is asked and answered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Mode {
+    /// Type the answer
+    #[default]
+    Typed,
+    /// Pick the answer from numbered candidates
+    Choice,
+    /// Press Enter to reveal the back, then grade yourself
+    Flashcard,
+    /// Type the answer before a countdown runs out
+    Timed,
+    /// Pair a board of fronts with their shuffled backs
+    Match,
+    /// Hear the front spoken and type it
+    Listen,
+    /// See the romaji and type the kana, with --ime on
+    Dictation,
+    /// Keep going until every item was answered right several times in a
+    /// row
+    Mastery,
+    /// Answer a fixed number of distinct items once each, without changing
+    /// any weights
+    Exam,
+}
+
+/// How many candidates a question has in `Mode::Choice`.
+pub const CHOICES: usize = 4;
+/// How many pairs a board has in `Mode::Match`.
+pub const BOARD_SIZE: usize = 8;
+
+#[derive(Debug, Clone)]
+pub enum State {
+    /// `choices` is empty unless the engine is in `Mode::Choice`.
+    AwaitingAnswer {
+        item: StudyItem,
+        choices: Vec<String>,
+    },
+    /// The back of `item` is shown and the learner grades their recall.
+    AwaitingGrade {
+        item: StudyItem,
+    },
+    /// A board of `items` is shown next to their `backs` in another order,
+    /// to be paired up.
+    AwaitingMatches {
+        items: Vec<StudyItem>,
+        backs: Vec<String>,
+    },
+    ShowingFeedback,
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    /// A line typed by the user: an answer or a backslash command.
+    Line(String),
+    /// Move on from the feedback to the next question.
+    Continue,
+    /// The time to answer ran out; counts as a miss.
+    Timeout,
+    /// End the session, e.g. because input was closed.
+    Quit,
+}
+
+/// What a driver should present in response to an input. Every batch that
+/// leaves the engine awaiting an answer ends with a `Question`, followed by
+/// its `Choices` in `Mode::Choice`; one awaiting a grade ends with a
+/// `Reveal` and one awaiting matches with a `Board`.
+#[derive(Debug, Clone)]
+pub enum Render {
+    Question(StudyItem),
+    /// Candidate answers, picked by their 1-based position.
+    Choices(Vec<String>),
+    /// The back of a flashcard, to be graded.
+    Reveal(StudyItem),
+    /// Every item missed with the answer given, at the end of an exam.
+    Misses(Vec<(StudyItem, String)>),
+    /// Fronts numbered from 1 and backs lettered from `a`, to be paired.
+    Board {
+        fronts: Vec<String>,
+        backs: Vec<String>,
+    },
+    /// Each item on a board with the back it was paired with and whether
+    /// that was right.
+    Matches(Vec<(StudyItem, String, bool)>),
+    Correct(StudyItem),
+    Incorrect(StudyItem),
+    Help(Vec<(String, String)>),
+    /// Every item's weight and scheduling state, in the order to show them.
+    Schedule(Vec<ItemSchedule>),
+    Copy {
+        label: &'static str,
+        text: String,
+    },
+    Invalid(String),
+    Notice(String),
+    NoItems,
+    Quit,
+}
+
+/// What recording an answer changed besides the weights.
+struct Recorded {
+    item: StudyItem,
+    correct: bool,
+    /// The item's run of correct answers before this one.
+    run: u32,
+    timed: bool,
+}
+
+/// The study loop as a state machine, independent of how questions are
+/// shown or answers collected. Drivers feed it `Input`s and present the
+/// `Render`s it returns.
+pub struct SessionEngine {
+    session: StudySession,
+    state: State,
+    summary: SessionSummary,
+    commands: CommandRegistry,
+    middleware: Vec<Box<dyn Middleware>>,
+    events: EventBus,
+    mode: Mode,
+    /// How many questions an exam has.
+    questions: usize,
+    /// The exam questions not asked yet.
+    exam: Vec<StudyItem>,
+    /// How many answers end the session.
+    count: Option<usize>,
+    /// How long the session may run, checked after each card.
+    time_limit: Option<Duration>,
+    /// How many correct answers in a row master an item.
+    streak: u32,
+    /// The current run of correct answers per `(front, back)`.
+    streaks: HashMap<(String, String), u32>,
+    /// Whether to report how each answer changed the weights.
+    verbose: bool,
+    /// The answers to the last question, or every pair of the last board,
+    /// for `undo`.
+    last_answers: Vec<Recorded>,
+    started_at: Option<Instant>,
+    asked_at: Option<Instant>,
+}
+
+impl SessionEngine {
+    pub fn new(session: StudySession) -> Self {
+        Self {
+            session,
+            state: State::Finished,
+            summary: SessionSummary::default(),
+            commands: CommandRegistry::with_builtins(),
+            middleware: Vec::new(),
+            events: EventBus::default(),
+            mode: Mode::default(),
+            questions: usize::MAX,
+            exam: Vec::new(),
+            count: None,
+            time_limit: None,
+            streak: 3,
+            streaks: HashMap::new(),
+            verbose: false,
+            last_answers: Vec::new(),
+            started_at: None,
+            asked_at: None,
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn session(&self) -> &StudySession {
+        &self.session
+    }
+
+    pub fn summary(&self) -> &SessionSummary {
+        &self.summary
+    }
+
+    pub fn commands(&self) -> &CommandRegistry {
+        &self.commands
+    }
+
+    pub fn commands_mut(&mut self) -> &mut CommandRegistry {
+        &mut self.commands
+    }
+
+    /// Appends `middleware` to the chain wrapping questions and answers.
+    pub fn add_middleware(&mut self, middleware: impl Middleware + 'static) {
+        self.middleware.push(Box::new(middleware));
+    }
+
+    /// Adds `listener` to those told of every answer and the session's
+    /// end.
+    pub fn subscribe(&mut self, listener: impl Listener + 'static) {
+        self.events.subscribe(listener);
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// Limits an exam to `n` questions; by default it covers every item.
+    pub fn set_questions(&mut self, n: usize) {
+        self.questions = n;
+    }
+
+    /// Ends the session once `n` cards have been answered.
+    pub fn set_count(&mut self, n: usize) {
+        self.count = Some(n);
+    }
+
+    /// Ends the session once `limit` has passed since the first question.
+    /// A card being answered when it runs out is still finished.
+    pub fn set_time_limit(&mut self, limit: Duration) {
+        self.time_limit = Some(limit);
+    }
+
+    /// Sets how many correct answers in a row master an item in
+    /// `Mode::Mastery`.
+    pub fn set_streak(&mut self, n: u32) {
+        self.streak = n.max(1);
+    }
+
+    /// Reports every weight an answer changed, after its feedback.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    fn mastered(&self, item: &StudyItem) -> bool {
+        self.streaks
+            .get(&(item.front.clone(), item.back.clone()))
+            .is_some_and(|&run| run >= self.streak)
+    }
+
+    /// Returns the item currently being asked, if any.
+    pub fn current(&self) -> Option<&StudyItem> {
+        match &self.state {
+            State::AwaitingAnswer { item, .. } | State::AwaitingGrade { item } => Some(item),
+            _ => None,
+        }
+    }
+
+    /// Returns how far the session has got.
+    pub fn progress(&self) -> Progress {
+        let total = match self.mode {
+            Mode::Exam => Some(self.questions.min(self.session.items().len())),
+            _ => self.count,
+        };
+        let weight = self.current().and_then(|item| {
+            let session = &self.session;
+            session
+                .items()
+                .iter()
+                .position(|other| other.front == item.front && other.back == item.back)
+                .map(|index| session.weights()[index])
+        });
+        Progress {
+            index: self.summary.answered + 1,
+            total,
+            weight,
+        }
+    }
+
+    /// Ends the session.
+    pub fn finish(&mut self) {
+        info!(
+            answered = self.summary.answered,
+            correct = self.summary.correct,
+            "finished session"
+        );
+        let already_finished = matches!(self.state, State::Finished);
+        self.state = State::Finished;
+        if let Some(started_at) = self.started_at {
+            self.summary.elapsed = started_at.elapsed();
+        }
+        if !already_finished {
+            self.events.emit(Event::SessionEnd {
+                answered: self.summary.answered,
+                correct: self.summary.correct,
+                elapsed: self.summary.elapsed,
+            });
+        }
+    }
+
+    /// Draws the first question.
+    pub fn start(&mut self) -> Result<Vec<Render>> {
+        self.started_at = Some(Instant::now());
+        if self.mode == Mode::Exam {
+            self.exam = self.session.draw_unique(self.questions);
+        }
+        self.next()
+    }
+
+    pub fn handle(&mut self, input: Input) -> Result<Vec<Render>> {
+        if input != Input::Continue {
+            self.events.emit(Event::Input {
+                input: input.clone(),
+            });
+        }
+        let asked = match &self.state {
+            _ if !self.verbose => Vec::new(),
+            State::AwaitingAnswer { item, .. } | State::AwaitingGrade { item } => {
+                vec![item.clone()]
+            }
+            State::AwaitingMatches { items, .. } => items.clone(),
+            _ => Vec::new(),
+        };
+        if asked.is_empty() {
+            return self.dispatch(input);
+        }
+        let before = self.session.weights().to_vec();
+        let mut renders = self.dispatch(input)?;
+        if !matches!(self.state, State::ShowingFeedback) {
+            return Ok(renders);
+        }
+        for item in asked {
+            let Some(index) = self
+                .session
+                .items()
+                .iter()
+                .position(|i| i.front == item.front && i.back == item.back)
+            else {
+                continue;
+            };
+            renders.push(Render::Notice(format!(
+                "{} / {}: weight {} → {}",
+                item.front,
+                item.back,
+                before[index],
+                self.session.weights()[index]
+            )));
+        }
+        Ok(renders)
+    }
+
+    fn dispatch(&mut self, input: Input) -> Result<Vec<Render>> {
+        match (&self.state, input) {
+            (State::AwaitingMatches { .. }, Input::Line(line)) => {
+                let line = line.trim();
+                match line.strip_prefix('\\') {
+                    Some(command) => self.command(command),
+                    None => self.match_board(line),
+                }
+            }
+            (State::AwaitingAnswer { .. } | State::AwaitingGrade { .. }, Input::Line(line)) => {
+                let line = line.trim();
+                let Some(command) = line.strip_prefix('\\') else {
+                    return match (&self.state, self.mode) {
+                        (State::AwaitingAnswer { item, .. }, Mode::Flashcard) => {
+                            let item = item.clone();
+                            self.state = State::AwaitingGrade { item };
+                            Ok(self.question())
+                        }
+                        (State::AwaitingGrade { .. }, _) => match line.parse() {
+                            Ok(grade) => self.grade(grade),
+                            Err(e) => {
+                                let mut renders = vec![Render::Notice(format!(
+                                    "{}; grade with 1-4 or again, hard, good, easy",
+                                    e
+                                ))];
+                                renders.extend(self.question());
+                                Ok(renders)
+                            }
+                        },
+                        _ => self.answer(line.to_string()),
+                    };
+                };
+                self.command(command)
+            }
+            (State::AwaitingAnswer { .. }, Input::Timeout) => self.time_out(),
+            (State::ShowingFeedback, Input::Continue) => self.next(),
+            (
+                State::AwaitingAnswer { .. }
+                | State::AwaitingGrade { .. }
+                | State::AwaitingMatches { .. }
+                | State::ShowingFeedback,
+                Input::Quit,
+            ) => {
+                self.finish();
+                Ok(vec![Render::Quit])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Runs a backslash command, then shows the question again.
+    fn command(&mut self, command: &str) -> Result<Vec<Render>> {
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        let mut renders = match self.commands.get(name) {
+            Some(handler) => handler(self, args.trim())?,
+            None => vec![Render::Invalid("Unknown command".to_string())],
+        };
+        renders.extend(self.question());
+        Ok(renders)
+    }
+
+    /// Moves on from the current question without answering it, so it
+    /// counts neither right nor wrong. In an exam it counts as unanswered,
+    /// still without touching the weights.
+    pub fn skip(&mut self) -> Result<Vec<Render>> {
+        if matches!(self.state, State::ShowingFeedback | State::Finished) {
+            return Ok(Vec::new());
+        }
+        if self.mode == Mode::Exam {
+            if let Some(item) = self.current().cloned() {
+                let renders = vec![Render::Notice(
+                    "Skipped; counted as unanswered.".to_string(),
+                )];
+                return self.record(item, String::new(), false, renders);
+            }
+        }
+        if let Some(item) = self.current().cloned() {
+            self.events.emit(Event::Skipped { item });
+        }
+        self.session.skip()?;
+        self.asked_at = None;
+        self.state = State::ShowingFeedback;
+        Ok(vec![Render::Notice("Skipped.".to_string())])
+    }
+
+    /// Takes back the last answer, or every pair of the last match board:
+    /// the weights go back to what they were before it and it no longer
+    /// counts in the summary. Listeners are told with `Event::Undone`, so
+    /// they can forget it too. Exam answers are final.
+    pub fn undo(&mut self) -> Result<Vec<Render>> {
+        if self.mode == Mode::Exam {
+            return Ok(vec![Render::Notice(
+                "Exam answers cannot be taken back.".to_string(),
+            )]);
+        }
+        if self.last_answers.is_empty() {
+            return Ok(vec![Render::Notice("Nothing to undo.".to_string())]);
+        }
+        self.session.undo()?;
+        let last = std::mem::take(&mut self.last_answers);
+        let notice = match last.as_slice() {
+            [answer] => format!("Took back the answer to {}.", answer.item.front),
+            answers => format!("Took back the board's {} answers.", answers.len()),
+        };
+        for answer in last.into_iter().rev() {
+            self.summary.answered -= 1;
+            if answer.correct {
+                self.summary.correct -= 1;
+            } else {
+                self.summary.missed.pop();
+            }
+            if answer.timed {
+                self.summary.response_times.pop();
+            }
+            self.streaks.insert(
+                (answer.item.front.clone(), answer.item.back.clone()),
+                answer.run,
+            );
+            self.events.emit(Event::Undone { item: answer.item });
+        }
+        Ok(vec![Render::Notice(notice)])
+    }
+
+    /// Starts the items for which `reset` returns true over; see
+    /// [`StudySession::reset`].
+    pub fn reset(&mut self, reset: impl FnMut(&StudyItem) -> bool) -> Result<usize> {
+        Ok(self.session.reset(reset)?)
+    }
+
+    /// Renders the question currently being asked, with its choices or
+    /// its revealed back.
+    fn question(&self) -> Vec<Render> {
+        match &self.state {
+            State::AwaitingAnswer { item, choices } if !choices.is_empty() => vec![
+                Render::Question(item.clone()),
+                Render::Choices(choices.clone()),
+            ],
+            State::AwaitingAnswer { item, .. } => vec![Render::Question(item.clone())],
+            State::AwaitingGrade { item } => vec![Render::Reveal(item.clone())],
+            State::AwaitingMatches { items, backs } => vec![Render::Board {
+                fronts: items.iter().map(|item| item.front.clone()).collect(),
+                backs: backs.clone(),
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Grades `answer` against the current question and updates weights.
+    /// When there are choices, a number picks one of them.
+    pub fn answer(&mut self, mut answer: String) -> Result<Vec<Render>> {
+        let State::AwaitingAnswer { item, choices } = &self.state else {
+            return Ok(Vec::new());
+        };
+        let item = item.clone();
+        if let Some(choice) = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| choices.get(n.checked_sub(1)?))
+        {
+            answer = choice.clone();
+        }
+
+        for middleware in &mut self.middleware {
+            middleware.before_answer(&item, &mut answer)?;
+        }
+
+        let correct = if self.mode == Mode::Exam {
+            self.session.is_correct(&item, &answer)
+        } else {
+            self.session.answer(&answer)?
+        };
+        let renders = if correct {
+            vec![Render::Correct(item.clone())]
+        } else {
+            vec![Render::Incorrect(item.clone())]
+        };
+        self.record(item, answer, correct, renders)
+    }
+
+    /// Grades a board from one letter per front, in order, naming the back
+    /// it pairs with, e.g. `cabd`.
+    fn match_board(&mut self, line: &str) -> Result<Vec<Render>> {
+        let State::AwaitingMatches { items, backs } = &self.state else {
+            return Ok(Vec::new());
+        };
+        let letters: Vec<char> = line
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        let picked: Option<Vec<&String>> = letters
+            .iter()
+            .map(|&letter| {
+                let index = (letter as usize).checked_sub('a' as usize)?;
+                backs.get(index)
+            })
+            .collect();
+        let repeated = letters
+            .iter()
+            .enumerate()
+            .any(|(i, letter)| letters[..i].contains(letter));
+        let picked = match picked {
+            Some(picked) if picked.len() == items.len() && !repeated => picked,
+            _ => {
+                let mut renders = vec![Render::Notice(format!(
+                    "Type one letter per front, in order and each once, e.g. {}",
+                    ('a'..).take(items.len()).collect::<String>()
+                ))];
+                renders.extend(self.question());
+                return Ok(renders);
+            }
+        };
+        let matches: Vec<(StudyItem, String, bool)> = items
+            .iter()
+            .zip(picked)
+            .map(|(item, back)| (item.clone(), back.clone(), *back == item.back))
+            .collect();
+
+        let grades: Vec<(StudyItem, Grade)> = matches
+            .iter()
+            .map(|(item, _, correct)| {
+                let grade = if *correct { Grade::Good } else { Grade::Again };
+                (item.clone(), grade)
+            })
+            .collect();
+        self.session.grade_items(&grades)?;
+        let mut renders = vec![Render::Matches(matches.clone())];
+        self.last_answers.clear();
+        for (item, back, correct) in matches {
+            renders = self.record(item, back, correct, renders)?;
+        }
+        Ok(renders)
+    }
+
+    /// Counts the current question as missed because time ran out.
+    fn time_out(&mut self) -> Result<Vec<Render>> {
+        let State::AwaitingAnswer { item, .. } = &self.state else {
+            return Ok(Vec::new());
+        };
+        let item = item.clone();
+        self.session.grade(Grade::Again)?;
+        let renders = vec![
+            Render::Notice("Out of time.".to_string()),
+            Render::Incorrect(item.clone()),
+        ];
+        self.record(item, String::new(), false, renders)
+    }
+
+    /// Shows the back of the current question and counts it as missed.
+    pub fn reveal(&mut self) -> Result<Vec<Render>> {
+        let State::AwaitingAnswer { item, .. } = &self.state else {
+            return Ok(Vec::new());
+        };
+        let item = item.clone();
+        if self.mode != Mode::Exam {
+            self.session.grade(Grade::Again)?;
+        }
+        let renders = vec![Render::Notice(format!("{} = {}", item.front, item.back))];
+        self.record(item, String::new(), false, renders)
+    }
+
+    /// Applies a self-given grade to the revealed flashcard.
+    pub fn grade(&mut self, grade: Grade) -> Result<Vec<Render>> {
+        let State::AwaitingGrade { item } = &self.state else {
+            return Ok(Vec::new());
+        };
+        let item = item.clone();
+        self.session.grade(grade)?;
+        self.record(item, grade.to_string(), grade.passed(), Vec::new())
+    }
+
+    /// Counts an answer in the summary, runs the `after_answer` hooks and
+    /// moves on to the feedback.
+    fn record(
+        &mut self,
+        item: StudyItem,
+        answer: String,
+        correct: bool,
+        mut renders: Vec<Render>,
+    ) -> Result<Vec<Render>> {
+        info!(front = %item.front, %answer, correct, "answered");
+        // The pairs of a board are taken back together.
+        if self.mode != Mode::Match {
+            self.last_answers.clear();
+        }
+        let timed = self.asked_at.is_some();
+        if let Some(asked_at) = self.asked_at.take() {
+            self.summary
+                .response_times
+                .push((item.clone(), asked_at.elapsed()));
+        }
+        self.summary.answered += 1;
+        let run = self
+            .streaks
+            .entry((item.front.clone(), item.back.clone()))
+            .or_default();
+        self.last_answers.push(Recorded {
+            item: item.clone(),
+            correct,
+            run: *run,
+            timed,
+        });
+        if correct {
+            self.summary.correct += 1;
+            *run += 1;
+        } else {
+            self.summary.missed.push((item.clone(), answer.clone()));
+            *run = 0;
+        }
+
+        for middleware in &mut self.middleware {
+            middleware.after_answer(&item, &answer, correct, &mut renders)?;
+        }
+        self.events.emit(match correct {
+            true => Event::Correct { item, answer },
+            false => Event::Incorrect { item, answer },
+        });
+
+        self.state = State::ShowingFeedback;
+        Ok(renders)
+    }
+
+    /// Returns `(weight, front, back)` for every item, heaviest first.
+    pub fn weights(&self) -> Vec<(u32, String, String)> {
+        let mut weighted_items: Vec<_> = self
+            .session
+            .weights()
+            .iter()
+            .zip(self.session.items())
+            .map(|(&w, item)| (w, item.front.clone(), item.back.clone()))
+            .collect();
+
+        weighted_items.sort_by_key(|b| std::cmp::Reverse(b.0));
+        weighted_items
+    }
+
+    /// Returns every item's weight, FSRS memory or Leitner box and misses
+    /// this session, heaviest first.
+    pub fn schedule(&self) -> Vec<ItemSchedule> {
+        let session = &self.session;
+        let mut rows: Vec<ItemSchedule> = session
+            .items()
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let lapses = self
+                    .summary
+                    .missed
+                    .iter()
+                    .filter(|(missed, _)| missed.front == item.front && missed.back == item.back)
+                    .count();
+                ItemSchedule {
+                    front: item.front.clone(),
+                    back: item.back.clone(),
+                    weight: Some(session.weights()[index]),
+                    lapses: lapses as u32,
+                    leitner_box: session.boxes().map(|boxes| boxes[index]),
+                    ..ItemSchedule::default()
+                }
+                .with_memory(session.memory(index))
+            })
+            .collect();
+        schedule::sort(&mut rows, Column::Weight);
+        rows
+    }
+
+    /// Lays out a new board of items with distinct backs, asking each of
+    /// them as `next` asks a single item.
+    fn next_board(&mut self) -> Result<Vec<Render>> {
+        let mut items: Vec<StudyItem> = Vec::new();
+        for item in self.session.draw_unique(usize::MAX) {
+            if items.len() == BOARD_SIZE {
+                break;
+            }
+            if items.iter().all(|other| other.back != item.back) {
+                items.push(item);
+            }
+        }
+        if items.is_empty() {
+            self.finish();
+            return Ok(vec![Render::NoItems]);
+        }
+        let mut backs: Vec<String> = items.iter().map(|item| item.back.clone()).collect();
+        backs.shuffle(self.session.rng());
+
+        let mut renders = Vec::new();
+        for item in &items {
+            self.ask(item, &mut renders)?;
+        }
+        self.asked_at = Some(Instant::now());
+        self.state = State::AwaitingMatches { items, backs };
+        renders.extend(self.question());
+        Ok(renders)
+    }
+
+    /// Runs the `before_question` hooks for `item` and tells listeners it
+    /// is being asked.
+    fn ask(&mut self, item: &StudyItem, renders: &mut Vec<Render>) -> Result<()> {
+        for middleware in &mut self.middleware {
+            middleware.before_question(item, renders)?;
+        }
+        self.events.emit(Event::Question { item: item.clone() });
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<Vec<Render>> {
+        if self
+            .count
+            .is_some_and(|count| self.summary.answered >= count)
+        {
+            self.finish();
+            return Ok(Vec::new());
+        }
+        let out_of_time = self
+            .time_limit
+            .zip(self.started_at)
+            .is_some_and(|(limit, started_at)| started_at.elapsed() >= limit);
+        if out_of_time {
+            self.finish();
+            return Ok(vec![Render::Notice("Time is up.".to_string())]);
+        }
+        if self.mode == Mode::Match {
+            return self.next_board();
+        }
+        if self.mode == Mode::Exam && self.summary.answered > 0 && self.exam.is_empty() {
+            self.finish();
+            return Ok(vec![Render::Misses(self.summary.missed.clone())]);
+        }
+        if self.mode == Mode::Mastery && self.session.items().iter().all(|item| self.mastered(item))
+        {
+            self.finish();
+            let secs = self.summary.elapsed.as_secs();
+            return Ok(vec![Render::Notice(format!(
+                "Mastered all {} items in {}m{:02}s.",
+                self.session.items().len(),
+                secs / 60,
+                secs % 60
+            ))]);
+        }
+        let item = match self.mode {
+            Mode::Exam => self.exam.pop(),
+            // Skip mastered items, giving up after as many draws as there
+            // are items so heavily weighted ones cannot stall the session.
+            Mode::Mastery => {
+                let mut item = self.session.next_card().cloned();
+                for _ in 0..self.session.items().len() {
+                    match &item {
+                        Some(drawn) if self.mastered(drawn) => {
+                            item = self.session.next_card().cloned();
+                        }
+                        _ => break,
+                    }
+                }
+                item
+            }
+            _ => self.session.next_card().cloned(),
+        };
+        let Some(item) = item else {
+            self.finish();
+            return Ok(vec![Render::NoItems]);
+        };
+
+        let mut renders = Vec::new();
+        self.ask(&item, &mut renders)?;
+
+        let choices = match self.mode {
+            Mode::Choice => self.session.choices(&item, CHOICES),
+            _ => Vec::new(),
+        };
+        self.asked_at = Some(Instant::now());
+        self.state = State::AwaitingAnswer { item, choices };
+        renders.extend(self.question());
+        Ok(renders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{self, ScriptedFrontend};
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Fixed(Vec<(&'static str, &'static str)>);
+
+    impl crate::StudySetLoader for Fixed {
+        fn name(&self) -> String {
+            "fixed".to_string()
+        }
+
+        fn load(&self, _rng: &mut dyn rand::RngCore) -> crate::error::Result<Vec<StudyItem>> {
+            Ok(self
+                .0
+                .iter()
+                .map(|&(front, back)| StudyItem::new(front, back))
+                .collect())
+        }
+    }
+
+    fn engine(mode: Mode) -> SessionEngine {
+        engine_with(mode, Fixed(vec![("あ", "a"), ("い", "i"), ("う", "u")]))
+    }
+
+    /// An engine whose items all have the back `x`, so answers need not
+    /// follow the order they are drawn in.
+    fn same_backs(mode: Mode) -> SessionEngine {
+        engine_with(mode, Fixed(vec![("一", "x"), ("二", "x"), ("三", "x")]))
+    }
+
+    fn engine_with(mode: Mode, items: Fixed) -> SessionEngine {
+        let session = StudySession::builder()
+            .loader(items)
+            .seed(7)
+            .build()
+            .unwrap();
+        let mut engine = SessionEngine::new(session);
+        engine.set_mode(mode);
+        engine
+    }
+
+    fn run(engine: &mut SessionEngine, lines: &[&str]) -> ScriptedFrontend {
+        let mut frontend = ScriptedFrontend::new(lines.iter().copied());
+        frontend::run(engine, &mut frontend).unwrap();
+        frontend
+    }
+
+    fn notices(frontend: &ScriptedFrontend) -> Vec<&str> {
+        frontend
+            .shown
+            .iter()
+            .filter_map(|render| match render {
+                Render::Notice(notice) => Some(notice.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn answers_are_graded_and_summarised() {
+        let mut engine = same_backs(Mode::Typed);
+        let frontend = run(&mut engine, &["x", "y", "x"]);
+        let summary = frontend.summary.as_ref().unwrap();
+        assert_eq!(frontend.questions().len(), 4);
+        assert_eq!((summary.answered, summary.correct), (3, 2));
+        assert_eq!(summary.missed[0].1, "y");
+        assert_eq!(frontend.progress[2].index, 3);
+    }
+
+    #[test]
+    fn count_ends_the_session() {
+        let mut engine = same_backs(Mode::Typed);
+        engine.set_count(2);
+        let frontend = run(&mut engine, &["x"; 5]);
+        assert_eq!(frontend.questions().len(), 2);
+        assert_eq!(frontend.summary.as_ref().unwrap().answered, 2);
+        assert_eq!(frontend.progress[0].total, Some(2));
+    }
+
+    #[test]
+    fn time_limit_ends_the_session_after_the_current_card() {
+        let mut engine = same_backs(Mode::Typed);
+        engine.start().unwrap();
+        engine.set_time_limit(Duration::ZERO);
+        let renders = engine.handle(Input::Line("x".to_string())).unwrap();
+        assert!(matches!(&renders[0], Render::Correct(_)));
+        let renders = engine.handle(Input::Continue).unwrap();
+        assert!(matches!(&renders[..], [Render::Notice(notice)] if notice == "Time is up."));
+        assert!(matches!(engine.state(), State::Finished));
+        assert_eq!(engine.summary().answered, 1);
+    }
+
+    #[test]
+    fn exams_ask_distinct_items_without_changing_weights() {
+        let mut engine = same_backs(Mode::Exam);
+        engine.set_questions(2);
+        let weights = engine.session().weights().to_vec();
+        let frontend = run(&mut engine, &["x", "y", "x"]);
+        let mut asked = frontend.questions();
+        assert_eq!(asked.len(), 2);
+        asked.dedup();
+        assert_eq!(asked.len(), 2);
+        assert_eq!(engine.session().weights(), weights);
+        assert!(matches!(
+            frontend.shown.last(),
+            Some(Render::Misses(misses)) if misses.len() == 1 && misses[0].1 == "y"
+        ));
+    }
+
+    #[test]
+    fn undo_takes_back_the_last_answer() {
+        let mut engine = same_backs(Mode::Typed);
+        let weights = engine.session().weights().to_vec();
+        let undone = Rc::new(RefCell::new(0));
+        let count = Rc::clone(&undone);
+        engine.subscribe(move |event: &Event| {
+            if let Event::Undone { .. } = event {
+                *count.borrow_mut() += 1;
+            }
+        });
+
+        engine.start().unwrap();
+        engine.handle(Input::Line("y".to_string())).unwrap();
+        assert_ne!(engine.session().weights(), weights);
+        engine.handle(Input::Continue).unwrap();
+        engine.handle(Input::Line("\\u".to_string())).unwrap();
+
+        assert_eq!(engine.session().weights(), weights);
+        assert_eq!(engine.summary().answered, 0);
+        assert!(engine.summary().missed.is_empty());
+        assert_eq!(*undone.borrow(), 1);
+        let renders = engine.handle(Input::Line("\\u".to_string())).unwrap();
+        assert!(matches!(&renders[0], Render::Notice(notice) if notice == "Nothing to undo."));
+    }
+
+    #[test]
+    fn undo_takes_back_a_whole_match_board() {
+        let mut engine = engine(Mode::Match);
+        let weights = engine.session().weights().to_vec();
+        engine.start().unwrap();
+        engine.handle(Input::Line("abc".to_string())).unwrap();
+        assert_eq!(engine.summary().answered, 3);
+        engine.handle(Input::Continue).unwrap();
+        let renders = engine.handle(Input::Line("\\u".to_string())).unwrap();
+
+        assert!(matches!(&renders[0], Render::Notice(notice) if notice.contains("3 answers")));
+        assert_eq!(engine.session().weights(), weights);
+        assert_eq!(engine.summary().answered, 0);
+        assert_eq!(engine.summary().correct, 0);
+        assert!(engine.summary().missed.is_empty());
+    }
+
+    #[test]
+    fn exam_answers_cannot_be_undone() {
+        let mut engine = same_backs(Mode::Exam);
+        engine.set_questions(2);
+        engine.start().unwrap();
+        engine.handle(Input::Line("y".to_string())).unwrap();
+        engine.handle(Input::Continue).unwrap();
+        let renders = engine.handle(Input::Line("\\u".to_string())).unwrap();
+        assert!(
+            matches!(&renders[0], Render::Notice(notice) if notice == "Exam answers cannot be taken back.")
+        );
+        assert_eq!(engine.summary().answered, 1);
+        assert_eq!(engine.summary().missed.len(), 1);
+    }
+
+    #[test]
+    fn skipped_items_count_neither_way() {
+        let mut engine = same_backs(Mode::Typed);
+        engine.set_count(1);
+        let frontend = run(&mut engine, &["\\s", "x"]);
+        assert_eq!(frontend.questions().len(), 2);
+        assert!(notices(&frontend).contains(&"Skipped."));
+        let summary = frontend.summary.as_ref().unwrap();
+        assert_eq!((summary.answered, summary.correct), (1, 1));
+    }
+
+    #[test]
+    fn exam_skips_count_as_unanswered() {
+        let mut engine = same_backs(Mode::Exam);
+        engine.set_questions(2);
+        let weights = engine.session().weights().to_vec();
+        let frontend = run(&mut engine, &["\\s", "\\s"]);
+        assert_eq!(engine.session().weights(), weights);
+        assert_eq!(frontend.questions().len(), 2);
+        let summary = frontend.summary.as_ref().unwrap();
+        assert_eq!((summary.answered, summary.correct), (2, 0));
+        assert!(matches!(
+            frontend.shown.last(),
+            Some(Render::Misses(misses)) if misses.len() == 2
+        ));
+    }
+
+    #[test]
+    fn timeouts_count_as_misses() {
+        let mut engine = same_backs(Mode::Timed);
+        engine.set_count(1);
+        let mut frontend = ScriptedFrontend::from_inputs([Input::Timeout]);
+        frontend::run(&mut engine, &mut frontend).unwrap();
+        assert!(notices(&frontend).contains(&"Out of time."));
+        assert_eq!(frontend.summary.as_ref().unwrap().missed.len(), 1);
+    }
+
+    #[test]
+    fn choices_are_picked_by_number() {
+        let mut engine = engine(Mode::Choice);
+        let renders = engine.start().unwrap();
+        let Some(Render::Choices(choices)) = renders.last() else {
+            panic!("expected choices, got {:?}", renders);
+        };
+        let item = engine.current().unwrap().clone();
+        let pick = choices.iter().position(|c| *c == item.back).unwrap() + 1;
+        let renders = engine.handle(Input::Line(pick.to_string())).unwrap();
+        assert!(matches!(&renders[0], Render::Correct(correct) if correct.front == item.front));
+    }
+
+    #[test]
+    fn flashcards_are_revealed_then_self_graded() {
+        let mut engine = same_backs(Mode::Flashcard);
+        engine.set_count(2);
+        let frontend = run(&mut engine, &["", "good", "", "maybe", "again"]);
+        assert_eq!(
+            frontend
+                .shown
+                .iter()
+                .filter(|render| matches!(render, Render::Reveal(_)))
+                .count(),
+            3
+        );
+        let summary = frontend.summary.as_ref().unwrap();
+        assert_eq!((summary.answered, summary.correct), (2, 1));
+    }
+
+    #[test]
+    fn mastery_ends_once_every_item_is_mastered() {
+        let mut engine = same_backs(Mode::Mastery);
+        engine.set_streak(1);
+        let frontend = run(&mut engine, &["x"; 10]);
+        assert_eq!(frontend.summary.as_ref().unwrap().answered, 3);
+        assert!(notices(&frontend)[0].starts_with("Mastered all 3 items"));
+    }
+
+    /// Records the front of every item asked.
+    fn asked(engine: &mut SessionEngine) -> Rc<RefCell<Vec<String>>> {
+        let asked = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&asked);
+        engine.subscribe(move |event: &Event| {
+            if let Event::Question { item } = event {
+                log.borrow_mut().push(item.front.clone());
+            }
+        });
+        asked
+    }
+
+    #[test]
+    fn match_boards_ask_every_item() {
+        let mut engine = engine(Mode::Match);
+        let asked = asked(&mut engine);
+        engine.start().unwrap();
+        let mut fronts = asked.borrow().clone();
+        fronts.sort();
+        assert_eq!(fronts, ["あ", "い", "う"]);
+    }
+
+    #[test]
+    fn match_boards_reject_repeated_letters() {
+        let mut engine = engine(Mode::Match);
+        engine.start().unwrap();
+        let renders = engine.handle(Input::Line("aab".to_string())).unwrap();
+        assert!(matches!(&renders[0], Render::Notice(notice) if notice.contains("each once")));
+        assert!(matches!(engine.state(), State::AwaitingMatches { .. }));
+        assert_eq!(engine.summary().answered, 0);
+
+        let State::AwaitingMatches { items, backs } = engine.state() else {
+            unreachable!();
+        };
+        let letters: String = items
+            .iter()
+            .map(|item| {
+                let index = backs.iter().position(|back| *back == item.back).unwrap();
+                (b'a' + index as u8) as char
+            })
+            .collect();
+        engine.handle(Input::Line(letters)).unwrap();
+        assert_eq!(engine.summary().correct, 3);
+    }
+}
@@ -0,0 +1,52 @@
+//! The ways the library can fail, for callers that need to tell them
+//! apart. Application code may still wrap these in `anyhow`.
+use rand::distr::weighted::Error as WeightError;
+use std::{io, path::PathBuf};
+use thiserror::Error;
+
+pub type Result<T, E = LjpError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum LjpError {
+    /// No built-in set or deck has this name.
+    #[error("Set '{0}' not found")]
+    UnknownSet(String),
+    /// A deck line is neither `front,back[,mnemonic]` nor a cloze.
+    #[error("Malformed line {line} in deck {deck}: {text}")]
+    MalformedDeck {
+        deck: String,
+        line: usize,
+        text: String,
+    },
+    /// A set's table is neither in the assets directory nor built in.
+    #[error("Asset {0} not found; put it in the assets directory or build with embedded-assets")]
+    MissingAsset(&'static str),
+    /// The session has no items to draw from.
+    #[error("No items available for study")]
+    EmptySession,
+    /// An answer or grade came before any card was drawn.
+    #[error("No card has been drawn to answer")]
+    NoCardDrawn,
+    /// An item passed to the session is not one of its own.
+    #[error("'{0}' is not in this session")]
+    UnknownItem(String),
+    /// The weights cannot be sampled from, e.g. because all are zero.
+    #[error("Failed to sync weighted index")]
+    Weights(#[from] WeightError),
+    /// A saved session could not be parsed or contradicts itself.
+    #[error("Session {} is corrupt: {reason}", .path.display())]
+    StateCorruption { path: PathBuf, reason: String },
+    /// A plugin could not be loaded or misbehaved.
+    #[error("Plugin {} failed: {reason}", .path.display())]
+    Plugin { path: PathBuf, reason: String },
+    /// A deck script could not be compiled or its hook failed.
+    #[error("Script {} failed: {reason}", .path.display())]
+    Script { path: PathBuf, reason: String },
+    #[error("Failed to {action} {}", .path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
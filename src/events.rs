@@ -0,0 +1,292 @@
+//! Structured events from a running session, for features that only need
+//! to know what happened: stats, logs, notifications or speech. Unlike
+//! middleware, listeners cannot change the session or add to what is
+//! shown.
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{engine::Input, StudyItem, StudySession};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Written by `ljp study --events` before the first question: the
+    /// command line, the seed and the session as built, so the session
+    /// can be replayed.
+    SessionStart {
+        args: Vec<String>,
+        seed: u64,
+        session: Box<StudySession>,
+    },
+    /// Something the learner typed or did, other than moving on from
+    /// feedback.
+    Input {
+        input: Input,
+    },
+    /// A card was drawn and asked.
+    Question {
+        item: StudyItem,
+    },
+    Correct {
+        item: StudyItem,
+        answer: String,
+    },
+    Incorrect {
+        item: StudyItem,
+        answer: String,
+    },
+    /// A card was moved on from without an answer.
+    Skipped {
+        item: StudyItem,
+    },
+    /// The last answer to `item` was taken back.
+    Undone {
+        item: StudyItem,
+    },
+    SessionEnd {
+        answered: usize,
+        correct: usize,
+        #[serde(serialize_with = "as_secs")]
+        elapsed: Duration,
+    },
+}
+
+fn as_secs<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// Receives every event of a session. Listeners deal with their own
+/// failures, so one cannot end the session for the others.
+pub trait Listener {
+    fn on_event(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event)> Listener for F {
+    fn on_event(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+/// The listeners of a session, called in the order they subscribed.
+#[derive(Default)]
+pub struct EventBus {
+    listeners: Vec<Box<dyn Listener>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, listener: impl Listener + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        for listener in &mut self.listeners {
+            listener.on_event(&event);
+        }
+    }
+}
+
+/// An answer that can no longer be taken back.
+#[derive(Debug, Clone)]
+pub struct Answer {
+    pub item: StudyItem,
+    pub answer: String,
+    pub correct: bool,
+    /// From asking the question to answering it.
+    pub response: Duration,
+    /// When it was graded.
+    pub at: DateTime<Local>,
+}
+
+/// Follows a session's events for listeners that keep answers, holding
+/// back the last step's answers while they can still be undone: one
+/// answer, or every pair of a match board. They are final once an answer
+/// to a later question is given or the session ends.
+#[derive(Debug, Default)]
+pub struct Answers {
+    asked_at: Option<Instant>,
+    /// Whether a question was asked since the pending answers were given.
+    asked_since: bool,
+    pending: Vec<Answer>,
+}
+
+impl Answers {
+    /// Takes in `event`, returning the answers it made final.
+    pub fn follow(&mut self, event: &Event) -> Vec<Answer> {
+        let (item, answer, correct) = match event {
+            Event::Question { .. } => {
+                self.asked_at = Some(Instant::now());
+                self.asked_since = true;
+                return Vec::new();
+            }
+            Event::Undone { item } => {
+                if let Some(index) = self.pending.iter().rposition(|answer| {
+                    answer.item.front == item.front && answer.item.back == item.back
+                }) {
+                    self.pending.remove(index);
+                }
+                return Vec::new();
+            }
+            Event::SessionEnd { .. } => return self.flush(),
+            Event::SessionStart { .. } | Event::Input { .. } | Event::Skipped { .. } => {
+                return Vec::new()
+            }
+            Event::Correct { item, answer } => (item, answer, true),
+            Event::Incorrect { item, answer } => (item, answer, false),
+        };
+        let response = self
+            .asked_at
+            .map(|asked_at| asked_at.elapsed())
+            .unwrap_or_default();
+        let done = match std::mem::take(&mut self.asked_since) {
+            true => self.flush(),
+            false => Vec::new(),
+        };
+        self.pending.push(Answer {
+            item: item.clone(),
+            answer: answer.clone(),
+            correct,
+            response,
+            at: Local::now(),
+        });
+        done
+    }
+
+    /// Returns the answers still held back, for when the session is over.
+    pub fn flush(&mut self) -> Vec<Answer> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Appends each event to a file as a line of JSON.
+pub struct EventLog {
+    path: PathBuf,
+    file: File,
+}
+
+impl EventLog {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open event log {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    fn write(&mut self, event: &Event) -> Result<()> {
+        let line = serde_json::to_string(event).context("Failed to serialize event")?;
+        writeln!(self.file, "{}", line)
+            .with_context(|| format!("Failed to write event log {}", self.path.display()))
+    }
+}
+
+impl Listener for EventLog {
+    fn on_event(&mut self, event: &Event) {
+        if let Err(err) = self.write(event) {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answered(front: &str, correct: bool) -> Event {
+        let item = StudyItem::new(front.to_string(), "back".to_string());
+        let answer = "back".to_string();
+        match correct {
+            true => Event::Correct { item, answer },
+            false => Event::Incorrect { item, answer },
+        }
+    }
+
+    fn asked(front: &str) -> Event {
+        Event::Question {
+            item: StudyItem::new(front.to_string(), "back".to_string()),
+        }
+    }
+
+    fn fronts(answers: Vec<Answer>) -> Vec<String> {
+        answers
+            .into_iter()
+            .map(|answer| answer.item.front)
+            .collect()
+    }
+
+    #[test]
+    fn answers_are_final_once_another_is_given() {
+        let mut answers = Answers::default();
+        answers.follow(&asked("a"));
+        assert!(answers.follow(&answered("a", true)).is_empty());
+        answers.follow(&asked("b"));
+        assert_eq!(fronts(answers.follow(&answered("b", false))), ["a"]);
+        let end = Event::SessionEnd {
+            answered: 2,
+            correct: 1,
+            elapsed: Duration::ZERO,
+        };
+        let last = answers.follow(&end);
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].item.front, "b");
+        assert!(!last[0].correct);
+        assert!(answers.flush().is_empty());
+    }
+
+    #[test]
+    fn answers_keep_the_time_they_were_graded() {
+        let mut answers = Answers::default();
+        let before = Local::now();
+        answers.follow(&asked("a"));
+        answers.follow(&answered("a", true));
+        let graded = Local::now();
+        std::thread::sleep(Duration::from_millis(5));
+        answers.follow(&asked("b"));
+        let first = answers.follow(&answered("b", true));
+        assert!(before <= first[0].at && first[0].at <= graded);
+    }
+
+    #[test]
+    fn undone_answers_are_dropped() {
+        let mut answers = Answers::default();
+        answers.follow(&asked("a"));
+        answers.follow(&answered("a", true));
+        answers.follow(&Event::Undone {
+            item: StudyItem::new("a".to_string(), "back".to_string()),
+        });
+        assert!(answers.follow(&answered("a", false)).is_empty());
+        assert_eq!(fronts(answers.flush()), ["a"]);
+    }
+
+    #[test]
+    fn a_board_is_held_back_as_a_whole() {
+        let mut answers = Answers::default();
+        answers.follow(&asked("a"));
+        answers.follow(&asked("b"));
+        answers.follow(&answered("a", true));
+        assert!(answers.follow(&answered("b", true)).is_empty());
+        for front in ["b", "a"] {
+            answers.follow(&Event::Undone {
+                item: StudyItem::new(front.to_string(), "back".to_string()),
+            });
+        }
+        assert!(answers.flush().is_empty());
+
+        answers.follow(&asked("a"));
+        answers.follow(&asked("b"));
+        answers.follow(&answered("a", true));
+        answers.follow(&answered("b", false));
+        answers.follow(&asked("c"));
+        assert_eq!(fronts(answers.follow(&answered("c", true))), ["a", "b"]);
+    }
+}
@@ -0,0 +1,49 @@
+const ORIGINS: &str = include_str!("../assets/origins.csv");
+
+/// Kana written with dakuten (゛) or handakuten (゜), whose origin is that
+/// of the plain kana they are based on.
+const VOICED: &str =
+    "がぎぐげござじずぜぞだぢづでどばびぶべぼガギグゲゴザジズゼゾダヂヅデドバビブベボ";
+const SEMI_VOICED: &str = "ぱぴぷぺぽパピプペポ";
+const SMALL: &str = "ぁぃぅぇぉっゃゅょゎァィゥェォッャュョヮ";
+
+fn origin(kana: char) -> Option<&'static str> {
+    ORIGINS
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .find(|(k, _)| k.chars().eq([kana]))
+        .map(|(_, kanji)| kanji.trim())
+}
+
+/// Returns the plain kana `c` is derived from by adding marks or shrinking
+/// it, e.g. が → か, ぱ → は, ゃ → や.
+fn base(c: char) -> char {
+    let offset: i32 = if SEMI_VOICED.contains(c) {
+        -2
+    } else if VOICED.contains(c) {
+        -1
+    } else if SMALL.contains(c) {
+        1
+    } else {
+        0
+    };
+    char::from_u32((c as i32 + offset) as u32).unwrap_or(c)
+}
+
+/// Describes where each kana in `front` comes from, one line per
+/// character, e.g. `あ ← 安`. Returns nothing when no character has
+/// bundled information.
+pub fn explain(front: &str) -> Vec<String> {
+    front
+        .chars()
+        .filter_map(|c| {
+            let plain = base(c);
+            let kanji = origin(plain)?;
+            Some(if plain == c {
+                format!("{} ← {}", c, kanji)
+            } else {
+                format!("{} ({}) ← {}", c, plain, kanji)
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,135 @@
+//! `ljp forecast`: how many reviews fall due each day over the coming
+//! weeks, from the FSRS due dates in the progress store and the new items
+//! the learner means to add each day. Every review is taken to be answered
+//! Good, so the forecast is the least work to expect. Items scheduled by
+//! weight or Leitner box have no due dates and are left out.
+use chrono::{DateTime, Local, TimeDelta};
+
+use crate::{
+    fsrs::{Fsrs, Memory, PARAM_COUNT},
+    schedule::ItemSchedule,
+    Grade,
+};
+
+/// The longest bar drawn, in characters.
+const BAR_WIDTH: u32 = 50;
+
+/// The reviews due on each of the `days` days from `now`'s, with overdue
+/// ones on the first day. `new_per_day` new items are first reviewed on
+/// each day, then again whenever they fall due.
+pub fn project(
+    schedules: &[ItemSchedule],
+    params: [f64; PARAM_COUNT],
+    new_per_day: u32,
+    now: DateTime<Local>,
+    days: u32,
+) -> Vec<u32> {
+    let mut counts = vec![0; days as usize];
+    let day = |at: DateTime<Local>| (at.date_naive() - now.date_naive()).num_days().max(0) as usize;
+    let mut fsrs = Fsrs::new(params, 1);
+    let mut follow = |fsrs: &mut Fsrs, mut at: DateTime<Local>| {
+        while day(at) < counts.len() {
+            counts[day(at)] += 1;
+            fsrs.review_at(0, Grade::Good, at.timestamp() as u64);
+            let due = fsrs.memory(0).map_or(0, |memory| memory.due()) as i64;
+            match DateTime::from_timestamp(due, 0) {
+                Some(due) if due > at => at = due.with_timezone(&Local),
+                _ => break,
+            }
+        }
+    };
+
+    for schedule in schedules {
+        let (Some(stability), Some(difficulty), Some(due)) =
+            (schedule.interval, schedule.ease, schedule.due)
+        else {
+            continue;
+        };
+        let last_review = due.timestamp() - (stability * 86_400.0) as i64;
+        fsrs.remember(
+            0,
+            Memory {
+                stability,
+                difficulty,
+                last_review: last_review.max(0) as u64,
+            },
+        );
+        follow(&mut fsrs, due.with_timezone(&Local).max(now));
+    }
+    for day in 0..days {
+        let at = now + TimeDelta::days(i64::from(day));
+        for _ in 0..new_per_day {
+            fsrs.reset(0);
+            follow(&mut fsrs, at);
+        }
+    }
+    counts
+}
+
+/// Draws one bar per day, scaled to the busiest day.
+pub fn render(counts: &[u32], now: DateTime<Local>) -> String {
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    let mut out = String::new();
+    for (i, &count) in counts.iter().enumerate() {
+        let day = now + TimeDelta::days(i as i64);
+        let width = (count * BAR_WIDTH).div_ceil(max);
+        out.push_str(&format!(
+            "{} {:>5} {}\n",
+            day.format("%a %m-%d"),
+            count,
+            "█".repeat(width as usize)
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fsrs::DEFAULT_PARAMS;
+    use chrono::{TimeZone, Utc};
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, 10, 9, 0, 0).unwrap()
+    }
+
+    fn due_in(days: i64, stability: f64) -> ItemSchedule {
+        ItemSchedule {
+            ease: Some(5.0),
+            interval: Some(stability),
+            due: Some((now() + TimeDelta::days(days)).with_timezone(&Utc)),
+            ..ItemSchedule::default()
+        }
+    }
+
+    #[test]
+    fn overdue_items_are_due_today_and_come_back_later() {
+        let schedules = [due_in(-3, 2.0), due_in(2, 2.0), ItemSchedule::default()];
+        let counts = project(&schedules, DEFAULT_PARAMS, 0, now(), 60);
+        assert_eq!(counts[0], 1);
+        assert_eq!(counts[1], 0);
+        assert!(counts[2] >= 1);
+        // Both come back once the interval that grew after review passes.
+        assert!(counts[3..].iter().sum::<u32>() >= 2);
+    }
+
+    #[test]
+    fn new_items_add_their_first_reviews_every_day() {
+        let without = project(&[], DEFAULT_PARAMS, 0, now(), 14);
+        let with = project(&[], DEFAULT_PARAMS, 10, now(), 14);
+        assert!(without.iter().all(|&count| count == 0));
+        assert!(with.iter().all(|&count| count >= 10));
+        assert!(with[13] > with[0]);
+    }
+
+    #[test]
+    fn bars_are_scaled_to_the_busiest_day() {
+        let chart = render(&[10, 5, 0], now());
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Tue 03-10"));
+        assert_eq!(lines[0].matches('█').count(), 50);
+        assert_eq!(lines[1].matches('█').count(), 25);
+        assert_eq!(lines[2].matches('█').count(), 0);
+    }
+}
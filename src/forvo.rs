@@ -0,0 +1,235 @@
+//! Native speakers' pronunciations from Forvo for `--mode listen`, set up
+//! in the `[forvo]` section of the config file:
+//!
+//! ```toml
+//! [forvo]
+//! key = "0123456789abcdef"
+//! ```
+//!
+//! Clips are fetched with `curl` and kept under `audio/forvo` in the data
+//! directory, so each word is fetched once and plays offline after that.
+//! Words Forvo has no pronunciation of are spoken by text-to-speech.
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    paths,
+    speech::{Player, Speaker},
+};
+
+const API: &str = "https://apifree.forvo.com";
+/// How long a fetch may take before it is given up on.
+const TIMEOUT_SECS: &str = "10";
+/// The file in the cache listing words Forvo has no pronunciation of, so
+/// they are not asked for again.
+const MISSING: &str = "missing.txt";
+
+/// The `[forvo]` section of the config file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForvoConfig {
+    /// The Forvo API key.
+    pub key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    items: Vec<Pronunciation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pronunciation {
+    pathmp3: String,
+}
+
+/// Where fetched clips are cached.
+pub fn cache_dir() -> Option<PathBuf> {
+    paths::data_dir().map(|dir| dir.join("audio").join("forvo"))
+}
+
+/// Speaks words with Forvo's recordings.
+pub struct Forvo {
+    key: String,
+    cache: PathBuf,
+    player: Player,
+    missing: HashSet<String>,
+    /// Speaks the words Forvo has no pronunciation of.
+    fallback: Option<Box<dyn Speaker>>,
+}
+
+impl Forvo {
+    /// Reads which words are known to be missing from the cache at
+    /// `cache`. Clips are played with `player`.
+    pub fn open(
+        config: &ForvoConfig,
+        cache: PathBuf,
+        player: Player,
+        fallback: Option<Box<dyn Speaker>>,
+    ) -> Result<Self> {
+        let missing = match fs::read_to_string(cache.join(MISSING)) {
+            Ok(data) => data.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e).context("Failed to read the Forvo cache"),
+        };
+        Ok(Self {
+            key: config.key.clone(),
+            cache,
+            player,
+            missing,
+            fallback,
+        })
+    }
+
+    /// The clip of `word`, fetching it if it is not cached, or `None` if
+    /// Forvo has no pronunciation of it.
+    pub fn clip(&mut self, word: &str) -> Result<Option<PathBuf>> {
+        let path = self.cache.join(format!("{}.mp3", file_name(word)));
+        if path.is_file() {
+            return Ok(Some(path));
+        }
+        if self.missing.contains(word) {
+            return Ok(None);
+        }
+        let url = format!(
+            "{}/key/{}/format/json/action/word-pronunciations/word/{}/language/ja/order/rate-desc/limit/1",
+            API,
+            self.key,
+            encode(word)
+        );
+        let response: Response = serde_json::from_slice(&curl(&url, None)?)
+            .context("Unexpected Forvo response; is the API key right?")?;
+        let Some(pronunciation) = response.items.into_iter().next() else {
+            self.missing.insert(word.to_string());
+            fs::create_dir_all(&self.cache)
+                .with_context(|| format!("Failed to create {}", self.cache.display()))?;
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.cache.join(MISSING))
+                .context("Failed to write the Forvo cache")?;
+            writeln!(file, "{}", word).context("Failed to write the Forvo cache")?;
+            return Ok(None);
+        };
+        fs::create_dir_all(&self.cache)
+            .with_context(|| format!("Failed to create {}", self.cache.display()))?;
+        // Downloaded beside the clip, so an interrupted fetch leaves none.
+        let partial = path.with_extension("part");
+        curl(&pronunciation.pathmp3, Some(&partial))?;
+        fs::rename(&partial, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Some(path))
+    }
+}
+
+impl Speaker for Forvo {
+    fn speak(&mut self, text: &str) -> Result<()> {
+        match self.clip(text) {
+            Ok(Some(path)) => return self.player.play(&path),
+            Ok(None) => {}
+            Err(e) if self.fallback.is_some() => eprintln!("Warning: {:#}", e),
+            Err(e) => return Err(e),
+        }
+        match &mut self.fallback {
+            Some(fallback) => fallback.speak(text),
+            None => anyhow::bail!("Forvo has no pronunciation of {}", text),
+        }
+    }
+}
+
+/// Fetches `url`, into `output` if given, else returning the body.
+fn curl(url: &str, output: Option<&Path>) -> Result<Vec<u8>> {
+    let mut command = Command::new("curl");
+    command.args([
+        "--silent",
+        "--fail",
+        "--location",
+        "--max-time",
+        TIMEOUT_SECS,
+    ]);
+    if let Some(path) = output {
+        command.arg("--output").arg(path);
+    }
+    let result = command
+        .arg(url)
+        .output()
+        .context("Failed to run curl, which Forvo needs")?;
+    if !result.status.success() {
+        match result.status.code() {
+            Some(code) => anyhow::bail!("Forvo request failed (curl error {})", code),
+            None => anyhow::bail!("Forvo request was interrupted"),
+        }
+    }
+    Ok(result.stdout)
+}
+
+/// Percent-encodes `word` for a URL path.
+fn encode(word: &str) -> String {
+    word.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// `word` as a file name, without path separators.
+fn file_name(word: &str) -> String {
+    word.replace(['/', '\\', '\0'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl Speaker for Recorder {
+        fn speak(&mut self, text: &str) -> Result<()> {
+            self.0.borrow_mut().push(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn words_are_encoded_for_the_url() {
+        assert_eq!(encode("食べる"), "%E9%A3%9F%E3%81%B9%E3%82%8B");
+        assert_eq!(encode("a b/c"), "a%20b%2Fc");
+        assert_eq!(file_name("a/b"), "a_b");
+    }
+
+    #[test]
+    fn cached_clips_play_and_missing_words_fall_back() {
+        let cache = std::env::temp_dir().join(format!("ljp-forvo-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache);
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(cache.join("あ.mp3"), "").unwrap();
+        fs::write(cache.join(MISSING), "ゔ\n").unwrap();
+        let spoken = Rc::new(RefCell::new(Vec::new()));
+        let config = ForvoConfig {
+            key: "key".to_string(),
+        };
+        let mut forvo = Forvo::open(
+            &config,
+            cache.clone(),
+            Player::new("true").unwrap(),
+            Some(Box::new(Recorder(Rc::clone(&spoken)))),
+        )
+        .unwrap();
+
+        assert_eq!(forvo.clip("あ").unwrap(), Some(cache.join("あ.mp3")));
+        forvo.speak("あ").unwrap();
+        forvo.speak("ゔ").unwrap();
+        assert_eq!(*spoken.borrow(), ["ゔ"]);
+        fs::remove_dir_all(&cache).unwrap();
+    }
+}
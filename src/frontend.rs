@@ -0,0 +1,816 @@
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use std::{
+    collections::VecDeque,
+    io::{self, stdin, IsTerminal, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clipboard,
+    commands::CommandRegistry,
+    engine::{Input, Progress, Render, SessionEngine, SessionSummary, State},
+    graphics::ImageDir,
+    readline::LineEditor,
+    romaji::{self, Script},
+    schedule,
+    speech::Speaker,
+    theme::{paint, Theme},
+    StudyItem,
+};
+
+/// A presentation layer for study sessions. The session logic lives in
+/// `SessionEngine`; a frontend only shows what it is told and collects
+/// what the user types.
+pub trait Frontend {
+    /// Called with the session's progress just before each question.
+    fn set_progress(&mut self, _progress: Progress) {}
+    fn display_question(&mut self, item: &StudyItem) -> Result<()>;
+    /// Returns the next input for `item`, or for a whole board if `None`:
+    /// a line, `Timeout` if the time to answer ran out, or `Quit` once
+    /// input is exhausted.
+    fn read_input(&mut self, item: Option<&StudyItem>) -> Result<Input>;
+    fn show_feedback(&mut self, item: &StudyItem, correct: bool) -> Result<()>;
+    /// Shows anything that is neither a question nor feedback: help,
+    /// weights, notices and errors.
+    fn show_message(&mut self, render: &Render) -> Result<()>;
+    fn show_summary(&mut self, summary: &SessionSummary) -> Result<()>;
+    /// Called after feedback, before the next question is drawn.
+    fn pause(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs `engine` to completion, presenting it through `frontend`.
+pub fn run(engine: &mut SessionEngine, frontend: &mut dyn Frontend) -> Result<()> {
+    let mut renders = engine.start()?;
+    loop {
+        for render in &renders {
+            match render {
+                Render::Question(item) => {
+                    frontend.set_progress(engine.progress());
+                    frontend.display_question(item)?
+                }
+                Render::Correct(item) => frontend.show_feedback(item, true)?,
+                Render::Incorrect(item) => frontend.show_feedback(item, false)?,
+                _ => frontend.show_message(render)?,
+            }
+        }
+
+        let input = match engine.state() {
+            State::Finished => break,
+            State::ShowingFeedback => {
+                frontend.pause()?;
+                Input::Continue
+            }
+            State::AwaitingAnswer { item, .. } | State::AwaitingGrade { item } => {
+                frontend.read_input(Some(item))?
+            }
+            State::AwaitingMatches { .. } => frontend.read_input(None)?,
+        };
+        // Ctrl-C may have come while the frontend was busy, e.g. pausing.
+        let input = if INTERRUPTED.load(Ordering::SeqCst) {
+            Input::Quit
+        } else {
+            input
+        };
+
+        renders = engine.handle(input)?;
+    }
+
+    frontend.show_summary(engine.summary())
+}
+
+/// Set by Ctrl-C once `catch_interrupts` has been called.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes Ctrl-C end the session as `\q` does, so its summary is still
+/// shown and its progress saved. A second Ctrl-C exits at once.
+pub fn catch_interrupts() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+    })
+    .context("Failed to install the Ctrl-C handler")
+}
+
+/// How many of the most-missed items the end-of-session summary lists.
+const HARDEST_SHOWN: usize = 3;
+
+/// How long feedback stays on screen before the next question.
+#[derive(Debug, Clone, Copy)]
+pub enum Pause {
+    Millis(u64),
+    Key,
+}
+
+impl FromStr for Pause {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "key" => Ok(Pause::Key),
+            _ => s
+                .parse()
+                .map(Pause::Millis)
+                .map_err(|_| format!("expected milliseconds or 'key', got '{}'", s)),
+        }
+    }
+}
+
+/// How many terminal columns the fronts of a board are padded to.
+const BOARD_COLUMN: usize = 8;
+
+/// The columns `text` takes up in a terminal, counting CJK characters
+/// and kana as two.
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c >= '\u{1100}' { 2 } else { 1 })
+        .sum()
+}
+
+/// Registers the commands that only make sense in a terminal.
+pub fn register_commands(registry: &mut CommandRegistry) {
+    // The engine shows the question again after every command, which in
+    // --mode listen speaks it again.
+    registry.register(
+        "p",
+        "Repeat the prompt, replaying its audio in --mode listen",
+        |_, _| Ok(Vec::new()),
+    );
+    registry.register(
+        "c",
+        "Copy the current prompt to the clipboard",
+        |engine, _| {
+            Ok(engine
+                .current()
+                .map(|item| Render::Copy {
+                    label: "prompt",
+                    text: item.front.clone(),
+                })
+                .into_iter()
+                .collect())
+        },
+    );
+    registry.register(
+        "C",
+        "Copy the current answer to the clipboard",
+        |engine, _| {
+            Ok(engine
+                .current()
+                .map(|item| Render::Copy {
+                    label: "answer",
+                    text: item.back.clone(),
+                })
+                .into_iter()
+                .collect())
+        },
+    );
+}
+
+/// The plain line-based terminal frontend.
+pub struct CliFrontend {
+    pub images: Option<ImageDir>,
+    pub theme: Theme,
+    pub show_answer: bool,
+    pub echo_reading: bool,
+    pub pause: Option<Pause>,
+    /// Draws prompts at double height and width with extra spacing.
+    pub large_print: bool,
+    /// How long each answer may take before it counts as missed.
+    pub per_card: Option<Duration>,
+    /// Converts typed romaji to kana, live when stdin is a terminal.
+    pub ime: bool,
+    /// Speaks fronts instead of showing them.
+    pub speaker: Option<Box<dyn Speaker>>,
+    /// Says nothing about correct answers.
+    pub quiet: bool,
+    /// Replaces the input label, e.g. `[{index}/{total}] {front} ▸ `; see
+    /// [`fill_template`].
+    pub prompt_template: Option<String>,
+    /// Where the session stands, for `prompt_template`.
+    pub progress: Progress,
+    /// Shown with each question when cards are asked in more than one
+    /// direction, e.g. `[→ {answer}]`; see [`direction_label`].
+    pub direction_label: Option<String>,
+    /// Reads answers with line editing; only usable when stdin is a
+    /// terminal and neither `per_card` nor `ime` is set.
+    pub editor: Option<LineEditor>,
+}
+
+/// Lines of stdin, read on a background thread so that waiting for one
+/// can time out.
+fn stdin_lines() -> &'static Mutex<Receiver<io::Result<String>>> {
+    static LINES: OnceLock<Mutex<Receiver<io::Result<String>>>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdin().lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
+/// How often waiting for a line checks for Ctrl-C.
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+/// Waits for the next line of input, for at most `timeout` if given.
+fn read_line(timeout: Option<Duration>) -> Result<Input> {
+    let lines = stdin_lines()
+        .lock()
+        .map_err(|_| anyhow::anyhow!("stdin reader panicked"))?;
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Ok(Input::Quit);
+        }
+        let wait = match deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Ok(Input::Timeout);
+                }
+                left.min(INTERRUPT_POLL)
+            }
+            None => INTERRUPT_POLL,
+        };
+        match lines.recv_timeout(wait) {
+            Ok(line) => return Ok(Input::Line(line.context("Failed to read line from stdin")?)),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(Input::Quit),
+        }
+    }
+}
+
+/// How many seconds before running out the countdown is painted in the
+/// theme's `incorrect` style.
+const COUNTDOWN_WARNING: u64 = 3;
+
+/// The time left to answer, shown before the prompt and ticking down.
+struct Countdown<'a> {
+    deadline: Instant,
+    warning: &'a str,
+}
+
+impl Countdown<'_> {
+    fn left(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    fn render(&self) -> String {
+        let secs = self.left().as_secs_f64().ceil() as u64;
+        let text = format!("[{}s] ", secs);
+        match secs <= COUNTDOWN_WARNING {
+            true => paint(self.warning, &text),
+            false => text,
+        }
+    }
+}
+
+/// Reads a line in raw mode after `prompt` and the countdown, if any,
+/// which ticks down as the learner types. With a `script`, the romaji
+/// typed so far is shown as kana and answers are submitted as kana;
+/// commands, and everything without a script, as typed.
+fn read_raw(prompt: &str, script: Option<Script>, countdown: Option<Countdown>) -> Result<Input> {
+    terminal::enable_raw_mode().context("Failed to enable raw terminal mode")?;
+    let input = read_raw_line(prompt, script, countdown);
+    terminal::disable_raw_mode().context("Failed to disable raw terminal mode")?;
+    println!();
+    input
+}
+
+fn read_raw_line(
+    prompt: &str,
+    script: Option<Script>,
+    countdown: Option<Countdown>,
+) -> Result<Input> {
+    let mut typed = String::new();
+    let draw = |typed: &str| -> Result<()> {
+        let shown = match script.filter(|_| !typed.starts_with('\\')) {
+            Some(script) => romaji::preview(typed, script),
+            None => typed.to_string(),
+        };
+        let countdown = countdown
+            .as_ref()
+            .map(Countdown::render)
+            .unwrap_or_default();
+        print!("\r{}{}{}\x1b[K", countdown, prompt, shown);
+        io::stdout().flush().context("Failed to flush stdout")
+    };
+    draw(&typed)?;
+    loop {
+        if let Some(countdown) = &countdown {
+            let left = countdown.left();
+            if left.is_zero() {
+                return Ok(Input::Timeout);
+            }
+            // Wake up as the shown seconds change to redraw them.
+            let tick = left - Duration::from_secs(left.as_secs());
+            let tick = if tick.is_zero() {
+                Duration::from_secs(1)
+            } else {
+                tick
+            };
+            if !event::poll(tick).context("Failed to poll terminal")? {
+                if !countdown.left().is_zero() {
+                    draw(&typed)?;
+                }
+                continue;
+            }
+        }
+        let Event::Key(key) = event::read().context("Failed to read from terminal")? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let convert = script.filter(|_| !typed.starts_with('\\'));
+        match key.code {
+            KeyCode::Enter => {
+                return Ok(Input::Line(match convert {
+                    Some(script) => romaji::to_kana(&typed, script),
+                    None => typed,
+                }))
+            }
+            KeyCode::Char('c' | 'd') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Ok(Input::Quit)
+            }
+            KeyCode::Char(c) => typed.push(c),
+            KeyCode::Backspace => {
+                typed.pop();
+            }
+            _ => continue,
+        }
+        draw(&typed)?;
+    }
+}
+
+/// Fills the placeholders of a prompt template: `{index}` and `{total}`
+/// for progress (`total` is `-` in open-ended sessions), `{front}`,
+/// `{set}`, `{weight}` and `{label}`, the set's input label.
+pub fn fill_template(template: &str, item: &StudyItem, progress: Progress) -> String {
+    let total = progress.total.map_or("-".to_string(), |n| n.to_string());
+    let weight = progress.weight.map_or("-".to_string(), |w| w.to_string());
+    template
+        .replace("{index}", &progress.index.to_string())
+        .replace("{total}", &total)
+        .replace("{front}", &item.front)
+        .replace("{set}", &item.set)
+        .replace("{weight}", &weight)
+        .replace("{label}", item.label.as_deref().unwrap_or("|>"))
+}
+
+/// What kind of answer `back` is: `kanji` if it has any, else `kana` if
+/// it has any, else `romaji`.
+fn answer_kind(back: &str) -> &'static str {
+    let is_kanji = |c: char| ('\u{4E00}'..='\u{9FFF}').contains(&c) || c == '々';
+    let is_kana = |c: char| ('\u{3041}'..='\u{30FF}').contains(&c);
+    if back.chars().any(is_kanji) {
+        "kanji"
+    } else if back.chars().any(is_kana) {
+        "kana"
+    } else {
+        "romaji"
+    }
+}
+
+/// Fills `{answer}` in a direction label template with what kind of
+/// answer `item` expects, so reversed cards say to type kana.
+pub fn direction_label(template: &str, item: &StudyItem) -> String {
+    template.replace("{answer}", answer_kind(&item.back))
+}
+
+impl Frontend for CliFrontend {
+    fn set_progress(&mut self, progress: Progress) {
+        self.progress = progress;
+    }
+
+    fn display_question(&mut self, item: &StudyItem) -> Result<()> {
+        let template_shows_front = self
+            .prompt_template
+            .as_ref()
+            .is_some_and(|template| template.contains("{front}"));
+        if let Some(speaker) = &mut self.speaker {
+            println!("\n{}", paint(&self.theme.front, "(listen)"));
+            if let Err(e) = speaker.speak_item(item) {
+                eprintln!("Warning: {:#}", e);
+            }
+        } else if self.large_print {
+            // DEC double-height lines: the top and bottom halves of the
+            // glyphs are drawn on two consecutive lines.
+            let front = paint(&self.theme.front, &item.front);
+            println!("\n\n\x1b#3{}\n\x1b#4{}\n", front, front);
+        } else if template_shows_front {
+            println!();
+        } else {
+            println!("\n{}", paint(&self.theme.front, &item.front));
+        }
+        let label = self
+            .direction_label
+            .as_ref()
+            .map(|template| paint(&self.theme.prompt, &direction_label(template, item)));
+        match (item.prompt.as_deref(), label.as_deref()) {
+            (Some(prompt), Some(label)) => println!("{} {}", prompt, label),
+            (Some(line), None) | (None, Some(line)) => println!("{}", line),
+            (None, None) => {}
+        }
+        Ok(())
+    }
+
+    fn read_input(&mut self, item: Option<&StudyItem>) -> Result<Input> {
+        let label = item.and_then(|item| item.label.as_deref()).unwrap_or("|>");
+        let (raw, styled) = match (&self.prompt_template, item) {
+            (Some(template), Some(item)) => {
+                let prompt = fill_template(template, item, self.progress);
+                (prompt.clone(), paint(&self.theme.prompt, &prompt))
+            }
+            _ => (
+                format!("{} ", label),
+                format!("{} ", paint(&self.theme.prompt, label)),
+            ),
+        };
+        if let Some(editor) = &mut self.editor {
+            return editor.read(&raw, &styled);
+        }
+        let script = item.filter(|_| self.ime).map(|item| Script::of(&item.back));
+        if (self.ime || self.per_card.is_some()) && stdin().is_terminal() {
+            let countdown = self.per_card.map(|per_card| Countdown {
+                deadline: Instant::now() + per_card,
+                warning: &self.theme.incorrect,
+            });
+            return read_raw(&styled, script, countdown);
+        }
+
+        match self.per_card {
+            Some(per_card) => print!("[{}s] {}", per_card.as_secs_f64(), styled),
+            None => print!("{}", styled),
+        }
+        io::stdout().flush().context("Failed to flush stdout")?;
+        Ok(match (read_line(self.per_card)?, script) {
+            (Input::Line(line), Some(script)) if !line.trim_start().starts_with('\\') => {
+                Input::Line(romaji::to_kana(line.trim(), script))
+            }
+            (input, _) => input,
+        })
+    }
+
+    fn show_feedback(&mut self, item: &StudyItem, correct: bool) -> Result<()> {
+        let theme = &self.theme;
+        match (correct, self.echo_reading, self.show_answer) {
+            (true, _, _) if self.quiet => {}
+            (true, true, _) => println!(
+                "{} {} = {}",
+                paint(&theme.correct, "Correct!"),
+                item.front,
+                paint(&theme.answer, &item.back)
+            ),
+            (true, false, _) => println!("{}", paint(&theme.correct, "Correct!")),
+            (false, _, true) => println!(
+                "{} The correct answer is: {}",
+                paint(&theme.incorrect, "Incorrect."),
+                paint(&theme.answer, &item.back)
+            ),
+            (false, _, false) => println!("{}", paint(&theme.incorrect, "Incorrect.")),
+        }
+
+        if self.large_print {
+            println!();
+        }
+
+        if let Some(Err(e)) = self.images.as_ref().map(|images| images.show(&item.front)) {
+            eprintln!("Warning: {:#}", e);
+        }
+        Ok(())
+    }
+
+    fn show_message(&mut self, render: &Render) -> Result<()> {
+        match render {
+            Render::Help(lines) => {
+                println!("Available commands:");
+                for (usage, description) in lines {
+                    println!("  {:<8} - {}", usage, description);
+                }
+            }
+            Render::Schedule(rows) => println!("{}", schedule::table(rows)),
+            Render::Choices(choices) => {
+                for (n, choice) in choices.iter().enumerate() {
+                    println!("  {}) {}", n + 1, choice);
+                }
+            }
+            Render::Reveal(item) => {
+                println!("{}", paint(&self.theme.answer, &item.back));
+                println!("1) again  2) hard  3) good  4) easy");
+            }
+            Render::Misses(missed) if missed.is_empty() => println!("No misses."),
+            Render::Misses(missed) => {
+                println!("Missed:");
+                for (item, answer) in missed {
+                    println!("  {} / {} (answered '{}')", item.front, item.back, answer);
+                }
+            }
+            Render::Board { fronts, backs } => {
+                println!();
+                for (i, (front, back)) in fronts.iter().zip(backs).enumerate() {
+                    let letter = char::from(b'a' + i as u8);
+                    let pad = " ".repeat(BOARD_COLUMN.saturating_sub(width(front)));
+                    println!(
+                        "  {}) {}{} {}) {}",
+                        i + 1,
+                        paint(&self.theme.front, front),
+                        pad,
+                        letter,
+                        back
+                    );
+                }
+                println!("Pair them with one letter per number, e.g. bdac");
+            }
+            Render::Matches(matches) => {
+                for (i, (item, back, correct)) in matches.iter().enumerate() {
+                    if *correct {
+                        println!(
+                            "{} {}) {} = {}",
+                            paint(&self.theme.correct, "✓"),
+                            i + 1,
+                            item.front,
+                            back
+                        );
+                    } else {
+                        println!(
+                            "{} {}) {} = {} ({})",
+                            paint(&self.theme.incorrect, "✗"),
+                            i + 1,
+                            item.front,
+                            back,
+                            paint(&self.theme.answer, &item.back)
+                        );
+                    }
+                }
+            }
+            Render::Copy { label, text } => {
+                clipboard::copy(text)?;
+                println!("Copied {} to clipboard.", label);
+            }
+            Render::Invalid(e) => eprintln!("Invalid command: {}. Type \\q to quit.", e),
+            Render::Notice(message) => println!("{}", message),
+            Render::NoItems => println!("No items available for study. Exiting session."),
+            Render::Quit => println!("Quitting..."),
+            Render::Question(_) | Render::Correct(_) | Render::Incorrect(_) => {}
+        }
+        Ok(())
+    }
+
+    fn show_summary(&mut self, summary: &SessionSummary) -> Result<()> {
+        if summary.answered > 0 {
+            let secs = summary.elapsed.as_secs();
+            println!(
+                "Score: {}/{} ({:.0}%) in {}m{:02}s",
+                summary.correct,
+                summary.answered,
+                summary.accuracy() * 100.0,
+                secs / 60,
+                secs % 60
+            );
+            let hardest: Vec<String> = summary
+                .hardest(HARDEST_SHOWN)
+                .iter()
+                .map(|(item, misses)| format!("{} / {} ({}×)", item.front, item.back, misses))
+                .collect();
+            if !hardest.is_empty() {
+                println!("Hardest: {}", hardest.join(", "));
+            }
+        }
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<()> {
+        match self.pause {
+            Some(Pause::Millis(ms)) => thread::sleep(Duration::from_millis(ms)),
+            Some(Pause::Key) => {
+                let prompt = "(press Enter to continue)";
+                if let Some(editor) = &mut self.editor {
+                    editor.read(prompt, prompt)?;
+                    return Ok(());
+                }
+                // Reading stdin on the background thread would race the
+                // raw-mode reads of the IME and countdown.
+                if (self.ime || self.per_card.is_some()) && stdin().is_terminal() {
+                    read_raw(prompt, None, None)?;
+                } else {
+                    print!("{}", prompt);
+                    io::stdout().flush().context("Failed to flush stdout")?;
+                    read_line(None)?;
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// A frontend for other programs to drive: each question is printed as a
+/// single line with its front, each answer is read as a single line, and
+/// the session ends with its result as one line of JSON. Notices and
+/// errors go to stderr so they cannot be mistaken for questions.
+#[derive(Debug, Default)]
+pub struct BatchFrontend;
+
+impl Frontend for BatchFrontend {
+    fn display_question(&mut self, item: &StudyItem) -> Result<()> {
+        println!("{}", item.front);
+        Ok(())
+    }
+
+    fn read_input(&mut self, _item: Option<&StudyItem>) -> Result<Input> {
+        io::stdout().flush().context("Failed to flush stdout")?;
+        read_line(None)
+    }
+
+    fn show_feedback(&mut self, item: &StudyItem, correct: bool) -> Result<()> {
+        if correct {
+            println!("correct");
+        } else {
+            println!("incorrect {}", item.back);
+        }
+        Ok(())
+    }
+
+    fn show_message(&mut self, render: &Render) -> Result<()> {
+        match render {
+            Render::Help(lines) => {
+                for (usage, description) in lines {
+                    println!("{}\t{}", usage, description);
+                }
+            }
+            Render::Schedule(rows) => {
+                let columns = schedule::columns(rows);
+                for row in rows {
+                    let cells: Vec<String> = columns
+                        .iter()
+                        .map(|&column| row.cell(column).unwrap_or_default())
+                        .collect();
+                    println!("{}\t{}\t{}", row.front, row.back, cells.join("\t"));
+                }
+            }
+            Render::Choices(choices) => {
+                for (n, choice) in choices.iter().enumerate() {
+                    println!("{}\t{}", n + 1, choice);
+                }
+            }
+            Render::Reveal(item) => println!("{}", item.back),
+            Render::Board { fronts, backs } => {
+                for (i, (front, back)) in fronts.iter().zip(backs).enumerate() {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        i + 1,
+                        front,
+                        char::from(b'a' + i as u8),
+                        back
+                    );
+                }
+            }
+            Render::Matches(matches) => {
+                for (item, _, correct) in matches {
+                    if *correct {
+                        println!("correct {}", item.front);
+                    } else {
+                        println!("incorrect {} {}", item.front, item.back);
+                    }
+                }
+            }
+            Render::Copy { text, .. } => clipboard::copy(text)?,
+            Render::Invalid(e) => eprintln!("Invalid command: {}", e),
+            Render::Notice(message) => eprintln!("{}", message),
+            // The final result covers these.
+            Render::Misses(_) | Render::NoItems | Render::Quit => {}
+            Render::Question(_) | Render::Correct(_) | Render::Incorrect(_) => {}
+        }
+        Ok(())
+    }
+
+    fn show_summary(&mut self, summary: &SessionSummary) -> Result<()> {
+        let missed: Vec<_> = summary
+            .missed
+            .iter()
+            .map(|(item, answer)| {
+                serde_json::json!({ "front": item.front, "back": item.back, "answer": answer })
+            })
+            .collect();
+        let result = serde_json::json!({
+            "answered": summary.answered,
+            "correct": summary.correct,
+            "accuracy": summary.accuracy(),
+            "elapsed_secs": summary.elapsed.as_secs_f64(),
+            "missed": missed,
+        });
+        println!("{}", result);
+        Ok(())
+    }
+}
+
+/// A frontend that answers from a script instead of a terminal and keeps
+/// everything it was shown, so whole sessions can be driven from code.
+/// Once the script runs out it quits.
+#[derive(Debug, Default)]
+pub struct ScriptedFrontend {
+    script: VecDeque<Input>,
+    /// Every question, feedback and message shown, in order. Feedback is
+    /// kept as `Render::Correct` or `Render::Incorrect`.
+    pub shown: Vec<Render>,
+    /// The progress passed before each question.
+    pub progress: Vec<Progress>,
+    pub summary: Option<SessionSummary>,
+}
+
+impl ScriptedFrontend {
+    /// Returns a frontend that types `lines` one at a time.
+    pub fn new<I, S>(lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::from_inputs(lines.into_iter().map(|line| Input::Line(line.into())))
+    }
+
+    /// Returns a frontend that gives `inputs` in order, e.g. to include
+    /// timeouts.
+    pub fn from_inputs(inputs: impl IntoIterator<Item = Input>) -> Self {
+        Self {
+            script: inputs.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// The fronts of the questions asked, in order.
+    pub fn questions(&self) -> Vec<&str> {
+        self.shown
+            .iter()
+            .filter_map(|render| match render {
+                Render::Question(item) => Some(item.front.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Frontend for ScriptedFrontend {
+    fn set_progress(&mut self, progress: Progress) {
+        self.progress.push(progress);
+    }
+
+    fn display_question(&mut self, item: &StudyItem) -> Result<()> {
+        self.shown.push(Render::Question(item.clone()));
+        Ok(())
+    }
+
+    fn read_input(&mut self, _item: Option<&StudyItem>) -> Result<Input> {
+        Ok(self.script.pop_front().unwrap_or(Input::Quit))
+    }
+
+    fn show_feedback(&mut self, item: &StudyItem, correct: bool) -> Result<()> {
+        self.shown.push(if correct {
+            Render::Correct(item.clone())
+        } else {
+            Render::Incorrect(item.clone())
+        });
+        Ok(())
+    }
+
+    fn show_message(&mut self, render: &Render) -> Result<()> {
+        self.shown.push(render.clone());
+        Ok(())
+    }
+
+    fn show_summary(&mut self, summary: &SessionSummary) -> Result<()> {
+        self.summary = Some(summary.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn direction_labels_name_the_expected_answer() {
+        let label = |back: &str| direction_label("[→ {answer}]", &StudyItem::new("?", back));
+        assert_eq!(label("shi"), "[→ romaji]");
+        assert_eq!(label("し"), "[→ kana]");
+        assert_eq!(label("シー"), "[→ kana]");
+        assert_eq!(label("時々"), "[→ kanji]");
+    }
+}
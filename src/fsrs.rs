@@ -0,0 +1,274 @@
+//! The FSRS-4.5 memory model: each reviewed item has a stability (days
+//! until recall drops to 90%) and a difficulty, and the item least likely
+//! to be recalled right now is asked next.
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Grade;
+
+/// The number of FSRS-4.5 model parameters.
+pub const PARAM_COUNT: usize = 17;
+
+/// The published FSRS-4.5 defaults, for learners without optimized
+/// parameters of their own.
+pub const DEFAULT_PARAMS: [f64; PARAM_COUNT] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072,
+    0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+const DECAY: f64 = -0.5;
+/// Chosen so that retrievability is 90% after `stability` days.
+const FACTOR: f64 = 19.0 / 81.0;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+/// FSRS rates a review from 1 (Again) to 4 (Easy).
+const GOOD: f64 = 3.0;
+
+fn rating(grade: Grade) -> f64 {
+    match grade {
+        Grade::Again => 1.0,
+        Grade::Hard => 2.0,
+        Grade::Good => GOOD,
+        Grade::Easy => 4.0,
+    }
+}
+
+/// Parses a comma-separated list of parameters, e.g. exported from an
+/// FSRS optimizer.
+pub fn parse_params(s: &str) -> Result<[f64; PARAM_COUNT], String> {
+    let params = s
+        .split(',')
+        .map(|param| {
+            param
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}' is not a number", param.trim()))
+        })
+        .collect::<Result<Vec<f64>, _>>()?;
+    params.try_into().map_err(|params: Vec<f64>| {
+        format!("expected {} parameters, got {}", PARAM_COUNT, params.len())
+    })
+}
+
+/// What FSRS knows about a reviewed item.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Memory {
+    /// Days after a review until recall drops to 90%: the interval.
+    pub stability: f64,
+    /// How hard the item is to remember, from 1 to 10.
+    pub difficulty: f64,
+    /// Seconds since the Unix epoch.
+    pub last_review: u64,
+}
+
+impl Memory {
+    /// When recall drops to 90%, in seconds since the Unix epoch.
+    pub fn due(&self) -> u64 {
+        self.last_review + (self.stability * SECONDS_PER_DAY) as u64
+    }
+}
+
+/// FSRS state for every item in a session, indexed like its items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fsrs {
+    params: [f64; PARAM_COUNT],
+    memories: Vec<Option<Memory>>,
+}
+
+impl Fsrs {
+    pub fn new(params: [f64; PARAM_COUNT], len: usize) -> Self {
+        Self {
+            params,
+            memories: vec![None; len],
+        }
+    }
+
+    /// Keeps only the items at `indices`, in that order.
+    pub fn select(&mut self, indices: &[usize]) {
+        self.memories = indices.iter().map(|&i| self.memories[i]).collect();
+    }
+
+    /// What is known about item `index`, unless it was never reviewed.
+    pub fn memory(&self, index: usize) -> Option<Memory> {
+        self.memories[index]
+    }
+
+    /// Replaces what is known about item `index`, e.g. with what an
+    /// earlier session saved.
+    pub fn remember(&mut self, index: usize, memory: Memory) {
+        self.memories[index] = Some(memory);
+    }
+
+    /// The probability of recalling item `index` now. Unseen items have
+    /// retrievability 0 so they are introduced before anything is
+    /// repeated.
+    pub fn retrievability(&self, index: usize) -> f64 {
+        match self.memories[index] {
+            Some(memory) => retrievability(elapsed_days(memory.last_review), memory.stability),
+            None => 0.0,
+        }
+    }
+
+    /// The chance of forgetting each item as a percentage, which serves as
+    /// the item's weight.
+    pub fn weights(&self) -> Vec<u32> {
+        (0..self.memories.len())
+            .map(|i| ((1.0 - self.retrievability(i)) * 100.0).round() as u32)
+            .collect()
+    }
+
+    /// Returns the items ordered from least to most likely to be recalled.
+    /// `order` is shuffled beforehand so ties are broken at random.
+    pub fn by_retrievability(&self, mut order: Vec<usize>) -> Vec<usize> {
+        let retrievability: Vec<f64> = (0..self.memories.len())
+            .map(|i| self.retrievability(i))
+            .collect();
+        order.sort_by(|&a, &b| retrievability[a].total_cmp(&retrievability[b]));
+        order
+    }
+
+    /// Forgets everything about item `index`, as if it was never seen.
+    pub fn reset(&mut self, index: usize) {
+        self.memories[index] = None;
+    }
+
+    /// Updates item `index` after an answer.
+    pub fn review(&mut self, index: usize, grade: Grade) {
+        self.review_at(index, grade, now());
+    }
+
+    /// Updates item `index` after an answer given at `at`, in seconds
+    /// since the Unix epoch, e.g. to replay reviews done elsewhere.
+    pub fn review_at(&mut self, index: usize, grade: Grade, at: u64) {
+        let w = &self.params;
+        let correct = grade.passed();
+        // Hard shortens the new interval and Easy lengthens it.
+        let bonus = match grade {
+            Grade::Hard => w[15],
+            Grade::Easy => w[16],
+            Grade::Again | Grade::Good => 1.0,
+        };
+        let grade = rating(grade);
+        let memory = match self.memories[index] {
+            None => Memory {
+                stability: w[grade as usize - 1],
+                difficulty: self.initial_difficulty(grade),
+                last_review: at,
+            },
+            Some(memory) => {
+                let elapsed = at.saturating_sub(memory.last_review) as f64 / SECONDS_PER_DAY;
+                let r = retrievability(elapsed, memory.stability);
+                let (s, d) = (memory.stability, memory.difficulty);
+                let stability = if correct {
+                    s * (w[8].exp()
+                        * (11.0 - d)
+                        * s.powf(-w[9])
+                        * ((w[10] * (1.0 - r)).exp() - 1.0)
+                        * bonus
+                        + 1.0)
+                } else {
+                    w[11]
+                        * d.powf(-w[12])
+                        * ((s + 1.0).powf(w[13]) - 1.0)
+                        * (w[14] * (1.0 - r)).exp()
+                };
+                let difficulty = d - w[6] * (grade - GOOD);
+                let difficulty = w[7] * self.initial_difficulty(GOOD) + (1.0 - w[7]) * difficulty;
+                Memory {
+                    stability,
+                    difficulty: difficulty.clamp(1.0, 10.0),
+                    last_review: at,
+                }
+            }
+        };
+        self.memories[index] = Some(memory);
+    }
+
+    fn initial_difficulty(&self, grade: f64) -> f64 {
+        (self.params[4] - (grade - GOOD) * self.params[5]).clamp(1.0, 10.0)
+    }
+}
+
+fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+    (1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn elapsed_days(since: u64) -> f64 {
+    now().saturating_sub(since) as f64 / SECONDS_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stability(fsrs: &Fsrs, index: usize) -> f64 {
+        fsrs.memories[index].unwrap().stability
+    }
+
+    #[test]
+    fn parses_a_full_set_of_params() {
+        let params = DEFAULT_PARAMS.map(|p| p.to_string()).join(", ");
+        assert_eq!(parse_params(&params), Ok(DEFAULT_PARAMS));
+        assert!(parse_params("1, 2, 3").unwrap_err().contains("expected 17"));
+        assert!(parse_params("1, x").unwrap_err().contains("'x'"));
+    }
+
+    #[test]
+    fn unseen_items_come_first() {
+        let mut fsrs = Fsrs::new(DEFAULT_PARAMS, 3);
+        fsrs.review(1, Grade::Good);
+        assert_eq!(fsrs.retrievability(0), 0.0);
+        assert!(fsrs.retrievability(1) > 0.99);
+        assert_eq!(fsrs.weights(), [100, 0, 100]);
+        assert_eq!(fsrs.by_retrievability(vec![1, 0, 2]), [0, 2, 1]);
+    }
+
+    #[test]
+    fn first_reviews_start_from_the_grade_params() {
+        let mut fsrs = Fsrs::new(DEFAULT_PARAMS, 4);
+        for (index, grade) in [Grade::Again, Grade::Hard, Grade::Good, Grade::Easy]
+            .into_iter()
+            .enumerate()
+        {
+            fsrs.review(index, grade);
+            assert_eq!(stability(&fsrs, index), DEFAULT_PARAMS[index]);
+        }
+    }
+
+    #[test]
+    fn recall_grows_stability_and_lapses_shrink_it() {
+        let mut fsrs = Fsrs::new(DEFAULT_PARAMS, 1);
+        let days_ago = |fsrs: &mut Fsrs, days: f64| {
+            let memory = fsrs.memories[0].as_mut().unwrap();
+            memory.last_review -= (days * SECONDS_PER_DAY) as u64;
+        };
+        fsrs.review(0, Grade::Good);
+        let first = stability(&fsrs, 0);
+        days_ago(&mut fsrs, first);
+        assert!((fsrs.retrievability(0) - 0.9).abs() < 0.01);
+        fsrs.review(0, Grade::Good);
+        let recalled = stability(&fsrs, 0);
+        assert!(recalled > first);
+        fsrs.review(0, Grade::Again);
+        assert!(stability(&fsrs, 0) < recalled);
+        let difficulty = fsrs.memories[0].unwrap().difficulty;
+        assert!((1.0..=10.0).contains(&difficulty));
+    }
+
+    #[test]
+    fn reset_and_select_follow_the_items() {
+        let mut fsrs = Fsrs::new(DEFAULT_PARAMS, 3);
+        fsrs.review(2, Grade::Easy);
+        fsrs.select(&[2, 0]);
+        assert!(fsrs.memories[0].is_some() && fsrs.memories[1].is_none());
+        fsrs.reset(0);
+        assert_eq!(fsrs.weights(), [100, 100]);
+    }
+}
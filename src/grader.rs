@@ -0,0 +1,147 @@
+//! How typed answers are compared with the expected back.
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::romaji::{self, Script};
+
+/// Decides whether an answer counts as the expected back.
+pub trait Grader {
+    fn accepts(&self, expected: &str, answer: &str) -> bool;
+}
+
+/// Accepts only the back exactly as written.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Exact;
+
+impl Grader for Exact {
+    fn accepts(&self, expected: &str, answer: &str) -> bool {
+        answer == expected
+    }
+}
+
+/// Ignores letter case, e.g. `Tokyo` for `tokyo`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CaseInsensitive;
+
+impl Grader for CaseInsensitive {
+    fn accepts(&self, expected: &str, answer: &str) -> bool {
+        answer.to_lowercase() == expected.to_lowercase()
+    }
+}
+
+/// Accepts any romanization that spells the same kana, e.g. `si` for
+/// `shi` or `tu` for `tsu`, and じ and ず for ぢ and づ, which sound the
+/// same.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Romanization;
+
+fn same_sound(text: &str) -> String {
+    romaji::to_kana(text, Script::Hiragana)
+        .chars()
+        .map(|c| match c {
+            'ぢ' => 'じ',
+            'づ' => 'ず',
+            'ヂ' => 'ジ',
+            'ヅ' => 'ズ',
+            _ => c,
+        })
+        .collect()
+}
+
+impl Grader for Romanization {
+    fn accepts(&self, expected: &str, answer: &str) -> bool {
+        answer == expected || same_sound(answer) == same_sound(expected)
+    }
+}
+
+/// Forgives one typo per four characters of the back, ignoring case, so
+/// short answers such as single kana must still be exact.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fuzzy;
+
+/// The number of single-character insertions, deletions and substitutions
+/// that turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+impl Grader for Fuzzy {
+    fn accepts(&self, expected: &str, answer: &str) -> bool {
+        let (expected, answer) = (expected.to_lowercase(), answer.to_lowercase());
+        edit_distance(&expected, &answer) <= expected.chars().count() / 4
+    }
+}
+
+/// The built-in graders, by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Grading {
+    /// The back exactly as written
+    #[default]
+    Exact,
+    /// Any letter case
+    CaseInsensitive,
+    /// Any romanization of the same kana, e.g. si for shi
+    Romaji,
+    /// A typo per four characters
+    Fuzzy,
+}
+
+impl Grading {
+    pub fn grader(self) -> &'static dyn Grader {
+        match self {
+            Grading::Exact => &Exact,
+            Grading::CaseInsensitive => &CaseInsensitive,
+            Grading::Romaji => &Romanization,
+            Grading::Fuzzy => &Fuzzy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_and_case_insensitive() {
+        assert!(Grading::Exact.grader().accepts("Tokyo", "Tokyo"));
+        assert!(!Grading::Exact.grader().accepts("Tokyo", "tokyo"));
+        assert!(Grading::CaseInsensitive.grader().accepts("Tokyo", "tOKYO"));
+        assert!(!Grading::CaseInsensitive.grader().accepts("Tokyo", "Kyoto"));
+    }
+
+    #[test]
+    fn romaji_accepts_other_spellings_of_the_same_kana() {
+        let romaji = Grading::Romaji.grader();
+        assert!(romaji.accepts("shi", "si"));
+        assert!(romaji.accepts("tsu", "tu"));
+        assert!(romaji.accepts("ji", "di"));
+        assert!(romaji.accepts("zu", "du"));
+        assert!(!romaji.accepts("shi", "chi"));
+    }
+
+    #[test]
+    fn fuzzy_forgives_a_typo_per_four_characters() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        let fuzzy = Grading::Fuzzy.grader();
+        assert!(fuzzy.accepts("tabemasu", "Tabemasa"));
+        assert!(fuzzy.accepts("tabemasu", "tabemas"));
+        assert!(!fuzzy.accepts("tabemasu", "tabenai"));
+        assert!(!fuzzy.accepts("ka", "ki"));
+    }
+}
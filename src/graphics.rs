@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+const KITTY_CHUNK: usize = 4096;
+
+/// Inline image protocols ljp knows how to speak. Terminals that only
+/// support sixel need the image re-encoded, so they are treated as having
+/// no graphics support and simply don't show images.
+#[derive(Debug, Clone, Copy)]
+pub enum Protocol {
+    Kitty,
+    Iterm2,
+}
+
+/// Guesses the terminal's inline image protocol from its environment.
+pub fn detect() -> Option<Protocol> {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+
+    if !var("KITTY_WINDOW_ID").is_empty()
+        || var("TERM").contains("kitty")
+        || var("TERM").contains("ghostty")
+    {
+        return Some(Protocol::Kitty);
+    }
+    match var("TERM_PROGRAM").as_str() {
+        "iTerm.app" | "WezTerm" => return Some(Protocol::Iterm2),
+        "ghostty" => return Some(Protocol::Kitty),
+        _ => {}
+    }
+    if var("LC_TERMINAL") == "iTerm2" {
+        return Some(Protocol::Iterm2);
+    }
+
+    None
+}
+
+/// A directory of PNG images named after item fronts (e.g. `あ.png`),
+/// shown inline after each answer when the terminal supports it.
+#[derive(Debug, Clone)]
+pub struct ImageDir {
+    protocol: Protocol,
+    dir: PathBuf,
+}
+
+impl ImageDir {
+    /// Returns `None` when the terminal cannot display images.
+    pub fn new(dir: PathBuf) -> Option<Self> {
+        detect().map(|protocol| Self { protocol, dir })
+    }
+
+    /// Displays the image for `front`, doing nothing if there is none.
+    pub fn show(&self, front: &str) -> Result<()> {
+        let path = self.dir.join(format!("{}.png", front));
+        if !path.is_file() {
+            return Ok(());
+        }
+        show(self.protocol, &path)
+    }
+}
+
+/// Writes the PNG at `path` to stdout using `protocol`.
+pub fn show(protocol: Protocol, path: &Path) -> Result<()> {
+    let data =
+        fs::read(path).with_context(|| format!("Failed to read image {}", path.display()))?;
+    let encoded = STANDARD.encode(&data);
+    let mut stdout = io::stdout().lock();
+
+    match protocol {
+        Protocol::Kitty => {
+            let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(i + 1 < chunks.len());
+                let control = if i == 0 {
+                    format!("a=T,f=100,m={}", more)
+                } else {
+                    format!("m={}", more)
+                };
+                write!(stdout, "\x1b_G{};", control)?;
+                stdout.write_all(chunk)?;
+                write!(stdout, "\x1b\\")?;
+            }
+        }
+        Protocol::Iterm2 => {
+            write!(
+                stdout,
+                "\x1b]1337;File=inline=1;size={}:{}\x07",
+                data.len(),
+                encoded
+            )?;
+        }
+    }
+
+    writeln!(stdout)?;
+    stdout.flush().context("Failed to flush stdout")
+}
@@ -0,0 +1,78 @@
+use chrono::{Datelike, Days, NaiveDate};
+
+/// Shades from no reviews to the busiest day.
+const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+const WEEKDAYS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", "Sun"];
+
+fn reviews_on(days: &[(NaiveDate, u32)], day: NaiveDate) -> u32 {
+    days.iter()
+        .find(|(d, _)| *d == day)
+        .map(|&(_, reviews)| reviews)
+        .unwrap_or(0)
+}
+
+/// Renders the last `weeks` weeks up to `today` as a GitHub-style grid:
+/// one row per weekday, one column per week, each cell shaded by the
+/// number of reviews relative to the busiest day shown.
+pub fn render(days: &[(NaiveDate, u32)], today: NaiveDate, weeks: u32) -> String {
+    let days_since_monday = u64::from(today.weekday().num_days_from_monday());
+    let first = today - Days::new(u64::from(weeks - 1) * 7 + days_since_monday);
+    let max = days
+        .iter()
+        .filter(|(day, _)| *day >= first && *day <= today)
+        .map(|&(_, reviews)| reviews)
+        .max()
+        .unwrap_or(0);
+
+    let mut grid = String::new();
+    for (weekday, label) in WEEKDAYS.iter().enumerate() {
+        grid.push_str(&format!("{:<4}", label));
+        for week in 0..u64::from(weeks) {
+            let day = first + Days::new(week * 7 + weekday as u64);
+            if day > today {
+                break;
+            }
+            let reviews = reviews_on(days, day);
+            let shade = if reviews == 0 {
+                0
+            } else {
+                // 1..=4 by quarter of the busiest day.
+                1 + ((reviews - 1) * 4 / max.max(1)).min(3) as usize
+            };
+            grid.push(SHADES[shade]);
+        }
+        grid.push('\n');
+    }
+    grid
+}
+
+/// Returns `(current, longest)` streaks of consecutive study days. The
+/// current streak still counts if today has no reviews yet but yesterday
+/// did.
+pub fn streaks(days: &[(NaiveDate, u32)], today: NaiveDate) -> (u32, u32) {
+    let mut studied: Vec<NaiveDate> = days
+        .iter()
+        .filter(|&&(_, reviews)| reviews > 0)
+        .map(|&(day, _)| day)
+        .collect();
+    studied.sort();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in &studied {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    let yesterday = today.pred_opt();
+    let current = match previous {
+        Some(last) if last == today || Some(last) == yesterday => run,
+        _ => 0,
+    };
+    (current, longest)
+}
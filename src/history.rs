@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    events::{Answer, Answers, Event, Listener},
+    profiles,
+};
+
+/// Returns the default location of the review history for `profile`.
+pub fn default_path(profile: Option<&str>) -> Result<PathBuf> {
+    Ok(profiles::state_dir(profile)?.join("history.jsonl"))
+}
+
+/// One answer, as written to the history file.
+#[derive(Debug, Serialize)]
+struct Line<'a> {
+    timestamp: String,
+    set: &'a str,
+    front: &'a str,
+    back: &'a str,
+    answer: &'a str,
+    correct: bool,
+    latency_ms: u64,
+}
+
+/// One answer, as read back from the history file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Review {
+    pub timestamp: String,
+    #[serde(default)]
+    pub set: String,
+    pub front: String,
+    pub back: String,
+    pub answer: String,
+    pub correct: bool,
+    #[serde(default)]
+    pub latency_ms: u64,
+}
+
+impl Review {
+    /// When it was answered, in local time, if the timestamp is readable.
+    pub fn at(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|at| at.with_timezone(&Local))
+    }
+}
+
+/// Returns every review at `path`, oldest first. Lines that cannot be
+/// parsed are skipped.
+pub fn read(path: &Path) -> Result<Vec<Review>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open history {}", path.display()))?;
+    let mut reviews = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.context("Failed to read history")?;
+        if let Ok(review) = serde_json::from_str::<Review>(&line) {
+            reviews.push(review);
+        }
+    }
+    Ok(reviews)
+}
+
+/// Returns the reviews at `path` that were missed on or after `since`,
+/// oldest first. Lines that cannot be parsed are skipped.
+pub fn missed_since(path: &Path, since: NaiveDate) -> Result<Vec<Review>> {
+    Ok(read(path)?
+        .into_iter()
+        .filter(|review| !review.correct && review.at().is_some_and(|at| at.date_naive() >= since))
+        .collect())
+}
+
+/// Appends every answer to a JSONL file, one event per line. Answers are
+/// written once they can no longer be undone.
+pub struct HistoryListener {
+    path: PathBuf,
+    file: File,
+    answers: Answers,
+}
+
+impl HistoryListener {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open history {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            answers: Answers::default(),
+        })
+    }
+
+    fn write(&mut self, answer: Answer) {
+        let line = Line {
+            timestamp: answer.at.to_rfc3339(),
+            set: &answer.item.set,
+            front: &answer.item.front,
+            back: &answer.item.back,
+            answer: &answer.answer,
+            correct: answer.correct,
+            latency_ms: answer.response.as_millis() as u64,
+        };
+        let written = serde_json::to_string(&line)
+            .context("Failed to serialize history event")
+            .and_then(|line| {
+                writeln!(self.file, "{}", line)
+                    .with_context(|| format!("Failed to write history {}", self.path.display()))
+            });
+        if let Err(err) = written {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+}
+
+impl Listener for HistoryListener {
+    fn on_event(&mut self, event: &Event) {
+        for answer in self.answers.follow(event) {
+            self.write(answer);
+        }
+    }
+}
+
+impl Drop for HistoryListener {
+    fn drop(&mut self) {
+        for answer in self.answers.flush() {
+            self.write(answer);
+        }
+    }
+}
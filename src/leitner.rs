@@ -0,0 +1,94 @@
+//! Classic Leitner boxes: a correct answer moves an item up one box and a
+//! miss sends it back to box 1. Lower boxes are asked more often.
+use serde::{Deserialize, Serialize};
+
+use crate::Grade;
+
+/// The highest box an item can reach.
+pub const BOXES: u8 = 5;
+
+/// The box of every item in a session, indexed like its items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Leitner {
+    boxes: Vec<u8>,
+}
+
+impl Leitner {
+    /// Starts every item in box 1.
+    pub fn new(len: usize) -> Self {
+        Self {
+            boxes: vec![1; len],
+        }
+    }
+
+    pub fn boxes(&self) -> &[u8] {
+        &self.boxes
+    }
+
+    /// Puts item `index` in box `b`, kept between 1 and `BOXES`.
+    pub fn set_box(&mut self, index: usize, b: u8) {
+        self.boxes[index] = b.clamp(1, BOXES);
+    }
+
+    /// Keeps only the items at `indices`, in that order.
+    pub fn select(&mut self, indices: &[usize]) {
+        self.boxes = indices.iter().map(|&i| self.boxes[i]).collect();
+    }
+
+    /// Sampling weights that halve with each box, so box 1 is asked
+    /// sixteen times as often as box 5.
+    pub fn weights(&self) -> Vec<u32> {
+        self.boxes.iter().map(|&b| 1 << (BOXES - b)).collect()
+    }
+
+    /// Puts item `index` back in box 1.
+    pub fn reset(&mut self, index: usize) {
+        self.boxes[index] = 1;
+    }
+
+    /// Moves item `index` after an answer: `Hard` keeps it in its box and
+    /// `Easy` skips a box.
+    pub fn review(&mut self, index: usize, grade: Grade) {
+        let b = &mut self.boxes[index];
+        *b = match grade {
+            Grade::Again => 1,
+            Grade::Hard => *b,
+            Grade::Good => (*b + 1).min(BOXES),
+            Grade::Easy => (*b + 2).min(BOXES),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grades_move_items_between_boxes() {
+        let mut leitner = Leitner::new(1);
+        let mut after = |grade| {
+            leitner.review(0, grade);
+            leitner.boxes()[0]
+        };
+        assert_eq!(after(Grade::Good), 2);
+        assert_eq!(after(Grade::Hard), 2);
+        assert_eq!(after(Grade::Easy), 4);
+        assert_eq!(after(Grade::Easy), BOXES);
+        assert_eq!(after(Grade::Good), BOXES);
+        assert_eq!(after(Grade::Again), 1);
+    }
+
+    #[test]
+    fn lower_boxes_weigh_more() {
+        let mut leitner = Leitner::new(3);
+        leitner.review(1, Grade::Good);
+        leitner.review(2, Grade::Easy);
+        leitner.review(2, Grade::Easy);
+        assert_eq!(leitner.weights(), [16, 8, 1]);
+
+        leitner.select(&[2, 0]);
+        assert_eq!(leitner.boxes(), [BOXES, 1]);
+        leitner.reset(0);
+        assert_eq!(leitner.boxes(), [1, 1]);
+    }
+}
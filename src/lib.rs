@@ -0,0 +1,1108 @@
+//! The core of ljp: study sets, weighted sampling and grading, usable from
+//! frontends other than the bundled CLI and web server.
+//!
+//! ```no_run
+//! use ljp::StudySession;
+//!
+//! let mut session = StudySession::builder().set("hiragana").focus(1.5).build()?;
+//! for _ in 0..10 {
+//!     let Some(item) = session.next_card() else {
+//!         break;
+//!     };
+//!     println!("{}", item.front);
+//!     let correct = session.answer("a")?;
+//!     println!("{}", if correct { "Correct!" } else { "Incorrect." });
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+use clap::ValueEnum;
+use rand::distr::weighted::WeightedIndex;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path, rc::Rc};
+use tracing::{debug, info};
+
+pub mod anki;
+pub mod audio;
+pub mod backup;
+pub mod clipboard;
+pub mod cloze;
+pub mod commands;
+pub mod config;
+pub mod dictionary;
+pub mod engine;
+pub mod error;
+pub mod events;
+pub mod explain;
+pub mod forecast;
+pub mod forvo;
+pub mod frontend;
+pub mod fsrs;
+pub mod grader;
+pub mod graphics;
+pub mod heatmap;
+pub mod history;
+pub mod leitner;
+pub mod middleware;
+pub mod mnemonics;
+pub mod notes;
+pub mod paths;
+pub mod plugin;
+pub mod profiles;
+pub mod quiz;
+pub mod readline;
+pub mod replay;
+pub mod report;
+pub mod retention;
+pub mod romaji;
+pub mod schedule;
+pub mod script;
+pub mod serve;
+pub mod sets;
+pub mod speech;
+pub mod store;
+pub mod suggest;
+pub mod sync;
+pub mod theme;
+
+pub use error::LjpError;
+use error::Result;
+use fsrs::{Fsrs, Memory};
+use grader::{Grader, Grading};
+use leitner::Leitner;
+use sets::reversed::{BothWays, Reversed};
+pub use sets::SetRegistry;
+
+/// A single card: what is shown and the answer expected for it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StudyItem {
+    /// Identifies the item across sessions; `set:front:back` unless the
+    /// set gives one.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    pub front: String,
+    pub back: String,
+    /// Other answers that count as right, e.g. `ji` for `zi`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternates: Vec<String>,
+    /// The name of the set the item was loaded from.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub set: String,
+    /// How the question is phrased, e.g. "Reading?", from the set's
+    /// `prompt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// What the input field is labelled, from the set's `input_label`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// A memory aid shown on request, e.g. "し looks like a fishing hook".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<String>,
+    /// Free-form labels for filtering, e.g. `n5` or `verb`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Anything else worth knowing, shown by `\i`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Paths or URLs of images and audio that go with the item.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<String>,
+}
+
+impl StudyItem {
+    pub fn new(front: impl Into<String>, back: impl Into<String>) -> Self {
+        Self {
+            front: front.into(),
+            back: back.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The back followed by any alternates: every answer that counts.
+    pub fn answers(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.back.as_str()).chain(self.alternates.iter().map(String::as_str))
+    }
+}
+
+/// The items being studied and their weights. Items are drawn at random
+/// in proportion to their weight: every answer makes all items heavier and
+/// a correct answer resets that item to 1, so missed and unseen items come
+/// back more often.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudySession {
+    sets: Vec<String>,
+    items: Vec<StudyItem>,
+    #[serde(default)]
+    weights: Vec<u32>,
+    #[serde(default = "default_focus")]
+    focus: f64,
+    #[serde(default)]
+    similar: Vec<Vec<String>>,
+    /// How answers are compared, overriding `set_gradings`.
+    #[serde(default)]
+    grading: Option<Grading>,
+    /// How answers are compared per set, where the set names one.
+    #[serde(default)]
+    set_gradings: HashMap<String, Grading>,
+    /// Graders sets bring of their own, such as deck scripts. They are
+    /// not saved, so resumed sessions fall back to `set_gradings`.
+    #[serde(skip)]
+    set_graders: SetGraders,
+    #[serde(default)]
+    scheduler: Scheduler,
+    #[serde(skip)]
+    last: Option<usize>,
+    #[serde(skip)]
+    current: Option<usize>,
+    /// The weights and scheduler state before the last grade, for `undo`.
+    #[serde(skip)]
+    previous: Option<(Vec<u32>, Scheduler)>,
+    #[serde(skip)]
+    dist: Option<WeightedIndex<f64>>,
+    #[serde(skip, default = "entropy_rng")]
+    rng: StdRng,
+}
+
+impl StudySession {
+    /// Starts building a session; see [`SessionBuilder`].
+    pub fn builder() -> SessionBuilder {
+        SessionBuilder::default()
+    }
+
+    /// Loads the named sets with their default options, failing with
+    /// [`LjpError::UnknownSet`] if any is unknown.
+    pub fn new(sets: Vec<String>) -> Result<Self> {
+        Self::from_loaders(resolve_sets(&SetRegistry::with_builtins(), sets)?)
+    }
+
+    /// Loads every item from `loaders`, with weights from their
+    /// difficulty and prompts from their metadata.
+    pub fn from_loaders(loaders: Vec<Box<dyn StudySetLoader>>) -> Result<Self> {
+        Self::from_loaders_with_rng(loaders, entropy_rng())
+    }
+
+    /// Like [`StudySession::from_loaders`], but loads with `rng` and keeps
+    /// it for the session's random choices, so a seeded `rng` makes both
+    /// repeatable.
+    pub fn from_loaders_with_rng(
+        loaders: Vec<Box<dyn StudySetLoader>>,
+        mut rng: StdRng,
+    ) -> Result<Self> {
+        let mut resolved_sets = Vec::new();
+        let mut items = Vec::new();
+        let mut weights = Vec::new();
+        let mut set_gradings = HashMap::new();
+        let mut set_graders = SetGraders::default();
+        for resolved_set in loaders {
+            let set = resolved_set.name();
+            if let Some(grading) = resolved_set.grading() {
+                set_gradings.insert(set.clone(), grading);
+            }
+            if let Some(grader) = resolved_set.grader() {
+                set_graders.0.insert(set.clone(), grader);
+            }
+            let (prompt, label) = (resolved_set.prompt(), resolved_set.input_label());
+            let set_items: Vec<_> = resolved_set
+                .load(&mut rng)?
+                .into_iter()
+                .map(|item| StudyItem {
+                    set: set.clone(),
+                    prompt: item.prompt.or_else(|| prompt.clone()),
+                    label: item.label.or_else(|| label.clone()),
+                    ..item
+                })
+                .flat_map(cloze::expand)
+                .map(|mut item| {
+                    if item.id.is_empty() {
+                        item.id = format!("{}:{}:{}", set, item.front, item.back);
+                    }
+                    item
+                })
+                .collect();
+            debug!(set = %set, items = set_items.len(), "loaded set");
+            weights.extend(set_items.iter().map(|item| resolved_set.difficulty(item)));
+            items.extend(set_items);
+            resolved_sets.push(set);
+        }
+
+        let mut session = Self {
+            sets: resolved_sets,
+            items,
+            weights,
+            focus: default_focus(),
+            similar: sets::similarity_groups(),
+            grading: None,
+            set_gradings,
+            set_graders,
+            scheduler: Scheduler::Weights,
+            last: None,
+            current: None,
+            previous: None,
+            dist: None,
+            rng,
+        };
+        session.sync_dist()?;
+
+        Ok(session)
+    }
+
+    /// Reads a session written by [`StudySession::save`], with the items,
+    /// weights and scheduler state it had then.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).map_err(|source| LjpError::Io {
+            action: "read session",
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_json(&data).map_err(|reason| LjpError::StateCorruption {
+            path: path.to_path_buf(),
+            reason,
+        })
+    }
+
+    /// Parses a session serialized as by [`StudySession::save`], returning
+    /// why it is unusable if it is.
+    pub fn from_json(data: &str) -> std::result::Result<Self, String> {
+        let mut session: Self = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        if session.weights.len() != session.items.len() {
+            return Err(format!(
+                "{} weights for {} items",
+                session.weights.len(),
+                session.items.len()
+            ));
+        }
+        session.sync_dist().map_err(|e| e.to_string())?;
+        Ok(session)
+    }
+
+    /// Writes the session as JSON so it can be resumed later.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let data = serde_json::to_string_pretty(self).map_err(|e| LjpError::StateCorruption {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+        fs::write(path, data).map_err(|source| LjpError::Io {
+            action: "write session",
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// The names of the sets that were loaded.
+    pub fn sets(&self) -> &[String] {
+        &self.sets
+    }
+
+    pub fn items(&self) -> &[StudyItem] {
+        &self.items
+    }
+
+    /// The current weight of each item, in the same order as `items`.
+    pub fn weights(&self) -> &[u32] {
+        &self.weights
+    }
+
+    /// Draws the next card and makes it the one `answer` grades. Returns
+    /// `None` when the session has no items.
+    pub fn next_card(&mut self) -> Option<&StudyItem> {
+        let index = self.sample()?;
+        self.current = Some(index);
+        self.items.get(index)
+    }
+
+    /// Grades `answer` against the card from the last `next_card` and
+    /// updates the weights. Errors if there is no card to answer.
+    pub fn answer(&mut self, answer: &str) -> Result<bool> {
+        let index = self.current.ok_or_else(|| self.no_card())?;
+        let correct = self.is_correct(&self.items[index], answer);
+        self.grade(if correct { Grade::Good } else { Grade::Again })?;
+        Ok(correct)
+    }
+
+    /// Whether `answer` is right for `item`, without touching any weights.
+    pub fn is_correct(&self, item: &StudyItem, answer: &str) -> bool {
+        let set_grader = self
+            .set_graders
+            .0
+            .get(&item.set)
+            .filter(|_| self.grading.is_none());
+        let grader = match set_grader {
+            Some(grader) => grader.as_ref(),
+            None => self
+                .grading
+                .or_else(|| self.set_gradings.get(&item.set).copied())
+                .unwrap_or_default()
+                .grader(),
+        };
+        item.answers()
+            .any(|expected| grader.accepts(expected, answer))
+    }
+
+    /// Compares every answer with `grading`, whatever its set asks for.
+    pub fn set_grading(&mut self, grading: Grading) {
+        self.grading = Some(grading);
+    }
+
+    /// Returns up to `n` distinct items in random order, regardless of
+    /// their weights.
+    pub fn draw_unique(&mut self, n: usize) -> Vec<StudyItem> {
+        let mut items: Vec<StudyItem> = self
+            .items
+            .choose_multiple(&mut self.rng, n)
+            .cloned()
+            .collect();
+        items.shuffle(&mut self.rng);
+        items
+    }
+
+    /// Grades `item` as if it had been drawn with `next_card`, for modes
+    /// that ask several items at once. Errors if it is not in the session.
+    pub fn grade_item(&mut self, item: &StudyItem, grade: Grade) -> Result<()> {
+        let index = self
+            .items
+            .iter()
+            .position(|other| other.front == item.front && other.back == item.back)
+            .ok_or_else(|| LjpError::UnknownItem(item.front.clone()))?;
+        self.current = Some(index);
+        self.grade(grade)
+    }
+
+    /// Grades several items as one step, which `undo` takes back as a
+    /// whole, e.g. the pairs of a match board.
+    pub fn grade_items(&mut self, grades: &[(StudyItem, Grade)]) -> Result<()> {
+        let previous = (self.weights.clone(), self.scheduler.clone());
+        for (item, grade) in grades {
+            self.grade_item(item, *grade)?;
+        }
+        self.previous = Some(previous);
+        Ok(())
+    }
+
+    /// Updates the weights of the card from the last `next_card` with a
+    /// grade the learner gave themselves. Errors if there is no card to
+    /// grade.
+    pub fn grade(&mut self, grade: Grade) -> Result<()> {
+        let index = self.current.take().ok_or_else(|| self.no_card())?;
+        let before = self.weights[index];
+        self.previous = Some((self.weights.clone(), self.scheduler.clone()));
+        match &mut self.scheduler {
+            Scheduler::Weights => {
+                match grade {
+                    Grade::Again => {}
+                    Grade::Hard => self.weights[index] = (self.weights[index] / 2).max(1),
+                    Grade::Good => self.weights[index] = 1,
+                    Grade::Easy => self.weights[index] = 0,
+                }
+                self.increment()?;
+            }
+            Scheduler::Fsrs(fsrs) => {
+                fsrs.review(index, grade);
+                self.weights = fsrs.weights();
+            }
+            Scheduler::Leitner(leitner) => {
+                leitner.review(index, grade);
+                self.weights = leitner.weights();
+                self.sync_dist()?;
+            }
+        }
+        debug!(
+            front = %self.items[index].front,
+            %grade,
+            before,
+            after = self.weights[index],
+            "graded item"
+        );
+        Ok(())
+    }
+
+    /// Why there is no card to answer: the session is empty, or none has
+    /// been drawn yet.
+    fn no_card(&self) -> LjpError {
+        if self.items.is_empty() {
+            LjpError::EmptySession
+        } else {
+            LjpError::NoCardDrawn
+        }
+    }
+
+    /// Restores the weights from before the last grade. Only one grade
+    /// can be taken back; returns whether there was one.
+    pub fn undo(&mut self) -> Result<bool> {
+        let Some((weights, scheduler)) = self.previous.take() else {
+            return Ok(false);
+        };
+        self.weights = weights;
+        self.scheduler = scheduler;
+        self.sync_dist()?;
+        debug!("undid last grade");
+        Ok(true)
+    }
+
+    /// Puts back the card from the last `next_card` without grading it.
+    /// With plain weights it gets one heavier, so it comes back a little
+    /// sooner.
+    pub fn skip(&mut self) -> Result<()> {
+        let Some(index) = self.current.take() else {
+            return Ok(());
+        };
+        if matches!(self.scheduler, Scheduler::Weights) {
+            self.weights[index] += 1;
+            self.sync_dist()?;
+        }
+        debug!(front = %self.items[index].front, "skipped item");
+        Ok(())
+    }
+
+    /// Starts the items for which `reset` returns true over: a weight of
+    /// 1, box 1 or no FSRS history. Returns how many there were.
+    pub fn reset(&mut self, mut reset: impl FnMut(&StudyItem) -> bool) -> Result<usize> {
+        let indices: Vec<usize> = (0..self.items.len())
+            .filter(|&i| reset(&self.items[i]))
+            .collect();
+        for &index in &indices {
+            match &mut self.scheduler {
+                Scheduler::Weights => self.weights[index] = 1,
+                Scheduler::Fsrs(fsrs) => fsrs.reset(index),
+                Scheduler::Leitner(leitner) => leitner.reset(index),
+            }
+        }
+        match &self.scheduler {
+            Scheduler::Weights => {}
+            Scheduler::Fsrs(fsrs) => self.weights = fsrs.weights(),
+            Scheduler::Leitner(leitner) => self.weights = leitner.weights(),
+        }
+        self.sync_dist()?;
+        debug!(items = indices.len(), "reset items");
+        Ok(indices.len())
+    }
+
+    /// Makes every random choice from now on follow from `seed`, so the
+    /// same seed and answers give the same session.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// The session's random number generator, for shuffling that should
+    /// follow its seed.
+    pub(crate) fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Sets how strongly heavy items dominate sampling: each weight is
+    /// raised to `focus`, so 0 samples uniformly and 2 strongly favours
+    /// weak items.
+    pub fn set_focus(&mut self, focus: f64) -> Result<()> {
+        self.focus = focus;
+        self.sync_dist()
+    }
+
+    /// Replaces the weight of every item for which `weight` returns one,
+    /// e.g. to carry progress over from earlier sessions.
+    pub fn seed_weights<E: From<LjpError>>(
+        &mut self,
+        mut weight: impl FnMut(&StudyItem) -> Result<Option<u32>, E>,
+    ) -> Result<(), E> {
+        let mut seeded_items = 0;
+        for (item, w) in self.items.iter().zip(self.weights.iter_mut()) {
+            if let Some(seeded) = weight(item)? {
+                *w = seeded;
+                seeded_items += 1;
+            }
+        }
+        debug!(items = seeded_items, "seeded weights");
+        Ok(self.sync_dist()?)
+    }
+
+    /// Schedules cards with FSRS instead of weighted sampling. Weights
+    /// then show each item's chance of being forgotten, in percent.
+    pub fn use_fsrs(&mut self, params: [f64; fsrs::PARAM_COUNT]) {
+        let fsrs = Fsrs::new(params, self.items.len());
+        self.weights = fsrs.weights();
+        self.scheduler = Scheduler::Fsrs(fsrs);
+        self.dist = None;
+    }
+
+    /// Schedules cards with Leitner boxes, every item starting in box 1.
+    /// Weights then follow from each item's box.
+    pub fn use_leitner(&mut self) -> Result<()> {
+        let leitner = Leitner::new(self.items.len());
+        self.weights = leitner.weights();
+        self.scheduler = Scheduler::Leitner(leitner);
+        self.sync_dist()
+    }
+
+    /// Each item's Leitner box, when scheduling with Leitner boxes.
+    pub fn boxes(&self) -> Option<&[u8]> {
+        match &self.scheduler {
+            Scheduler::Leitner(leitner) => Some(leitner.boxes()),
+            _ => None,
+        }
+    }
+
+    /// What FSRS knows about item `index`, when scheduling with FSRS and
+    /// the item was reviewed.
+    pub fn memory(&self, index: usize) -> Option<Memory> {
+        match &self.scheduler {
+            Scheduler::Fsrs(fsrs) => fsrs.memory(index),
+            _ => None,
+        }
+    }
+
+    /// Restores the FSRS memory of every item for which `memory` returns
+    /// one, when scheduling with FSRS, e.g. to carry progress over from
+    /// earlier sessions.
+    pub fn seed_memories<E>(
+        &mut self,
+        mut memory: impl FnMut(&StudyItem) -> Result<Option<Memory>, E>,
+    ) -> Result<(), E> {
+        let Scheduler::Fsrs(fsrs) = &mut self.scheduler else {
+            return Ok(());
+        };
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(memory) = memory(item)? {
+                fsrs.remember(index, memory);
+            }
+        }
+        self.weights = fsrs.weights();
+        Ok(())
+    }
+
+    /// Restores the Leitner box of every item for which `boxed` returns
+    /// one, when scheduling with Leitner boxes.
+    pub fn seed_boxes<E: From<LjpError>>(
+        &mut self,
+        mut boxed: impl FnMut(&StudyItem) -> Result<Option<u8>, E>,
+    ) -> Result<(), E> {
+        let Scheduler::Leitner(leitner) = &mut self.scheduler else {
+            return Ok(());
+        };
+        for (index, item) in self.items.iter().enumerate() {
+            if let Some(b) = boxed(item)? {
+                leitner.set_box(index, b);
+            }
+        }
+        self.weights = leitner.weights();
+        Ok(self.sync_dist()?)
+    }
+
+    /// Rebuilds the sampling distribution from the weights. FSRS picks
+    /// cards itself and needs none.
+    fn sync_dist(&mut self) -> Result<()> {
+        if !matches!(self.scheduler, Scheduler::Fsrs(_)) && !self.weights.is_empty() {
+            let weights = self.weights.iter().map(|&w| f64::from(w).powf(self.focus));
+            self.dist = Some(WeightedIndex::new(weights)?);
+        }
+        Ok(())
+    }
+
+    fn increment(&mut self) -> Result<()> {
+        self.weights.iter_mut().for_each(|w| *w += 1);
+        self.sync_dist()?;
+        Ok(())
+    }
+
+    /// Narrows the session to the `n` heaviest items, breaking ties at
+    /// random.
+    pub fn keep_weakest(&mut self, n: usize) -> Result<()> {
+        let mut indices: Vec<usize> = (0..self.items.len()).collect();
+        indices.shuffle(&mut self.rng);
+        indices.sort_by_key(|&i| std::cmp::Reverse(self.weights[i]));
+        indices.truncate(n);
+        self.select(&indices)
+    }
+
+    /// Gives the items that have no mnemonic the one `mnemonic` returns,
+    /// if any. Returns how many were given one.
+    pub fn fill_mnemonics<E>(
+        &mut self,
+        mut mnemonic: impl FnMut(&StudyItem) -> Result<Option<String>, E>,
+    ) -> Result<usize, E> {
+        let mut filled = 0;
+        for item in self.items.iter_mut().filter(|item| item.mnemonic.is_none()) {
+            item.mnemonic = mnemonic(item)?;
+            filled += usize::from(item.mnemonic.is_some());
+        }
+        Ok(filled)
+    }
+
+    /// Keeps only the items for which `keep` returns true.
+    pub fn retain(&mut self, mut keep: impl FnMut(&StudyItem) -> bool) -> Result<()> {
+        let indices: Vec<usize> = (0..self.items.len())
+            .filter(|&i| keep(&self.items[i]))
+            .collect();
+        self.select(&indices)
+    }
+
+    /// Keeps only the items at `indices`, in that order, with their
+    /// weights and scheduling state.
+    fn select(&mut self, indices: &[usize]) -> Result<()> {
+        self.items = indices.iter().map(|&i| self.items[i].clone()).collect();
+        self.weights = indices.iter().map(|&i| self.weights[i]).collect();
+        match &mut self.scheduler {
+            Scheduler::Weights => {}
+            Scheduler::Fsrs(fsrs) => fsrs.select(indices),
+            Scheduler::Leitner(leitner) => leitner.select(indices),
+        }
+        self.last = None;
+        self.current = None;
+        self.dist = None;
+        self.sync_dist()
+    }
+
+    /// Returns up to `n` candidate backs for `item` in random order: its
+    /// own back plus distractors from the same set, preferring items that
+    /// look like it. No back appears twice.
+    pub fn choices(&mut self, item: &StudyItem, n: usize) -> Vec<String> {
+        let similar = |other: &StudyItem| {
+            self.similar.iter().any(|group| {
+                (group.contains(&item.front) && group.contains(&other.front))
+                    || (group.contains(&item.back) && group.contains(&other.back))
+            })
+        };
+        let mut candidates: Vec<&StudyItem> = self
+            .items
+            .iter()
+            .filter(|other| other.set == item.set && other.back != item.back)
+            .collect();
+        candidates.shuffle(&mut self.rng);
+        // Stable, so similar items stay shuffled among themselves.
+        candidates.sort_by_key(|other| !similar(other));
+
+        let mut choices = vec![item.back.clone()];
+        for other in candidates {
+            if choices.len() >= n {
+                break;
+            }
+            if !choices.contains(&other.back) {
+                choices.push(other.back.clone());
+            }
+        }
+        choices.shuffle(&mut self.rng);
+        choices
+    }
+
+    /// Adds groups of fronts that should not be asked back-to-back.
+    pub fn add_similar(&mut self, groups: Vec<Vec<String>>) {
+        self.similar.extend(groups);
+    }
+
+    /// Whether asking `index` right after the previous item would pair two
+    /// easily confused items.
+    fn interferes(&self, index: usize) -> bool {
+        let Some(last) = self.last.filter(|&last| last != index) else {
+            return false;
+        };
+        let (a, b) = (&self.items[last].front, &self.items[index].front);
+        self.similar
+            .iter()
+            .any(|group| group.contains(a) && group.contains(b))
+    }
+
+    fn sample(&mut self) -> Option<usize> {
+        let index = match &self.scheduler {
+            Scheduler::Weights | Scheduler::Leitner(_) => {
+                let dist = self.dist.as_ref()?;
+                let mut index = dist.sample(&mut self.rng);
+                for _ in 0..MAX_RESAMPLES {
+                    if !self.interferes(index) {
+                        break;
+                    }
+                    debug!(front = %self.items[index].front, "redrawing similar item");
+                    index = dist.sample(&mut self.rng);
+                }
+                index
+            }
+            Scheduler::Fsrs(fsrs) => {
+                let mut order: Vec<usize> = (0..self.items.len()).collect();
+                order.shuffle(&mut self.rng);
+                let order = fsrs.by_retrievability(order);
+                order
+                    .iter()
+                    .copied()
+                    .find(|&i| self.last != Some(i) && !self.interferes(i))
+                    .or_else(|| order.first().copied())?
+            }
+        };
+        debug!(
+            front = %self.items[index].front,
+            weight = self.weights[index],
+            total_weight = self.weights.iter().map(|&w| u64::from(w)).sum::<u64>(),
+            "drew item"
+        );
+        self.last = Some(index);
+        Some(index)
+    }
+}
+
+/// Which way round cards are asked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// Front to back, e.g. kana to romaji
+    #[default]
+    Forward,
+    /// Back to front, e.g. romaji to kana
+    Reverse,
+    /// Both ways, as separate cards with their own weights
+    Both,
+}
+
+/// How the next card is chosen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Algorithm {
+    /// Weighted random sampling that favours missed and unseen items
+    #[default]
+    Weights,
+    /// FSRS: ask the item least likely to be recalled
+    Fsrs,
+    /// Leitner boxes: lower boxes are asked more often
+    Leitner,
+}
+
+/// How well a card was recalled, from a self-graded review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    /// Every grade but `Again` counts as a correct answer.
+    pub fn passed(self) -> bool {
+        self != Grade::Again
+    }
+}
+
+impl std::str::FromStr for Grade {
+    type Err = String;
+
+    /// Accepts a grade's name, its first letter or its number from 1 to 4.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "1" | "a" | "again" => Ok(Grade::Again),
+            "2" | "h" | "hard" => Ok(Grade::Hard),
+            "3" | "g" | "good" => Ok(Grade::Good),
+            "4" | "e" | "easy" => Ok(Grade::Easy),
+            _ => Err(format!("'{}' is not a grade", s.trim())),
+        }
+    }
+}
+
+impl std::fmt::Display for Grade {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Grade::Again => "again",
+            Grade::Hard => "hard",
+            Grade::Good => "good",
+            Grade::Easy => "easy",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The state behind the session's [`Algorithm`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+enum Scheduler {
+    #[default]
+    Weights,
+    Fsrs(Fsrs),
+    Leitner(Leitner),
+}
+
+/// Each set's own grader, by set name.
+#[derive(Clone, Default)]
+struct SetGraders(HashMap<String, Rc<dyn Grader>>);
+
+impl std::fmt::Debug for SetGraders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// Decides whether an item stays in a session being built.
+type ItemFilter = Box<dyn Fn(&StudyItem) -> bool>;
+
+/// Configures a [`StudySession`] before its sets are loaded.
+pub struct SessionBuilder {
+    sets: Vec<String>,
+    loaders: Vec<Box<dyn StudySetLoader>>,
+    algorithm: Algorithm,
+    fsrs_params: [f64; fsrs::PARAM_COUNT],
+    direction: Direction,
+    reverse: Option<Vec<String>>,
+    focus: f64,
+    weakest: Option<usize>,
+    seed: Option<u64>,
+    grading: Option<Grading>,
+    similar: Vec<Vec<String>>,
+    filters: Vec<ItemFilter>,
+    registry: Option<SetRegistry>,
+}
+
+impl Default for SessionBuilder {
+    fn default() -> Self {
+        Self {
+            sets: Vec::new(),
+            loaders: Vec::new(),
+            algorithm: Algorithm::default(),
+            fsrs_params: fsrs::DEFAULT_PARAMS,
+            direction: Direction::default(),
+            reverse: None,
+            focus: default_focus(),
+            weakest: None,
+            seed: None,
+            grading: None,
+            similar: Vec::new(),
+            filters: Vec::new(),
+            registry: None,
+        }
+    }
+}
+
+impl SessionBuilder {
+    /// Adds a set by name, looked up in the builder's [`SetRegistry`].
+    pub fn set(mut self, name: impl Into<String>) -> Self {
+        self.sets.push(name.into());
+        self
+    }
+
+    pub fn sets(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.sets.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Looks set names up in `registry` rather than among the built-in
+    /// sets and decks, e.g. to include plugin sets.
+    pub fn registry(mut self, registry: SetRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Adds a set that is not in the registry, such as a user's deck.
+    pub fn loader(mut self, loader: impl StudySetLoader + 'static) -> Self {
+        self.loaders.push(Box::new(loader));
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Replaces the default FSRS parameters with optimized ones.
+    pub fn fsrs_params(mut self, params: [f64; fsrs::PARAM_COUNT]) -> Self {
+        self.fsrs_params = params;
+        self
+    }
+
+    /// Asks every set in `direction`, or only those named with `reverse`.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Asks the named sets back to front, or every set if `sets` is
+    /// empty. With a `direction` other than forward, applies that
+    /// direction to the named sets instead.
+    pub fn reverse(mut self, sets: Vec<String>) -> Self {
+        self.reverse = Some(sets);
+        self
+    }
+
+    /// See [`StudySession::set_focus`].
+    pub fn focus(mut self, focus: f64) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// Seeds the session's random choices, including the items generated
+    /// sets load; see [`StudySession::seed`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Compares answers with `grading`; see [`StudySession::set_grading`].
+    pub fn grading(mut self, grading: Grading) -> Self {
+        self.grading = Some(grading);
+        self
+    }
+
+    /// Keeps only the `n` heaviest items.
+    pub fn weakest(mut self, n: usize) -> Self {
+        self.weakest = Some(n);
+        self
+    }
+
+    /// Adds groups of easily confused fronts on top of the bundled ones.
+    pub fn similar(mut self, groups: Vec<Vec<String>>) -> Self {
+        self.similar.extend(groups);
+        self
+    }
+
+    /// Keeps only the items for which `keep` returns true. Filters are
+    /// applied before `weakest`.
+    pub fn only(mut self, keep: impl Fn(&StudyItem) -> bool + 'static) -> Self {
+        self.filters.push(Box::new(keep));
+        self
+    }
+
+    pub fn build(self) -> Result<StudySession> {
+        let registry = self.registry.unwrap_or_else(SetRegistry::with_builtins);
+        let mut loaders = resolve_sets(&registry, self.sets)?;
+        loaders.extend(self.loaders);
+        let direction = match (self.direction, &self.reverse) {
+            (Direction::Forward, Some(_)) => Direction::Reverse,
+            (direction, _) => direction,
+        };
+        let only = self.reverse.unwrap_or_default();
+        loaders = loaders
+            .into_iter()
+            .map(|loader| -> Box<dyn StudySetLoader> {
+                if !only.is_empty() && !only.contains(&loader.name()) {
+                    return loader;
+                }
+                match direction {
+                    Direction::Forward => loader,
+                    Direction::Reverse => Box::new(Reversed(loader)),
+                    Direction::Both => Box::new(BothWays(loader)),
+                }
+            })
+            .collect();
+        let rng = self.seed.map_or_else(entropy_rng, StdRng::seed_from_u64);
+        let mut session = StudySession::from_loaders_with_rng(loaders, rng)?;
+        if let Some(grading) = self.grading {
+            session.set_grading(grading);
+        }
+        session.set_focus(self.focus)?;
+        if !self.filters.is_empty() {
+            session.retain(|item| self.filters.iter().all(|keep| keep(item)))?;
+            debug!(items = session.items.len(), "applied filters");
+        }
+        if let Some(n) = self.weakest {
+            session.keep_weakest(n)?;
+            debug!(items = session.items.len(), "kept weakest");
+        }
+        session.add_similar(self.similar);
+        match self.algorithm {
+            Algorithm::Weights => {}
+            Algorithm::Fsrs => session.use_fsrs(self.fsrs_params),
+            Algorithm::Leitner => session.use_leitner()?,
+        }
+        info!(
+            sets = ?session.sets,
+            items = session.items.len(),
+            algorithm = ?self.algorithm,
+            ?direction,
+            focus = self.focus,
+            "built session"
+        );
+        Ok(session)
+    }
+}
+
+/// How many times sampling retries to avoid asking similar items in a row
+/// before giving up, so tiny sessions made of one group still progress.
+const MAX_RESAMPLES: usize = 10;
+
+fn default_focus() -> f64 {
+    1.0
+}
+
+fn entropy_rng() -> StdRng {
+    StdRng::from_rng(&mut rand::rng())
+}
+
+/// A source of study items, such as a bundled kana table or a generator.
+pub trait StudySetLoader {
+    fn name(&self) -> String;
+    /// Returns the set's items. Sets that generate items draw from `rng`,
+    /// so a seeded session loads the same items every time.
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>>;
+    /// The starting weight for `item`, so sets can front-load items that
+    /// learners typically find hard.
+    fn difficulty(&self, _item: &StudyItem) -> u32 {
+        1
+    }
+    /// How questions from this set are phrased, shown alongside each
+    /// front by every frontend.
+    fn prompt(&self) -> Option<String> {
+        None
+    }
+    /// The label for the answer input, replacing the default `|>`.
+    fn input_label(&self) -> Option<String> {
+        None
+    }
+    /// The prompt when the set is studied back to front with `--reverse`.
+    fn reverse_prompt(&self) -> Option<String> {
+        None
+    }
+    /// How answers to the set's items are compared, unless the session
+    /// says otherwise.
+    fn grading(&self) -> Option<Grading> {
+        None
+    }
+    /// A grader of the set's own, such as a deck script's `grade` hook,
+    /// used in place of `grading` unless the session says otherwise.
+    fn grader(&self) -> Option<Rc<dyn Grader>> {
+        None
+    }
+}
+
+/// Looks up each name in `registry`, failing with
+/// [`LjpError::UnknownSet`] at the first unknown one.
+fn resolve_sets(
+    registry: &SetRegistry,
+    names: Vec<String>,
+) -> Result<Vec<Box<dyn StudySetLoader>>> {
+    names.iter().map(|name| registry.get(name)).collect()
+}
+
+/// The names accepted by [`get_set`]: the built-in sets followed by the
+/// decks found in the user's deck directory.
+pub fn set_names() -> Vec<String> {
+    SetRegistry::with_builtins().names()
+}
+
+/// Looks up a built-in set or deck by name. Built-in sets shadow user
+/// decks of the same name.
+pub fn get_set(name: &str) -> Result<Box<dyn StudySetLoader>> {
+    SetRegistry::with_builtins().get(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fronts(session: &StudySession) -> Vec<&str> {
+        session
+            .items()
+            .iter()
+            .map(|item| item.front.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn seeded_sessions_generate_the_same_items() {
+        let build = |seed| {
+            StudySession::builder()
+                .sets(["prices", "times", "dates", "strings"])
+                .seed(seed)
+                .build()
+                .unwrap()
+        };
+        let (first, again, other) = (build(3), build(3), build(4));
+        assert_eq!(fronts(&first), fronts(&again));
+        assert_ne!(fronts(&first), fronts(&other));
+    }
+
+    #[test]
+    fn unknown_sets_are_errors() {
+        let err = StudySession::builder()
+            .sets(["hiragana", "klingon"])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, LjpError::UnknownSet(name) if name == "klingon"));
+    }
+}
@@ -1,236 +1,1771 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use rand::distr::weighted::WeightedIndex;
+use chrono::{Days, Local};
+use clap::{
+    builder::PossibleValuesParser, error::ErrorKind, parser::ValueSource, CommandFactory,
+    FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use clap_complete::Shell;
 use rand::prelude::*;
-use serde::{Deserialize, Serialize};
+use rand::rngs::StdRng;
+use regex::Regex;
+use serde_json::json;
 use std::{
-    io::{self, stdin, Write},
-    str::FromStr,
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Mutex,
+    time::Duration,
 };
-mod sets;
-
-use sets::hiragana::HiraganaStudySet;
-use sets::katakana::KatakanaStudySet;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StudyItem {
-    front: String,
-    back: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct StudySession {
-    sets: Vec<String>,
-    items: Vec<StudyItem>,
-    #[serde(default)]
-    weights: Vec<u32>,
-    #[serde(skip)]
-    dist: Option<WeightedIndex<u32>>,
-    #[serde(skip)]
-    rng: ThreadRng,
-}
-
-impl StudySession {
-    fn new(sets: Vec<String>) -> Result<Self> {
-        let mut resolved_sets = Vec::new();
-        let mut items = Vec::new();
-        for set_name in sets {
-            if let Some(resolved_set) = get_set(&set_name) {
-                resolved_sets.push(resolved_set.name());
-                items.extend(resolved_set.load());
-            } else {
-                eprintln!("Warning: Set '{}' not found.", set_name);
-            }
-        }
-
-        let weights = vec![1; items.len()];
-        let dist = if !weights.is_empty() {
-            Some(
-                WeightedIndex::new(&weights)
-                    .context("Failed to create weighted index for study items")?,
-            )
-        } else {
-            None
+
+use ljp::audio::{self, ClipSpeaker};
+use ljp::config::{self, Config};
+use ljp::engine::{Mode, Progress, SessionEngine};
+use ljp::events::{Event, EventLog, Listener};
+use ljp::forvo::{self, Forvo};
+use ljp::frontend::{self, BatchFrontend, CliFrontend, Frontend, Pause};
+use ljp::grader::Grading;
+use ljp::graphics::ImageDir;
+use ljp::history::{self, HistoryListener};
+use ljp::middleware::TimingMiddleware;
+use ljp::mnemonics::{self, Mnemonics};
+use ljp::paths;
+use ljp::quiz::{self, QuizFormat};
+use ljp::readline::LineEditor;
+use ljp::replay::{self, Step};
+use ljp::report::{self, ReportFormat};
+use ljp::retention;
+use ljp::romaji::DictationMiddleware;
+use ljp::schedule::{self, Column, ItemSchedule};
+use ljp::sets::deck::{csv_field, DeckStudySet};
+use ljp::speech::{CommandSpeaker, Player, Speaker};
+use ljp::store::{self, SetStats, Store, StoreListener};
+use ljp::sync::{self, SyncConfig};
+use ljp::theme::{self, ColorChoice, Theme};
+use ljp::{
+    anki, backup, engine::Input, forecast, fsrs, heatmap, notes, profiles, serve, set_names, sets,
+    suggest, Algorithm, Direction, LjpError, SetRegistry, StudyItem, StudySession,
+};
+
+// Running `ljp` without a subcommand is the same as `ljp study`; study
+// options before a subcommand are rejected by `args_from`.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Keep progress and history separate under this profile
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Append a debug log of session building, sampling and grading here
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+    /// Print `list`, `stats`, `forecast` and --dry-run results as prose or JSON
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    output: Output,
+    #[command(flatten)]
+    study: StudyArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// What `ljp export` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    /// front,back CSV, with a mnemonic column where there is one
+    Csv,
+    /// An Anki package (.apkg) whose cards keep their progress: FSRS due
+    /// dates and Leitner boxes become review cards, the rest are new
+    Anki,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    Text,
+    Json,
+}
+
+/// Prints `value` as pretty JSON.
+fn print_json(value: &serde_json::Value) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).context("Failed to serialize output")?
+    );
+    Ok(())
+}
+
+#[derive(clap::Args, Debug)]
+struct StudyArgs {
+    /// Comma-separated set names [default: hiragana, unless --deck is given]
+    #[arg(short, long)]
+    sets: Option<String>,
+    /// A front,back CSV file to study; may be repeated
+    #[arg(long)]
+    deck: Vec<PathBuf>,
+    /// Continue a session written by --save, ignoring the set options
+    #[arg(long)]
+    resume: Option<PathBuf>,
+    /// Write the session's items and weights here when it ends
+    #[arg(long)]
+    save: Option<PathBuf>,
+    /// The progress database [default: progress.db in the data directory]
+    #[arg(long)]
+    progress: Option<PathBuf>,
+    /// Neither read nor record long-term progress
+    #[arg(long, default_value = "false")]
+    no_progress: bool,
+    /// Where every answer is logged [default: history.jsonl in the data directory]
+    #[arg(long)]
+    history: Option<PathBuf>,
+    /// Do not log answers to the history file
+    #[arg(long, default_value = "false")]
+    no_history: bool,
+    /// Append the session as started, every question, answer and input, and
+    /// the session's end to this file as JSON lines, for `ljp replay`
+    #[arg(long)]
+    events: Option<PathBuf>,
+    /// Give kanji items without a mnemonic community ones, from an API URL
+    /// with {kanji} in it, fetched once and cached, or a JSON file of kanji
+    /// to mnemonics
+    #[arg(long)]
+    mnemonics: Option<String>,
+    /// Append a Markdown summary of the session to this file
+    #[arg(long)]
+    notes: Option<PathBuf>,
+    /// A directory of `<front>.png` images to show after each answer. Only
+    /// terminals with the kitty or iTerm2 image protocol show them (kitty,
+    /// Ghostty, iTerm2, WezTerm); sixel is not supported
+    #[arg(long)]
+    images: Option<PathBuf>,
+    /// Do not show the right answer after a miss
+    #[arg(long, default_value = "false")]
+    hide_answer: bool,
+    /// Repeat the reading after a correct answer
+    #[arg(long, default_value = "false")]
+    echo_reading: bool,
+    /// How long feedback stays up: milliseconds, or `key` to wait for a
+    /// keypress
+    #[arg(long)]
+    pause: Option<Pause>,
+    /// Color scheme [default: default, or high-contrast with --large-print]
+    #[arg(long)]
+    theme: Option<Theme>,
+    /// When to color output; `auto` respects NO_COLOR
+    #[arg(long, value_enum, default_value = "auto")]
+    color: ColorChoice,
+    /// Show how long each answer took
+    #[arg(long, default_value = "false")]
+    timing: bool,
+    /// How strongly heavy items are favoured, from 0 (every item equally
+    /// often) to 2; 1 draws in proportion to weight
+    #[arg(long, default_value = "1.0", value_parser = parse_focus)]
+    focus: f64,
+    /// Ask back to front, e.g. romaji to kana; optionally only for the
+    /// given comma-separated sets
+    #[arg(long, num_args = 0..=1, default_missing_value = "")]
+    reverse: Option<String>,
+    /// How questions are asked
+    #[arg(long, value_enum, default_value = "typed")]
+    mode: Mode,
+    /// Type answers in romaji and have them converted to kana as you type
+    #[arg(long, default_value = "false")]
+    ime: bool,
+    /// Text-to-speech command for --mode listen, given the text as its
+    /// last argument; with [forvo] in the config it speaks only words
+    /// Forvo has no recording of [default: say or espeak-ng, whichever is
+    /// installed]
+    #[arg(long)]
+    tts: Option<String>,
+    /// Command that plays recordings in --mode listen, given the file as
+    /// its last argument [default: afplay, mpv, ffplay or mpg123, whichever
+    /// is installed]
+    #[arg(long)]
+    player: Option<String>,
+    /// Time allowed per card in --mode timed, e.g. 5s or 1500ms
+    #[arg(long, default_value = "5s", value_parser = parse_duration)]
+    per_card: Duration,
+    /// End the session after this many answers
+    #[arg(long)]
+    count: Option<usize>,
+    /// End the session after this long, e.g. 10m; checked after each card
+    #[arg(long, value_parser = parse_duration)]
+    time: Option<Duration>,
+    /// How many correct answers in a row master an item in --mode mastery
+    #[arg(long, default_value = "3")]
+    streak: u32,
+    /// How many distinct items --mode exam asks
+    #[arg(long, default_value = "50")]
+    questions: usize,
+    /// Which way round to ask cards; with --reverse SETS, only for those
+    /// sets
+    #[arg(long, value_enum, default_value = "forward")]
+    direction: Direction,
+    /// Label each question with what to type when cards are asked back to
+    /// front, filling in {answer} with kana, romaji or kanji; empty to
+    /// leave it out
+    #[arg(long, default_value = "[→ {answer}]")]
+    direction_label: String,
+    /// How the next card is chosen
+    #[arg(long, value_enum, default_value = "weights")]
+    algo: Algorithm,
+    /// Comma-separated FSRS parameters for --algo fsrs
+    #[arg(long, value_parser = fsrs::parse_params)]
+    fsrs_params: Option<[f64; fsrs::PARAM_COUNT]>,
+    /// A file of easily confused fronts, one comma-separated group per
+    /// line, kept from being asked back to back
+    #[arg(long)]
+    similar: Option<PathBuf>,
+    /// List the items the session would include, with their weights,
+    /// without starting it
+    #[arg(long, default_value = "false")]
+    dry_run: bool,
+    /// Study only this many of the heaviest, most often missed items
+    #[arg(long)]
+    weakest: Option<usize>,
+    /// Study only items whose front or back matches this regex, e.g. '^k'
+    #[arg(long, value_parser = parse_regex)]
+    filter: Option<Regex>,
+    /// Leave out items whose front or back matches this regex
+    #[arg(long, value_parser = parse_regex)]
+    exclude: Option<Regex>,
+    /// Leave out items whose front or back is listed in this file, one
+    /// per line
+    #[arg(long)]
+    exclude_file: Option<PathBuf>,
+    /// Study only what the history shows was missed since this many days
+    /// ago [default: 1, i.e. since yesterday]
+    #[arg(long, num_args = 0..=1, default_missing_value = "1")]
+    missed: Option<u32>,
+    /// Enlarge glyphs, space out feedback, use the high-contrast theme and
+    /// pause longer after each answer
+    #[arg(long, default_value = "false")]
+    large_print: bool,
+    /// Exchange prompts and answers line by line on stdin/stdout, without
+    /// decoration, and finish with the result as JSON
+    #[arg(long, default_value = "false")]
+    batch: bool,
+    /// Replace the input label with this, filling in {index}, {total},
+    /// {front}, {set}, {weight} and {label}, e.g. "[{index}/{total}] {front}
+    /// ({set}) ▸ "
+    #[arg(long)]
+    prompt_template: Option<String>,
+    /// How answers are compared with the back [default: as the deck says,
+    /// or exact]
+    #[arg(long, value_enum)]
+    grading: Option<Grading>,
+    /// Seed the random choices, including the items of generated sets, so
+    /// a session can be repeated exactly
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Say nothing about correct answers, only mistakes
+    #[arg(short, long, default_value = "false", conflicts_with = "verbose")]
+    quiet: bool,
+    /// Show how each answer changed the weights
+    #[arg(short, long, default_value = "false")]
+    verbose: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Study items interactively
+    Study(Box<StudyArgs>),
+    /// List the available sets
+    List,
+    /// Show long-term progress per set from the progress store
+    Stats {
+        #[command(subcommand)]
+        command: Option<StatsCommand>,
+        /// The progress database [default: progress.db in the data directory]
+        #[arg(long)]
+        progress: Option<PathBuf>,
+        /// Show a calendar of study days and streaks instead
+        #[arg(long, default_value = "false")]
+        heatmap: bool,
+        /// Show every item's weight, ease, interval, due date, lapses and
+        /// box instead
+        #[arg(long, default_value = "false", conflicts_with = "heatmap")]
+        items: bool,
+        /// The column to sort items by [default: weight]
+        #[arg(long, value_enum, requires = "items")]
+        sort: Option<Column>,
+    },
+    /// Chart the reviews due each day over the coming weeks
+    Forecast {
+        /// The progress database [default: progress.db in the data directory]
+        #[arg(long)]
+        progress: Option<PathBuf>,
+        /// How many weeks ahead to look
+        #[arg(short, long, default_value = "4")]
+        weeks: u32,
+        /// How many new items you mean to add each day
+        #[arg(short, long, default_value = "0")]
+        new: u32,
+        /// Comma-separated FSRS parameters, as given to study --fsrs-params
+        #[arg(long, value_parser = fsrs::parse_params)]
+        fsrs_params: Option<[f64; fsrs::PARAM_COUNT]>,
+    },
+    /// Merge the review history with other devices through the backend
+    /// set in the config's [sync] section, and rebuild progress from it
+    Sync,
+    /// Archive the config, decks, progress and history
+    Backup {
+        /// Where to write the archive [default: a timestamped .zip in the
+        /// backups directory under the data directory]
+        #[arg(short = 'o', long = "out", id = "dest")]
+        dest: Option<PathBuf>,
+    },
+    /// Roll back to an archive made by `ljp backup` or before a migration,
+    /// archiving the current state first
+    Restore { archive: PathBuf },
+    /// Manage the profiles selectable with --profile
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    /// Copy a .csv or .tsv deck into the deck directory so it can be
+    /// studied by name, or make a deck of an Anki collection's notes and
+    /// carry over their reviews
+    Import {
+        path: PathBuf,
+        /// The name of the deck made from an Anki collection
+        #[arg(long, default_value = "anki")]
+        name: String,
+    },
+    /// Write the items of sets as front,back CSV or as an Anki package
+    Export {
+        #[arg(short, long, default_value = "hiragana")]
+        sets: String,
+        /// Where to write the export; CSV defaults to stdout
+        #[arg(short = 'o', long = "out", id = "dest")]
+        dest: Option<PathBuf>,
+        #[arg(short, long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+    },
+    /// Generate a printable quiz worksheet and answer key
+    Quiz {
+        #[arg(short, long, default_value = "hiragana")]
+        sets: String,
+        /// How many questions to ask, each a different item
+        #[arg(short, long, default_value = "30")]
+        count: usize,
+        /// Write the worksheet as HTML to print from a browser, or as PDF
+        #[arg(short, long, value_enum, default_value = "html")]
+        format: QuizFormat,
+        /// Pick and order the questions reproducibly
+        #[arg(long)]
+        seed: Option<u64>,
+        /// The worksheet's path, to which the format's extension is added
+        #[arg(short = 'o', long = "out", id = "dest", default_value = "quiz")]
+        dest: PathBuf,
+    },
+    /// Search item fronts, backs, notes and tags across all sets
+    Search {
+        text: String,
+        /// Read the text as a regular expression rather than literally
+        #[arg(short, long, default_value = "false")]
+        regex: bool,
+    },
+    /// Serve the web front-end to browsers
+    Serve {
+        #[arg(short, long, default_value = "hiragana")]
+        sets: String,
+        /// Where to listen; use 0.0.0.0:8080 to reach it from other devices
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
+    /// Re-run the sessions in an event log written with `study --events`
+    /// and check they ask the same questions and reach the same verdicts
+    Replay { log: PathBuf },
+    /// Print a shell completion script, e.g. `ljp completions bash`
+    Completions { shell: Shell },
+}
+
+#[derive(Subcommand, Debug)]
+enum StatsCommand {
+    /// Write a self-contained report with charts of accuracy, reviews per
+    /// day and mastery per set, e.g. to share with a tutor
+    Report {
+        #[arg(short, long, value_enum, default_value = "html")]
+        format: ReportFormat,
+        /// Where to write the report
+        #[arg(short = 'o', long = "out", id = "dest", default_value = "report.html")]
+        dest: PathBuf,
+        /// The review history [default: history.jsonl in the data directory]
+        #[arg(long)]
+        history: Option<PathBuf>,
+    },
+    /// Chart how often items were recalled against the time since their
+    /// last review, per set
+    Retention {
+        /// The review history [default: history.jsonl in the data directory]
+        #[arg(long)]
+        history: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ProfileCommand {
+    /// List existing profiles
+    List,
+    /// Create a profile with empty progress and history
+    Create { name: String },
+    /// Delete a profile with all of its progress and history
+    Delete { name: String },
+}
+
+/// Parses a duration such as `5s`, `1500ms` or `1m`; plain numbers are
+/// seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{}' is not a duration", s))?;
+    let secs = match unit {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        _ => {
+            return Err(format!(
+                "unknown unit '{}' in '{}'; use ms, s or m",
+                unit, s
+            ))
+        }
+    };
+    Duration::try_from_secs_f64(secs).map_err(|_| format!("'{}' is not a valid duration", s))
+}
+
+fn parse_regex(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
+}
+
+fn parse_focus(s: &str) -> Result<f64, String> {
+    let focus: f64 = s.parse().map_err(|_| format!("'{}' is not a number", s))?;
+    if (0.0..=2.0).contains(&focus) {
+        Ok(focus)
+    } else {
+        Err("focus must be between 0 and 2".to_string())
+    }
+}
+
+/// How many weeks `ljp stats --heatmap` shows.
+const HEATMAP_WEEKS: u32 = 52;
+
+/// How many of the slowest items `ljp stats` lists.
+const SLOWEST_SHOWN: usize = 5;
+
+/// How long feedback stays up in large-print mode unless --pause is given.
+const LARGE_PRINT_PAUSE: Pause = Pause::Millis(2500);
+
+/// Prints the items whose front, back, note or tags match `text`, with
+/// their tags and, from the profile's progress, their weight, box and due
+/// date.
+fn search(text: &str, regex: bool, profile: Option<&str>) -> Result<()> {
+    let pattern = if regex {
+        text.to_string()
+    } else {
+        regex::escape(text)
+    };
+    let pattern = Regex::new(&pattern).with_context(|| format!("Invalid pattern '{}'", text))?;
+
+    let progress = store::default_path(profile)?;
+    let schedules: HashMap<(String, String), ItemSchedule> = if progress.exists() {
+        Store::open(&progress)?
+            .schedules()?
+            .into_iter()
+            .map(|schedule| ((schedule.front.clone(), schedule.back.clone()), schedule))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let registry = SetRegistry::with_builtins();
+    let mut matches = 0;
+    for name in registry.names() {
+        let set = match registry.get(&name) {
+            Ok(set) => set,
+            Err(err) => {
+                eprintln!("Warning: {:#}", anyhow::Error::from(err));
+                continue;
+            }
         };
+        for item in set.load(&mut rand::rng())? {
+            let matched = pattern.is_match(&item.front)
+                || pattern.is_match(&item.back)
+                || item
+                    .note
+                    .as_deref()
+                    .is_some_and(|note| pattern.is_match(note))
+                || item.tags.iter().any(|tag| pattern.is_match(tag));
+            if !matched {
+                continue;
+            }
+            let mut line = format!("{} / {} [{}]", item.front, item.back, name);
+            if !item.tags.is_empty() {
+                line.push_str(&format!(" tags: {}", item.tags.join(", ")));
+            }
+            if let Some(schedule) = schedules.get(&(item.front.clone(), item.back.clone())) {
+                for (label, column) in [
+                    ("weight", Column::Weight),
+                    ("box", Column::Box),
+                    ("due", Column::Due),
+                ] {
+                    if let Some(cell) = schedule.cell(column) {
+                        line.push_str(&format!(" {}: {}", label, cell));
+                    }
+                }
+            }
+            println!("{}", line);
+            matches += 1;
+        }
+    }
 
-        Ok(Self {
-            sets: resolved_sets,
-            items,
-            weights,
-            dist,
-            rng: rand::rng(),
+    if matches == 0 {
+        println!("No items match '{}'.", text);
+    }
+
+    Ok(())
+}
+
+/// Lists every registered set with its description and how many items it
+/// has.
+fn list(output: Output) -> Result<()> {
+    let registry = SetRegistry::with_builtins();
+    let sets: Vec<(String, String, Option<usize>)> = registry
+        .descriptions()
+        .into_iter()
+        .map(|(name, description)| {
+            let items = match registry
+                .get(&name)
+                .and_then(|set| set.load(&mut rand::rng()))
+            {
+                Ok(items) => Some(items.len()),
+                Err(err) => {
+                    eprintln!("Warning: {:#}", anyhow::Error::from(err));
+                    None
+                }
+            };
+            (name, description, items)
         })
+        .collect();
+
+    if output == Output::Json {
+        let sets: Vec<_> = sets
+            .iter()
+            .map(|(name, description, items)| {
+                json!({ "name": name, "description": description, "items": items })
+            })
+            .collect();
+        return print_json(&json!(sets));
     }
 
-    fn sync_dist(&mut self) -> Result<()> {
-        if !self.weights.is_empty() {
-            self.dist =
-                Some(WeightedIndex::new(&self.weights).context("Failed to sync weighted index")?);
-        }
-        Ok(())
+    println!("{:<12} {:>6}  Description", "Set", "Items");
+    for (name, description, items) in sets {
+        let items = items.map_or_else(|| "-".to_string(), |n| n.to_string());
+        println!("{:<12} {:>6}  {}", name, items, description);
     }
+    Ok(())
+}
 
-    fn increment(&mut self) -> Result<()> {
-        self.weights.iter_mut().for_each(|w| *w += 1);
-        self.sync_dist()?;
-        Ok(())
+fn print_quiz(
+    sets: &str,
+    count: usize,
+    format: QuizFormat,
+    seed: Option<u64>,
+    output: &Path,
+) -> Result<()> {
+    let mut builder = StudySession::builder().sets(sets.split(','));
+    if let Some(seed) = seed {
+        builder = builder.seed(seed);
     }
+    let session = builder.build()?;
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(&mut rand::rng()),
+    };
+    let mut items: Vec<StudyItem> = session
+        .items()
+        .choose_multiple(&mut rng, count)
+        .cloned()
+        .collect();
+    // Sampling keeps the sets' order, so kana would come in table order.
+    items.shuffle(&mut rng);
+
+    if items.is_empty() {
+        return Err(LjpError::EmptySession.into());
+    }
+
+    let mut display_sets = session.sets().to_vec();
+    display_sets.sort();
+    let title = format!("ljp quiz: {}", display_sets.join(", "));
+
+    let (sheet, key) = quiz::write(output, format, &title, &items)?;
+    println!(
+        "Wrote {} questions to {} (answers: {})",
+        items.len(),
+        sheet,
+        key
+    );
+
+    Ok(())
+}
 
-    fn reset(&mut self, index: usize) -> Result<()> {
-        if index < self.weights.len() {
-            self.weights[index] = 1;
-            self.sync_dist()?;
+fn export(sets: &str, output: Option<&Path>) -> Result<()> {
+    let session = StudySession::builder().sets(sets.split(',')).build()?;
+    let mut csv = String::new();
+    for item in session.items() {
+        csv.push_str(&format!(
+            "{},{}",
+            csv_field(&item.front),
+            csv_field(&item.answers().collect::<Vec<_>>().join("|"))
+        ));
+        if let Some(mnemonic) = &item.mnemonic {
+            csv.push_str(&format!(",{}", csv_field(mnemonic)));
         }
-        Ok(())
+        csv.push('\n');
     }
 
-    fn sample(&mut self) -> Option<(usize, StudyItem)> {
-        if let Some(dist) = self.dist.as_ref() {
-            let index = dist.sample(&mut self.rng);
-            self.items.get(index).map(|item| (index, item.clone()))
-        } else {
-            None
+    match output {
+        Some(path) => {
+            fs::write(path, csv).with_context(|| format!("Failed to write {}", path.display()))?
         }
+        None => print!("{}", csv),
     }
+    Ok(())
 }
 
-pub trait StudySetLoader {
-    fn name(&self) -> String;
-    fn load(&self) -> Vec<StudyItem>;
-}
+/// Writes the items of `sets` to `dest` as an Anki package, with the
+/// schedules the profile's progress store has for them.
+fn export_anki(sets: &str, dest: &Path, profile: Option<&str>) -> Result<()> {
+    let session = StudySession::builder().sets(sets.split(',')).build()?;
+    let path = store::default_path(profile)?;
+    let store = if path.exists() {
+        Some(Store::open(&path)?)
+    } else {
+        None
+    };
+    let mut schedules: HashMap<(String, String), ItemSchedule> = match &store {
+        Some(store) => store
+            .schedules()?
+            .into_iter()
+            .map(|schedule| ((schedule.front.clone(), schedule.back.clone()), schedule))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let mut cards = Vec::new();
+    for item in session.items() {
+        let stats = match &store {
+            Some(store) => store.item_stats(item)?,
+            None => None,
+        };
+        cards.push(anki::ExportCard {
+            item: item.clone(),
+            schedule: schedules.remove(&(item.front.clone(), item.back.clone())),
+            stats,
+        });
+    }
 
-#[derive(Parser, Debug)]
-struct Args {
-    #[arg(short, long, default_value = "hiragana")]
-    sets: String,
-    #[arg(short, long, default_value = "false")]
-    list: bool,
+    let mut names = session.sets().to_vec();
+    names.sort();
+    anki::export(dest, &format!("ljp: {}", names.join(", ")), &cards)?;
+    let scheduled = cards.iter().filter(|card| card.schedule.is_some()).count();
+    println!(
+        "Exported {} cards ({} with progress) to {}",
+        cards.len(),
+        scheduled,
+        dest.display()
+    );
+    Ok(())
 }
 
-fn get_set(name: &str) -> Option<Box<dyn StudySetLoader>> {
-    match name {
-        "hiragana" => Some(Box::new(HiraganaStudySet)),
-        "katakana" => Some(Box::new(KatakanaStudySet)),
-        _ => None,
+/// Makes a deck called `name` of the notes in the Anki collection at
+/// `path` and seeds the progress store with their reviews.
+fn import_anki(path: &Path, name: &str, profile: Option<&str>) -> Result<()> {
+    let cards = anki::read(path)?;
+    if cards.is_empty() {
+        anyhow::bail!("{} has no notes with two fields", path.display());
     }
+    let target = sets::deck::create(name, &anki::deck(&cards))?;
+    let mut store = Store::open(&store::default_path(profile)?)?;
+    store.import(&anki::progress(&cards, name), &anki::days(&cards))?;
+    let reviews: usize = cards.iter().map(|card| card.reviews.len()).sum();
+    println!(
+        "Imported {} notes to {} with {} reviews; study them with `ljp -s {}`",
+        cards.len(),
+        target.display(),
+        reviews,
+        name
+    );
+    Ok(())
 }
 
-enum Commands {
-    Answer(String),
-    Help,
-    Weights,
-    Quit,
+fn set_stats_json(set: &SetStats) -> serde_json::Value {
+    json!({
+        "set": set.set,
+        "items": set.items,
+        "reviews": set.reviews,
+        "lapses": set.lapses,
+        "accuracy": set.accuracy(),
+        "mastered": set.mastered,
+        "struggling": set.struggling,
+        "average_response_secs": set.average_response(),
+    })
 }
 
-impl Commands {
-    fn help() {
-        println!("Available commands:");
-        println!("  \\h        - Show this help message");
-        println!("  \\w        - Show weights for current items");
-        println!("  \\q        - Quit the study session");
-        println!("  <answer> - Enter your answer for the current item");
+/// Reads the review history at `path` or the profile's default, if there
+/// is one yet.
+fn reviews(path: Option<PathBuf>, profile: Option<&str>) -> Result<Vec<history::Review>> {
+    let path = match path {
+        Some(path) => path,
+        None => history::default_path(profile)?,
+    };
+    if path.exists() {
+        history::read(&path)
+    } else {
+        Ok(Vec::new())
     }
 }
 
-impl FromStr for Commands {
-    type Err = String;
+/// Shows each set's forgetting curve from the review history.
+fn retention(history: Option<PathBuf>, profile: Option<&str>, output: Output) -> Result<()> {
+    let curves = retention::curves(&reviews(history, profile)?);
+    if output == Output::Json {
+        let labels = retention::labels();
+        let curves: Vec<_> = curves
+            .iter()
+            .map(|curve| {
+                let points: Vec<_> = labels
+                    .iter()
+                    .zip(&curve.points)
+                    .map(|(label, point)| {
+                        json!({
+                            "since_last_review": label,
+                            "reviews": point.reviews,
+                            "recalled": point.recalled,
+                        })
+                    })
+                    .collect();
+                json!({ "set": curve.set, "points": points })
+            })
+            .collect();
+        return print_json(&json!({ "sets": curves }));
+    }
+    if curves.is_empty() {
+        println!("No item was reviewed twice yet.");
+        return Ok(());
+    }
+    println!("Recalled, by time since the item's last review:\n");
+    print!("{}", retention::render(&curves));
+    Ok(())
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "\\h" => Ok(Commands::Help),
-            "\\w" => Ok(Commands::Weights),
-            "\\q" => Ok(Commands::Quit),
-            _ if s.starts_with('\\') => Err("Unknown command".to_string()),
-            _ => Ok(Commands::Answer(s.to_string())),
-        }
+/// Writes the HTML progress report to `dest`, from the progress store for
+/// mastery and the review history for everything per day.
+fn report(
+    progress: Option<PathBuf>,
+    history: Option<PathBuf>,
+    dest: &Path,
+    profile: Option<&str>,
+) -> Result<()> {
+    let progress = match progress {
+        Some(path) => path,
+        None => store::default_path(profile)?,
+    };
+    let sets = if progress.exists() {
+        Store::open(&progress)?.set_stats()?
+    } else {
+        Vec::new()
+    };
+    let days = report::daily(&reviews(history, profile)?);
+    let html = report::render_html(&days, &sets, Local::now().date_naive());
+    fs::write(dest, html).with_context(|| format!("Failed to write {}", dest.display()))?;
+    println!("Wrote report to {}", dest.display());
+    Ok(())
+}
+
+fn schedule_json(row: &ItemSchedule) -> serde_json::Value {
+    json!({
+        "front": row.front,
+        "back": row.back,
+        "weight": row.weight,
+        "ease": row.ease,
+        "interval_days": row.interval,
+        "due": row.due.map(|due| due.to_rfc3339()),
+        "lapses": row.lapses,
+        "box": row.leitner_box,
+    })
+}
+
+/// Shows every item in the progress store with its schedule, sorted by
+/// `column`.
+fn item_stats(store: Option<&Store>, column: Column, output: Output) -> Result<()> {
+    let mut rows = match store {
+        Some(store) => store.schedules()?,
+        None => Vec::new(),
+    };
+    schedule::sort(&mut rows, column);
+    if output == Output::Json {
+        return print_json(&json!({
+            "items": rows.iter().map(schedule_json).collect::<Vec<_>>(),
+        }));
+    }
+    if rows.is_empty() {
+        println!("No progress recorded yet.");
+    } else {
+        println!("{}", schedule::table(&rows));
     }
+    Ok(())
 }
 
-fn run_session(session: &mut StudySession) -> Result<()> {
-    loop {
-        let (item_index, item) = match session.sample() {
-            Some((idx, it)) => (idx, it),
-            None => {
-                println!("No items available for study. Exiting session.");
-                break Ok(());
-            }
+fn stats(
+    progress: Option<PathBuf>,
+    heatmap: bool,
+    items: Option<Column>,
+    profile: Option<&str>,
+    output: Output,
+) -> Result<()> {
+    let path = match progress {
+        Some(path) => path,
+        None => store::default_path(profile)?,
+    };
+    let store = if path.exists() {
+        Some(Store::open(&path)?)
+    } else {
+        None
+    };
+    if let Some(column) = items {
+        return item_stats(store.as_ref(), column, output);
+    }
+    if heatmap {
+        let days = match &store {
+            Some(store) => store.daily_reviews()?,
+            None => Vec::new(),
         };
+        let today = Local::now().date_naive();
+        let (current, longest) = heatmap::streaks(&days, today);
+        if output == Output::Json {
+            let days: Vec<_> = days
+                .iter()
+                .map(|(day, reviews)| json!({ "day": day.to_string(), "reviews": reviews }))
+                .collect();
+            return print_json(&json!({
+                "days": days,
+                "current_streak": current,
+                "longest_streak": longest,
+            }));
+        }
+        if store.is_none() {
+            println!("No progress recorded yet.");
+            return Ok(());
+        }
+        print!("{}", heatmap::render(&days, today, HEATMAP_WEEKS));
+        println!("\nCurrent streak: {} days", current);
+        println!("Longest streak: {} days", longest);
+        return Ok(());
+    }
+    let (set_stats, slowest, schedules) = match &store {
+        Some(store) => (
+            store.set_stats()?,
+            store.slowest(SLOWEST_SHOWN)?,
+            store.schedules()?,
+        ),
+        None => (Vec::new(), Vec::new(), Vec::new()),
+    };
+    let now = Local::now();
+    let (due_today, next_due) = suggest::due(&schedules, now);
+    let best_hour = suggest::best_hour(&reviews(None, profile)?);
+    let mut total = SetStats {
+        set: "total".to_string(),
+        ..SetStats::default()
+    };
+    for set in &set_stats {
+        total.items += set.items;
+        total.reviews += set.reviews;
+        total.lapses += set.lapses;
+        total.mastered += set.mastered;
+        total.struggling += set.struggling;
+        total.response_ms += set.response_ms;
+    }
+    if output == Output::Json {
+        let slowest: Vec<_> = slowest
+            .iter()
+            .map(
+                |(front, back, secs)| json!({ "front": front, "back": back, "average_secs": secs }),
+            )
+            .collect();
+        return print_json(&json!({
+            "sets": set_stats.iter().map(set_stats_json).collect::<Vec<_>>(),
+            "total": set_stats_json(&total),
+            "slowest": slowest,
+            "due_today": due_today,
+            "next_due": next_due.map(|due| due.to_rfc3339()),
+            "best_hour": best_hour,
+        }));
+    }
+    if set_stats.is_empty() {
+        println!("No progress recorded yet.");
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:>6} {:>8} {:>9} {:>9} {:>11} {:>9}",
+        "Set", "Items", "Reviews", "Accuracy", "Mastered", "Struggling", "Avg time"
+    );
+    for set in set_stats.iter().chain(std::iter::once(&total)) {
+        println!(
+            "{:<12} {:>6} {:>8} {:>8.0}% {:>9} {:>11} {:>8.1}s",
+            set.set,
+            set.items,
+            set.reviews,
+            set.accuracy() * 100.0,
+            set.mastered,
+            set.struggling,
+            set.average_response()
+        );
+    }
 
-        println!("\n{}", item.front);
-        print!("|> ");
+    if !slowest.is_empty() {
+        println!("\nSlowest items:");
+        for (front, back, secs) in slowest {
+            println!("  {} / {} / {:.1}s", front, back, secs);
+        }
+    }
 
-        io::stdout().flush().context("Failed to flush stdout")?;
+    if let Some(suggestion) = suggest::suggestion(due_today, next_due, best_hour, now) {
+        println!("\n{}", suggestion);
+    }
 
-        let mut input = String::new();
-        stdin()
-            .read_line(&mut input)
-            .context("Failed to read line from stdin")?;
+    Ok(())
+}
 
-        match Commands::from_str(input.trim()) {
-            Ok(Commands::Help) => {
-                Commands::help();
-                continue;
+/// Charts the reviews due each day for `weeks` weeks, with `new` new
+/// items a day on top of what the progress store has scheduled.
+fn forecast(
+    progress: Option<PathBuf>,
+    weeks: u32,
+    new: u32,
+    params: Option<[f64; fsrs::PARAM_COUNT]>,
+    profile: Option<&str>,
+    output: Output,
+) -> Result<()> {
+    let path = match progress {
+        Some(path) => path,
+        None => store::default_path(profile)?,
+    };
+    let schedules = if path.exists() {
+        Store::open(&path)?.schedules()?
+    } else {
+        Vec::new()
+    };
+    let now = Local::now();
+    let counts = forecast::project(
+        &schedules,
+        params.unwrap_or(fsrs::DEFAULT_PARAMS),
+        new,
+        now,
+        weeks * 7,
+    );
+    if output == Output::Json {
+        let days: Vec<_> = counts
+            .iter()
+            .enumerate()
+            .map(|(i, reviews)| {
+                let day = now.date_naive() + Days::new(i as u64);
+                json!({ "day": day.to_string(), "reviews": reviews })
+            })
+            .collect();
+        return print_json(&json!({ "days": days }));
+    }
+    if counts.iter().all(|&count| count == 0) {
+        println!("Nothing is due; study with --algo fsrs or pass --new to plan new items.");
+        return Ok(());
+    }
+    print!("{}", forecast::render(&counts, now));
+    let total: u32 = counts.iter().sum();
+    println!(
+        "\n{} reviews in {} days, {:.0} a day",
+        total,
+        counts.len(),
+        f64::from(total) / counts.len() as f64
+    );
+    Ok(())
+}
+
+fn build_session(args: &StudyArgs, profile: Option<&str>) -> Result<StudySession> {
+    let missed = match args.missed {
+        Some(days) => {
+            let path = match &args.history {
+                Some(path) => path.clone(),
+                None => history::default_path(profile)?,
+            };
+            let since = Local::now().date_naive() - Days::new(u64::from(days));
+            let missed = history::missed_since(&path, since)?;
+            if missed.is_empty() {
+                anyhow::bail!("Nothing was missed since {}", since);
             }
-            Ok(Commands::Weights) => {
-                let mut weighted_items: Vec<_> = session
-                    .weights
-                    .iter()
-                    .zip(session.items.iter())
-                    .map(|(&w, item)| (w, &item.front, &item.back))
-                    .collect();
+            Some(missed)
+        }
+        None => None,
+    };
 
-                weighted_items.sort_by(|a, b| b.0.cmp(&a.0));
+    // Without --sets, study the sets the missed items came from.
+    let sets = match (&args.sets, &missed) {
+        (Some(sets), _) => sets.split(',').map(String::from).collect(),
+        (None, Some(missed)) => {
+            let mut sets: Vec<String> = missed.iter().map(|review| review.set.clone()).collect();
+            sets.sort();
+            sets.dedup();
+            sets
+        }
+        (None, None) if args.deck.is_empty() => vec!["hiragana".to_string()],
+        (None, None) => Vec::new(),
+    };
+    let mut builder = StudySession::builder()
+        .sets(sets)
+        .focus(args.focus)
+        .algorithm(args.algo)
+        .direction(args.direction);
+    if let Some(params) = args.fsrs_params {
+        builder = builder.fsrs_params(params);
+    }
+    for path in &args.deck {
+        builder = builder.loader(DeckStudySet::from_path(path)?);
+    }
+    // Dictation asks every set back to front unless told which to reverse.
+    let reverse = match (&args.reverse, args.mode) {
+        (None, Mode::Dictation) => Some(""),
+        (reverse, _) => reverse.as_deref(),
+    };
+    if let Some(reverse) = reverse {
+        let sets = reverse
+            .split(',')
+            .filter(|set| !set.is_empty())
+            .map(String::from)
+            .collect();
+        builder = builder.reverse(sets);
+    }
+    if let Some(missed) = missed {
+        let missed: Vec<(String, String)> = missed
+            .into_iter()
+            .map(|review| (review.front, review.back))
+            .collect();
+        builder = builder.only(move |item| {
+            missed
+                .iter()
+                .any(|(front, back)| *front == item.front && *back == item.back)
+        });
+    }
+    if let Some(filter) = args.filter.clone() {
+        builder =
+            builder.only(move |item| filter.is_match(&item.front) || filter.is_match(&item.back));
+    }
+    if let Some(exclude) = args.exclude.clone() {
+        builder = builder
+            .only(move |item| !exclude.is_match(&item.front) && !exclude.is_match(&item.back));
+    }
+    if let Some(path) = &args.exclude_file {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read exclude file {}", path.display()))?;
+        let known: Vec<String> = data
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        builder =
+            builder.only(move |item| !known.contains(&item.front) && !known.contains(&item.back));
+    }
+    if let Some(n) = args.weakest {
+        builder = builder.weakest(n);
+    }
+    if let Some(seed) = args.seed {
+        builder = builder.seed(seed);
+    }
+    if let Some(grading) = args.grading {
+        builder = builder.grading(grading);
+    }
+    if let Some(path) = &args.similar {
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read similarity groups {}", path.display()))?;
+        builder = builder.similar(sets::parse_similarity_groups(&data));
+    }
+    Ok(builder.build()?)
+}
 
-                for (weight, front, back) in weighted_items {
-                    println!("{} / {} / {:<3}", front, back, weight);
-                }
-                println!();
-                continue;
+/// What `--mode listen` speaks with: recordings from decks' audio
+/// bundles, then Forvo's when the config sets it up, then text-to-speech.
+fn listen_speaker(args: &StudyArgs) -> Result<Box<dyn Speaker>> {
+    let tts = match &args.tts {
+        Some(command) => Some(CommandSpeaker::new(command).context("--tts must name a command")?),
+        None => CommandSpeaker::detect(),
+    };
+    let player = match &args.player {
+        Some(command) => Some(Player::new(command).context("--player must name a command")?),
+        None => Player::detect(),
+    };
+    let mut speaker = tts.map(|tts| Box::new(tts) as Box<dyn Speaker>);
+    if let Some(config) = load_config()?.forvo {
+        let cache = forvo::cache_dir().context("Could not determine the data directory")?;
+        let player = player
+            .clone()
+            .context("No audio player found for Forvo; install mpv or pass --player")?;
+        speaker = Some(Box::new(Forvo::open(&config, cache, player, speaker)?));
+    }
+    match (player, speaker) {
+        (Some(player), speaker) => {
+            let cache = audio::cache_dir().context("Could not determine the data directory")?;
+            Ok(Box::new(ClipSpeaker::new(player, cache, speaker)))
+        }
+        (None, Some(speaker)) => Ok(speaker),
+        (None, None) => {
+            anyhow::bail!("No text-to-speech program found; install espeak-ng or pass --tts")
+        }
+    }
+}
+
+/// The interactive terminal frontend configured from `args`, completing
+/// `commands` when line editing is available.
+fn cli_frontend(args: &StudyArgs, commands: Vec<String>) -> Result<CliFrontend> {
+    let speaker = match args.mode {
+        Mode::Listen => Some(listen_speaker(args)?),
+        _ => None,
+    };
+    let per_card = (args.mode == Mode::Timed).then_some(args.per_card);
+    let ime = args.ime || args.mode == Mode::Dictation;
+    // Line editing cannot time out, and the IME reads keys itself.
+    let editor = if per_card.is_none() && !ime && std::io::stdin().is_terminal() {
+        Some(LineEditor::new(commands)?)
+    } else {
+        None
+    };
+    Ok(CliFrontend {
+        images: args.images.clone().and_then(ImageDir::new),
+        theme: match &args.theme {
+            _ if !args.color.enabled() => Theme::default(),
+            Some(theme) => theme.clone(),
+            None => {
+                let name = if args.large_print {
+                    "high-contrast"
+                } else {
+                    "default"
+                };
+                name.parse().expect("built-in theme")
             }
-            Ok(Commands::Quit) => {
-                println!("Quitting...");
-                break Ok(());
+        },
+        show_answer: !args.hide_answer,
+        echo_reading: args.echo_reading,
+        pause: args.pause.or(args.large_print.then_some(LARGE_PRINT_PAUSE)),
+        large_print: args.large_print,
+        per_card,
+        ime,
+        speaker,
+        quiet: args.quiet,
+        prompt_template: args.prompt_template.clone(),
+        progress: Progress::default(),
+        direction_label: (args.direction != Direction::Forward
+            || args.reverse.is_some()
+            || args.mode == Mode::Dictation)
+            .then(|| args.direction_label.clone())
+            .filter(|label| !label.is_empty()),
+        editor,
+    })
+}
+
+/// Fills in community mnemonics for the session's kanji. Fetching stops
+/// at the first failure, such as being offline, and the session goes on
+/// with what was already known.
+fn add_mnemonics(session: &mut StudySession, source: &str) -> Result<()> {
+    let mut mnemonics =
+        Mnemonics::open(mnemonics::Source::parse(source)?, mnemonics::cache_path())?;
+    let mut failed = false;
+    session.fill_mnemonics(|item| -> Result<Option<String>> {
+        if failed {
+            return Ok(None);
+        }
+        match mnemonics.for_front(&item.front) {
+            Ok(mnemonic) => Ok(mnemonic),
+            Err(err) => {
+                eprintln!("Warning: {:#}", err);
+                failed = true;
+                Ok(None)
             }
-            Ok(Commands::Answer(answer)) => {
-                if answer == item.back {
-                    println!("Correct!");
-                    session.reset(item_index)?;
-                } else {
-                    println!("Incorrect. The correct answer is: {}", item.back);
-                }
+        }
+    })?;
+    mnemonics.save()
+}
+
+fn study(args: &StudyArgs, profile: Option<&str>, output: Output) -> Result<()> {
+    let mut session = match &args.resume {
+        Some(path) => {
+            let mut session = StudySession::load(path)?;
+            if let Some(seed) = args.seed {
+                session.seed(seed);
             }
-            Err(e) => {
-                eprintln!("Invalid command: {}. Type \\q to quit.", e);
-                continue;
+            if let Some(grading) = args.grading {
+                session.set_grading(grading);
             }
+            session
         }
+        None => build_session(args, profile)?,
+    };
 
-        session.increment()?;
+    let progress = if args.no_progress {
+        None
+    } else {
+        match &args.progress {
+            Some(path) => Some(path.clone()),
+            None => Some(store::default_path(profile)?),
+        }
+    };
+    // Resumed sessions already carry their weights and schedule.
+    if let Some(path) = progress.as_ref().filter(|_| args.resume.is_none()) {
+        let store = Store::open(path)?;
+        match args.algo {
+            Algorithm::Weights => session.seed_weights(|item| store.weight(item))?,
+            Algorithm::Fsrs => session.seed_memories(|item| store.memory(item))?,
+            Algorithm::Leitner => session.seed_boxes(|item| store.leitner_box(item))?,
+        }
     }
-}
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut display_sets = session.sets().to_vec();
+    display_sets.sort();
 
-    if args.list {
-        println!("Available sets: hiragana, katakana");
+    if args.dry_run && output == Output::Json {
+        let items: Vec<_> = session
+            .items()
+            .iter()
+            .zip(session.weights())
+            .map(|(item, weight)| {
+                json!({ "front": item.front, "back": item.back, "set": item.set, "weight": weight })
+            })
+            .collect();
+        return print_json(&json!({ "sets": display_sets, "items": items }));
+    }
+    if args.dry_run {
+        println!(
+            "Session would include {} items from sets: {}",
+            session.items().len(),
+            display_sets.join(", ")
+        );
+        for (item, weight) in session.items().iter().zip(session.weights().iter()) {
+            println!("{} / {} / {:<3}", item.front, item.back, weight);
+        }
         return Ok(());
     }
 
-    let set_names: Vec<String> = args.sets.split(',').map(String::from).collect();
+    if let Some(source) = &args.mnemonics {
+        add_mnemonics(&mut session, source)?;
+    }
+
+    // A logged session is reseeded as it starts, so `ljp replay` can
+    // start from the same place.
+    let log = match &args.events {
+        Some(path) => {
+            let seed = args.seed.unwrap_or_else(rand::random);
+            session.seed(seed);
+            let mut log = EventLog::open(path)?;
+            log.on_event(&Event::SessionStart {
+                args: std::env::args().collect(),
+                seed,
+                session: Box::new(session.clone()),
+            });
+            Some(log)
+        }
+        None => None,
+    };
 
-    let mut session = StudySession::new(set_names)?;
+    let mut engine = SessionEngine::new(session);
+    configure(&mut engine, args);
+    if let Some(limit) = args.time {
+        engine.set_time_limit(limit);
+    }
+    if let Some(path) = &progress {
+        engine.subscribe(StoreListener::new(Store::open(path)?));
+        store::register_commands(engine.commands_mut(), Store::open(path)?);
+    }
+    let history = if args.no_history {
+        None
+    } else {
+        match &args.history {
+            Some(path) => Some(path.clone()),
+            None => Some(history::default_path(profile)?),
+        }
+    };
+    if let Some(path) = &history {
+        engine.subscribe(HistoryListener::open(path)?);
+    }
+    if let Some(log) = log {
+        engine.subscribe(log);
+    }
+    let mut frontend: Box<dyn Frontend> = if args.batch {
+        Box::new(BatchFrontend)
+    } else if args.quiet {
+        Box::new(cli_frontend(args, engine.commands().names())?)
+    } else {
+        println!(
+            "Starting session for {} items from sets: {}",
+            engine.session().items().len(),
+            display_sets.join(", ")
+        );
+        println!("Type '\\h' for commands.");
+        Box::new(cli_frontend(args, engine.commands().names())?)
+    };
+    frontend::catch_interrupts()?;
+    frontend::run(&mut engine, frontend.as_mut())?;
 
-    let mut display_sets = session.sets.clone();
-    display_sets.sort();
+    if let Some(path) = &args.save {
+        engine.session().save(path)?;
+    }
+    // Only the weights algorithm reads weights back; the others would
+    // overwrite the last weights it saved with their own, so they save
+    // their schedule instead.
+    if let Some(path) = &progress {
+        let mut store = Store::open(path)?;
+        match args.algo {
+            Algorithm::Weights => store.save_weights(engine.session())?,
+            Algorithm::Fsrs | Algorithm::Leitner => store.save_schedule(engine.session())?,
+        }
+    }
+
+    if let Some(path) = &args.notes {
+        notes::append(path, &display_sets, engine.summary())?;
+    }
+
+    // Syncing reads back the history and progress, which the listeners
+    // finish writing when the engine goes.
+    drop(engine);
+    if !args.no_history && args.history.is_none() {
+        if let Some(config) = load_config()?.sync.filter(|sync| sync.after_session) {
+            if let Err(err) = sync(&config, profile) {
+                eprintln!("Warning: {:#}", err);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Merges the profile's history with its copy in the configured backend
+/// and rebuilds the profile's progress from the result.
+fn sync(config: &SyncConfig, profile: Option<&str>) -> Result<()> {
+    let mut backend = config.backend.open(expand_home)?;
+    let name = match profile {
+        Some(profile) => format!("profiles/{}/history.jsonl", profile),
+        None => "history.jsonl".to_string(),
+    };
+    let mut store = Store::open(&store::default_path(profile)?)?;
+    let synced = sync::sync(
+        backend.as_mut(),
+        &name,
+        &history::default_path(profile)?,
+        &mut store,
+    )?;
     println!(
-        "Starting session for {} items from sets: {}",
-        session.items.len(),
-        display_sets.join(", ")
+        "Synced with {}: {} reviews received, progress of {} items rebuilt",
+        backend.describe(),
+        synced.received,
+        synced.items
     );
-    println!("Type '\\h' for commands.");
+    Ok(())
+}
 
-    run_session(&mut session)?;
+/// Applies the study options that decide which questions are asked and
+/// how answers are judged, apart from the time limit.
+fn configure(engine: &mut SessionEngine, args: &StudyArgs) {
+    frontend::register_commands(engine.commands_mut());
+    engine.set_mode(args.mode);
+    engine.set_questions(args.questions);
+    engine.set_streak(args.streak);
+    engine.set_verbose(args.verbose);
+    if let Some(n) = args.count {
+        engine.set_count(n);
+    }
+    if args.timing {
+        engine.add_middleware(TimingMiddleware::default());
+    }
+    if args.mode == Mode::Dictation {
+        engine.add_middleware(DictationMiddleware);
+    }
+}
 
+/// Re-runs every session logged at `path` from its recorded session, seed
+/// and inputs, stopping at the first one that asks or judges differently.
+/// Options come from the recorded command line and the current config;
+/// graders from deck scripts are not recorded, so those sets are judged as
+/// in a resumed session.
+fn replay(path: &Path) -> Result<()> {
+    let transcripts = replay::read(path)?;
+    if transcripts.is_empty() {
+        anyhow::bail!(
+            "{} records no sessions; write one with `ljp study --events`",
+            path.display()
+        );
+    }
+    let config = load_config()?;
+    for (number, transcript) in transcripts.into_iter().enumerate() {
+        let profile = Args::try_parse_from(&transcript.args)
+            .ok()
+            .and_then(|args| args.profile);
+        let command = configured_command(&config, profile.as_deref())?;
+        let parsed = args_from(command, &transcript.args)
+            .with_context(|| format!("Session {} has an unusable command line", number + 1))?;
+        let args = match parsed.command {
+            Some(Command::Study(args)) => args,
+            None => Box::new(parsed.study),
+            Some(_) => anyhow::bail!("Session {} was not started by `ljp study`", number + 1),
+        };
+
+        let mut session = transcript.session;
+        session.seed(transcript.seed);
+        let mut engine = SessionEngine::new(session);
+        configure(&mut engine, &args);
+        // Time limits depend on the clock, so end where the recording did.
+        if args.time.is_some() && !transcript.inputs.contains(&Input::Quit) {
+            if let Some(answered) = transcript.answered {
+                engine.set_count(answered);
+            }
+        }
+        let steps = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&steps);
+        engine.subscribe(move |event: &Event| sink.borrow_mut().extend(Step::of(event)));
+        let mut frontend = frontend::ScriptedFrontend::from_inputs(transcript.inputs);
+        frontend::run(&mut engine, &mut frontend)?;
+
+        replay::compare(&transcript.steps, &steps.borrow()).map_err(|difference| {
+            anyhow::anyhow!("Session {} differs at {}", number + 1, difference)
+        })?;
+        println!(
+            "Session {}: {} steps replayed identically",
+            number + 1,
+            transcript.steps.len()
+        );
+    }
     Ok(())
 }
+
+/// Offers the known set names, including discovered decks, wherever a set
+/// is expected.
+fn with_set_names(command: clap::Command, names: &[String]) -> clap::Command {
+    let command = command.mut_args(|arg| match arg.get_id().as_str() {
+        "sets" | "reverse" => arg.value_parser(PossibleValuesParser::new(names.to_vec())),
+        _ => arg,
+    });
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    subcommands.iter().fold(command, |command, name| {
+        command.mut_subcommand(name, |subcommand| with_set_names(subcommand, names))
+    })
+}
+
+fn completions(shell: Shell) {
+    let names = set_names();
+    let mut command = with_set_names(Args::command(), &names);
+    clap_complete::generate(shell, &mut command, "ljp", &mut std::io::stdout());
+}
+
+/// Parses the command line, with defaults from the config file for any
+/// study option not given.
+fn parse_args() -> Result<Args> {
+    let config = load_config()?;
+    if let Some(dir) = &config.data_dir {
+        paths::set_data_dir(expand_home(dir));
+    }
+    theme::add_user_themes(config.themes.clone());
+
+    // The profile decides which overrides apply, so parse once to find it.
+    let profile = Args::parse().profile;
+    let command = configured_command(&config, profile.as_deref())?;
+    args_from(command, std::env::args_os()).map_err(|e| e.exit())
+}
+
+fn load_config() -> Result<Config> {
+    match config::default_path() {
+        Some(path) => Config::load(&path),
+        None => Ok(Config::default()),
+    }
+}
+
+/// The command line parser, with the study defaults `config` sets for
+/// `profile`.
+fn configured_command(config: &Config, profile: Option<&str>) -> Result<clap::Command> {
+    let mut command = Args::command();
+    for (flag, value) in config.study_defaults(profile)? {
+        let id = flag.replace('-', "_");
+        let study = command
+            .find_subcommand("study")
+            .expect("study subcommand exists");
+        if !study.get_arguments().any(|arg| arg.get_id() == id.as_str()) {
+            anyhow::bail!("Unknown study option '{}' in config", flag);
+        }
+        command = command
+            .mut_arg(&id, |arg| arg.default_value(value.clone()))
+            .mut_subcommand("study", |study| {
+                study.mut_arg(&id, |arg| arg.default_value(value.clone()))
+            });
+    }
+    Ok(command)
+}
+
+/// Parses `argv` with `command`, refusing study options given before a
+/// subcommand, where they would be silently ignored. Global options such
+/// as `--output` may go on either side of it.
+fn args_from(
+    mut command: clap::Command,
+    argv: impl IntoIterator<Item = impl Into<std::ffi::OsString> + Clone>,
+) -> Result<Args, clap::Error> {
+    let matches = command.try_get_matches_from_mut(argv)?;
+    if let Some((name, _)) = matches.subcommand() {
+        let study = <StudyArgs as clap::Args>::augment_args(clap::Command::new("study"));
+        let conflict = study
+            .get_arguments()
+            .find(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+            .map(|arg| arg.get_long().unwrap_or(arg.get_id().as_str()).to_string());
+        if let Some(flag) = conflict {
+            return Err(command.error(
+                ErrorKind::ArgumentConflict,
+                format!("the subcommand '{}' cannot be used with '--{}'", name, flag),
+            ));
+        }
+    }
+    Args::from_arg_matches(&matches)
+}
+
+/// Sends tracing events at debug level and above to the file at `path`.
+fn init_logging(path: &Path) -> Result<()> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+    tracing_subscriber::fmt()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(tracing::Level::DEBUG)
+        .init();
+    Ok(())
+}
+
+/// Expands a leading `~` to the home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn main() -> Result<()> {
+    let args = parse_args()?;
+    if let Some(path) = &args.log_file {
+        init_logging(path)?;
+    }
+
+    let profile = args.profile.as_deref();
+    let output = args.output;
+    match args.command.unwrap_or(Command::Study(Box::new(args.study))) {
+        Command::Study(study_args) => study(&study_args, profile, output),
+        Command::List => list(output),
+        Command::Stats {
+            command:
+                Some(StatsCommand::Report {
+                    format: ReportFormat::Html,
+                    dest,
+                    history,
+                }),
+            progress,
+            ..
+        } => report(progress, history, &dest, profile),
+        Command::Stats {
+            command: Some(StatsCommand::Retention { history }),
+            ..
+        } => retention(history, profile, output),
+        Command::Stats {
+            command: None,
+            progress,
+            heatmap,
+            items,
+            sort,
+        } => {
+            let items = items.then(|| sort.unwrap_or(Column::Weight));
+            stats(progress, heatmap, items, profile, output)
+        }
+        Command::Forecast {
+            progress,
+            weeks,
+            new,
+            fsrs_params,
+        } => forecast(progress, weeks, new, fsrs_params, profile, output),
+        Command::Sync => {
+            let config = load_config()?
+                .sync
+                .context("Set up syncing in the [sync] section of the config first")?;
+            sync(&config, profile)
+        }
+        Command::Backup { dest } => {
+            let locations = backup::Locations::current()?;
+            let dest = dest.unwrap_or_else(|| locations.archive_path(None));
+            let files = backup::create(&locations, &dest)?;
+            println!("Backed up {} files to {}", files, dest.display());
+            Ok(())
+        }
+        Command::Restore { archive } => {
+            let (safety, files) = backup::restore(&backup::Locations::current()?, &archive)?;
+            println!(
+                "Restored {} files from {}; the state before is in {}",
+                files,
+                archive.display(),
+                safety.display()
+            );
+            Ok(())
+        }
+        Command::Profile { command } => match command {
+            ProfileCommand::List => {
+                let names = profiles::list()?;
+                if names.is_empty() {
+                    println!("No profiles yet.");
+                } else {
+                    println!("Profiles: {}", names.join(", "));
+                }
+                Ok(())
+            }
+            ProfileCommand::Create { name } => {
+                let dir = profiles::create(&name)?;
+                println!("Created profile '{}' in {}", name, dir.display());
+                Ok(())
+            }
+            ProfileCommand::Delete { name } => {
+                profiles::delete(&name)?;
+                println!("Deleted profile '{}'", name);
+                Ok(())
+            }
+        },
+        Command::Import { path, name } if anki::is_collection(&path) => {
+            import_anki(&path, &name, profile)
+        }
+        Command::Import { path, .. } => {
+            let target = sets::deck::import(&path)?;
+            println!("Imported deck to {}", target.display());
+            Ok(())
+        }
+        Command::Export {
+            sets,
+            dest,
+            format: ExportFormat::Csv,
+        } => export(&sets, dest.as_deref()),
+        Command::Export {
+            sets,
+            dest,
+            format: ExportFormat::Anki,
+        } => {
+            let dest = dest.context("Exporting to Anki needs --out, e.g. --out kana.apkg")?;
+            export_anki(&sets, &dest, profile)
+        }
+        Command::Quiz {
+            sets,
+            count,
+            format,
+            seed,
+            dest,
+        } => print_quiz(&sets, count, format, seed, &dest),
+        Command::Search { text, regex } => search(&text, regex, profile),
+        Command::Serve { sets, bind } => {
+            let set_names: Vec<String> = sets.split(',').map(String::from).collect();
+            serve::run(&bind, set_names)
+        }
+        Command::Replay { log } => replay(&log),
+        Command::Completions { shell } => {
+            completions(shell);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(argv: &[&str]) -> Result<Args, clap::Error> {
+        args_from(Args::command(), argv)
+    }
+
+    #[test]
+    fn command_is_well_formed() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn export_takes_a_destination() {
+        let args = parse(&["ljp", "export", "-s", "katakana", "-o", "out.csv"]).unwrap();
+        match args.command {
+            Some(Command::Export { sets, dest, .. }) => {
+                assert_eq!(sets, "katakana");
+                assert_eq!(dest, Some(PathBuf::from("out.csv")));
+            }
+            other => panic!("expected export, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quiz_takes_a_destination() {
+        let args = parse(&["ljp", "quiz", "-c", "3", "--out", "/tmp/q"]).unwrap();
+        match args.command {
+            Some(Command::Quiz { count, dest, .. }) => {
+                assert_eq!(count, 3);
+                assert_eq!(dest, PathBuf::from("/tmp/q"));
+            }
+            other => panic!("expected quiz, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stats_options_go_before_the_report() {
+        let args = parse(&[
+            "ljp",
+            "stats",
+            "--progress",
+            "p.db",
+            "report",
+            "-o",
+            "r.html",
+        ])
+        .unwrap();
+        match args.command {
+            Some(Command::Stats {
+                command: Some(StatsCommand::Report { dest, .. }),
+                progress,
+                ..
+            }) => {
+                assert_eq!(dest, PathBuf::from("r.html"));
+                assert_eq!(progress, Some(PathBuf::from("p.db")));
+            }
+            other => panic!("expected a report, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn global_options_go_before_or_after_a_subcommand() {
+        let before = parse(&["ljp", "--output", "json", "list"]).unwrap();
+        let after = parse(&["ljp", "list", "--output", "json"]).unwrap();
+        assert_eq!(before.output, Output::Json);
+        assert_eq!(after.output, Output::Json);
+    }
+
+    #[test]
+    fn study_options_before_a_subcommand_are_rejected() {
+        let err = parse(&["ljp", "-s", "katakana", "list"]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn study_options_without_a_subcommand_study() {
+        let args = parse(&["ljp", "-s", "katakana"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.study.sets.as_deref(), Some("katakana"));
+    }
+}
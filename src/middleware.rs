@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::time::Instant;
+
+use crate::{engine::Render, StudyItem};
+
+/// Hooks that wrap question and answer handling in a `SessionEngine`, so
+/// cross-cutting features (timing, dictation, audio, …) compose without
+/// touching the engine or any frontend. Middleware runs in the order it
+/// was added.
+pub trait Middleware {
+    /// Called when `item` is about to be asked; may add renders that are
+    /// shown before the question.
+    fn before_question(&mut self, _item: &StudyItem, _renders: &mut Vec<Render>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before `answer` is graded; may rewrite it.
+    fn before_answer(&mut self, _item: &StudyItem, _answer: &mut String) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after grading; may add renders shown after the feedback.
+    fn after_answer(
+        &mut self,
+        _item: &StudyItem,
+        _answer: &str,
+        _correct: bool,
+        _renders: &mut Vec<Render>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Reports how long each answer took.
+#[derive(Debug, Default)]
+pub struct TimingMiddleware {
+    asked_at: Option<Instant>,
+}
+
+impl Middleware for TimingMiddleware {
+    fn before_question(&mut self, _item: &StudyItem, _renders: &mut Vec<Render>) -> Result<()> {
+        self.asked_at = Some(Instant::now());
+        Ok(())
+    }
+
+    fn after_answer(
+        &mut self,
+        _item: &StudyItem,
+        _answer: &str,
+        _correct: bool,
+        renders: &mut Vec<Render>,
+    ) -> Result<()> {
+        if let Some(asked_at) = self.asked_at.take() {
+            renders.push(Render::Notice(format!(
+                "Answered in {:.1}s",
+                asked_at.elapsed().as_secs_f64()
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+//! Community mnemonics for kanji, attached to items that have none of
+//! their own. They come from an API, fetched with `curl` and cached in the
+//! data directory, or from a JSON dataset of kanji to mnemonics on disk.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::Path, path::PathBuf, process::Command};
+
+use crate::paths;
+
+/// What the API URL has each kanji put in for.
+pub const PLACEHOLDER: &str = "{kanji}";
+/// How long a fetch may take before it is given up on.
+const TIMEOUT_SECS: &str = "10";
+
+/// Where mnemonics come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A URL with [`PLACEHOLDER`] in it, answering with
+    /// `{"mnemonic": "..."}`, or a 404 for kanji it has none for.
+    Api(String),
+    /// A JSON object of kanji to mnemonics.
+    Dataset(PathBuf),
+}
+
+impl Source {
+    /// Reads an `http(s)://` URL as an API and anything else as a dataset.
+    pub fn parse(source: &str) -> Result<Self> {
+        if !source.starts_with("http://") && !source.starts_with("https://") {
+            return Ok(Source::Dataset(PathBuf::from(source)));
+        }
+        if !source.contains(PLACEHOLDER) {
+            anyhow::bail!("Mnemonics URL {} has no {} in it", source, PLACEHOLDER);
+        }
+        Ok(Source::Api(source.to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Response {
+    mnemonic: Option<String>,
+}
+
+/// Fetched mnemonics, with `None` for kanji the API has none for so they
+/// are not asked for again. A cache of another API is not used.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cache {
+    api: String,
+    mnemonics: HashMap<char, Option<String>>,
+}
+
+/// Looks up mnemonics from a [`Source`].
+#[derive(Debug)]
+pub struct Mnemonics {
+    source: Source,
+    known: HashMap<char, Option<String>>,
+    /// Where fetched mnemonics are kept, if anywhere.
+    cache: Option<PathBuf>,
+    fetched: bool,
+}
+
+/// Where fetched mnemonics are cached.
+pub fn cache_path() -> Option<PathBuf> {
+    paths::data_dir().map(|dir| dir.join("mnemonics.json"))
+}
+
+/// Is `c` a kanji?
+pub fn is_kanji(c: char) -> bool {
+    matches!(c, '\u{4e00}'..='\u{9fff}' | '\u{3400}'..='\u{4dbf}' | '\u{f900}'..='\u{faff}')
+}
+
+impl Mnemonics {
+    /// Reads a dataset, or the cache kept at `cache` of what was fetched
+    /// from an API before.
+    pub fn open(source: Source, cache: Option<PathBuf>) -> Result<Self> {
+        let known = match &source {
+            Source::Dataset(path) => {
+                let data = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read mnemonics {}", path.display()))?;
+                let dataset: HashMap<String, String> = serde_json::from_str(&data)
+                    .with_context(|| format!("Invalid mnemonics {}", path.display()))?;
+                dataset
+                    .into_iter()
+                    .filter_map(|(kanji, mnemonic)| {
+                        let mut chars = kanji.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(kanji), None) => Some((kanji, Some(mnemonic))),
+                            _ => None,
+                        }
+                    })
+                    .collect()
+            }
+            Source::Api(url) => match cache.as_deref().map(read_cache).transpose()? {
+                Some(Some(cached)) if &cached.api == url => cached.mnemonics,
+                _ => HashMap::new(),
+            },
+        };
+        Ok(Self {
+            source,
+            known,
+            cache,
+            fetched: false,
+        })
+    }
+
+    /// The mnemonic for `kanji`, fetching it if it is not known yet.
+    pub fn get(&mut self, kanji: char) -> Result<Option<String>> {
+        if let Some(known) = self.known.get(&kanji) {
+            return Ok(known.clone());
+        }
+        let Source::Api(url) = &self.source else {
+            return Ok(None);
+        };
+        let mnemonic = fetch(&url.replace(PLACEHOLDER, &kanji.to_string()))?;
+        self.known.insert(kanji, mnemonic.clone());
+        self.fetched = true;
+        Ok(mnemonic)
+    }
+
+    /// The mnemonics for the kanji in `front`. A lone kanji's is used as
+    /// is; with several, each is labelled with its kanji.
+    pub fn for_front(&mut self, front: &str) -> Result<Option<String>> {
+        let mut kanji: Vec<char> = front.chars().filter(|&c| is_kanji(c)).collect();
+        kanji.dedup();
+        let mut found = Vec::new();
+        for &k in &kanji {
+            if let Some(mnemonic) = self.get(k)? {
+                found.push((k, mnemonic));
+            }
+        }
+        Ok(match found.as_slice() {
+            [] => None,
+            [(_, mnemonic)] if kanji.len() == 1 => Some(mnemonic.clone()),
+            _ => Some(
+                found
+                    .iter()
+                    .map(|(k, mnemonic)| format!("{}: {}", k, mnemonic))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            ),
+        })
+    }
+
+    /// Keeps what was fetched for next time.
+    pub fn save(&self) -> Result<()> {
+        let (Source::Api(url), Some(path)) = (&self.source, &self.cache) else {
+            return Ok(());
+        };
+        if !self.fetched {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let cache = Cache {
+            api: url.clone(),
+            mnemonics: self.known.clone(),
+        };
+        fs::write(path, serde_json::to_string(&cache)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Reads the cache at `path`; a missing or unreadable one is no cache.
+fn read_cache(path: &Path) -> Result<Option<Cache>> {
+    match fs::read_to_string(path) {
+        Ok(data) => Ok(serde_json::from_str(&data).ok()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn fetch(url: &str) -> Result<Option<String>> {
+    let output = Command::new("curl")
+        .args(["--silent", "--max-time", TIMEOUT_SECS])
+        .args(["--write-out", "\n%{http_code}"])
+        .arg(url)
+        .output()
+        .context("Failed to run curl, which fetching mnemonics needs")?;
+    if !output.status.success() {
+        match output.status.code() {
+            Some(code) => anyhow::bail!("Fetching mnemonics failed (curl error {})", code),
+            None => anyhow::bail!("Fetching mnemonics was interrupted"),
+        }
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+    match status.trim() {
+        "404" => Ok(None),
+        "200" => {
+            let response: Response =
+                serde_json::from_str(body).context("Unexpected mnemonics response")?;
+            Ok(response.mnemonic.filter(|m| !m.trim().is_empty()))
+        }
+        status => anyhow::bail!("Fetching mnemonics failed (HTTP {})", status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_apis_and_anything_else_a_dataset() {
+        assert_eq!(
+            Source::parse("https://example.com/{kanji}").unwrap(),
+            Source::Api("https://example.com/{kanji}".to_string())
+        );
+        assert!(Source::parse("https://example.com/").is_err());
+        assert_eq!(
+            Source::parse("kanji.json").unwrap(),
+            Source::Dataset(PathBuf::from("kanji.json"))
+        );
+    }
+
+    #[test]
+    fn fronts_get_the_mnemonics_of_their_kanji() {
+        let path = std::env::temp_dir().join(format!("ljp-mnemonics-{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"日": "the sun", "本": "a tree with its root marked", "日本": "ignored"}"#,
+        )
+        .unwrap();
+        let mut mnemonics = Mnemonics::open(Source::Dataset(path.clone()), None).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            mnemonics.for_front("日").unwrap().as_deref(),
+            Some("the sun")
+        );
+        assert_eq!(
+            mnemonics.for_front("日本").unwrap().as_deref(),
+            Some("日: the sun; 本: a tree with its root marked")
+        );
+        assert_eq!(mnemonics.for_front("月").unwrap().as_deref(), None);
+        assert_eq!(mnemonics.for_front("ねこ").unwrap(), None);
+    }
+
+    #[test]
+    fn caches_of_another_api_are_not_used() {
+        let path =
+            std::env::temp_dir().join(format!("ljp-mnemonics-cache-{}.json", std::process::id()));
+        let cache = Cache {
+            api: "https://a.example/{kanji}".to_string(),
+            mnemonics: HashMap::from([('日', Some("the sun".to_string())), ('月', None)]),
+        };
+        fs::write(&path, serde_json::to_string(&cache).unwrap()).unwrap();
+        let open = |url: &str| Mnemonics::open(Source::parse(url).unwrap(), Some(path.clone()));
+
+        let mut same = open("https://a.example/{kanji}").unwrap();
+        assert_eq!(same.get('日').unwrap().as_deref(), Some("the sun"));
+        assert_eq!(same.get('月').unwrap(), None);
+        assert!(open("https://b.example/{kanji}").unwrap().known.is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+use crate::engine::SessionSummary;
+
+fn escape_cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// Appends a Markdown summary of the session to the notes file at `path`,
+/// creating the file if it does not exist yet.
+pub fn append(path: &Path, sets: &[String], summary: &SessionSummary) -> Result<()> {
+    let mut markdown = format!(
+        "\n## ljp session — {}\n\n",
+        Local::now().format("%Y-%m-%d %H:%M")
+    );
+    markdown.push_str(&format!("- Sets: {}\n", sets.join(", ")));
+    markdown.push_str(&format!(
+        "- Score: {}/{} ({:.0}%)\n\n",
+        summary.correct,
+        summary.answered,
+        summary.accuracy() * 100.0
+    ));
+
+    if summary.missed.is_empty() {
+        markdown.push_str("No missed items.\n");
+    } else {
+        markdown.push_str("| Prompt | Your answer | Correct answer |\n");
+        markdown.push_str("|---|---|---|\n");
+        for (item, given) in &summary.missed {
+            markdown.push_str(&format!(
+                "| {} | {} | {} |\n",
+                escape_cell(&item.front),
+                escape_cell(given),
+                escape_cell(&item.back)
+            ));
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open notes file {}", path.display()))?;
+    file.write_all(markdown.as_bytes())
+        .with_context(|| format!("Failed to write notes file {}", path.display()))?;
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::{env, path::PathBuf, sync::OnceLock};
+
+/// A data directory chosen in the config file, which takes precedence.
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Keeps all data in `dir` instead. Only the first call has any effect.
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DATA_DIR.set(dir);
+}
+
+/// Returns `$XDG_DATA_HOME/ljp`, falling back to `~/.local/share/ljp`,
+/// unless a data directory was set.
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = DATA_DIR.get() {
+        return Some(dir.clone());
+    }
+    let base = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(base.join("ljp"))
+}
+
+/// Where user decks are discovered and imported to.
+pub fn decks_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("decks"))
+}
+
+/// Where set assets such as `hiragana.csv` are read from at runtime.
+pub fn assets_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("assets"))
+}
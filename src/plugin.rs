@@ -0,0 +1,228 @@
+//! Study sets shipped as WebAssembly modules, found in the `plugins`
+//! directory under the data directory.
+//!
+//! A plugin imports nothing and exports:
+//!
+//! - `memory`, its linear memory;
+//! - `ljp_abi_version() -> i32`, returning [`ABI_VERSION`];
+//! - `ljp_name() -> i64`, the set's name;
+//! - `ljp_load(seed: i64) -> i64`, the set's items as a JSON array of
+//!   [`StudyItem`]s, e.g. `[{"front": "あ", "back": "a"}]`. The seed
+//!   differs between loads so generated sets can differ too, and follows
+//!   the session's `--seed`;
+//! - optionally `ljp_prompt() -> i64`, how the set's questions are
+//!   phrased.
+//!
+//! Strings are UTF-8 in `memory`, returned as `offset << 32 | length`.
+//! Plugins are sandboxed: with no imports they have no files, network or
+//! clock, and every call is limited in fuel and memory.
+use anyhow::{Context, Result};
+use rand::{Rng, RngCore};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use wasmi::{Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::{error, paths, LjpError, StudyItem, StudySetLoader};
+
+/// The version of the interface above that plugins must report.
+pub const ABI_VERSION: i32 = 1;
+/// Roughly how many instructions a plugin may run per call.
+const FUEL: u64 = 1_000_000_000;
+/// How large a plugin's memory may grow, in bytes.
+const MEMORY_LIMIT: usize = 64 << 20;
+
+/// Where plugins are discovered.
+pub fn plugins_dir() -> Option<PathBuf> {
+    paths::data_dir().map(|dir| dir.join("plugins"))
+}
+
+/// Returns the `.wasm` files in the plugin directory, sorted.
+pub fn discover() -> Vec<PathBuf> {
+    let Some(entries) = plugins_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut plugins: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("wasm"))
+        })
+        .collect();
+    plugins.sort();
+    plugins
+}
+
+/// Plugins compiled so far, by path, with the modification time they were
+/// compiled at.
+static COMPILED: Mutex<Option<HashMap<PathBuf, (SystemTime, PluginStudySet)>>> = Mutex::new(None);
+
+/// A plugin instance, good for a single call.
+struct Running {
+    store: Store<StoreLimits>,
+    instance: Instance,
+}
+
+impl Running {
+    fn start(engine: &Engine, module: &Module) -> Result<Self> {
+        let limits = StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build();
+        let mut store = Store::new(engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL).map_err(wasmi::Error::from)?;
+        let instance = Linker::new(engine)
+            .instantiate(&mut store, module)
+            .context("Plugins may not import anything")?
+            .start(&mut store)?;
+        Ok(Self { store, instance })
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.instance.get_func(&self.store, name).is_some()
+    }
+
+    /// Calls the export `name`, which returns a string.
+    fn string<P: wasmi::WasmParams>(&mut self, name: &str, params: P) -> Result<String> {
+        let packed = self
+            .instance
+            .get_typed_func::<P, i64>(&self.store, name)
+            .with_context(|| format!("Missing export {}", name))?
+            .call(&mut self.store, params)
+            .with_context(|| format!("{} failed", name))?;
+        let (offset, len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .context("Missing export memory")?;
+        // Check before allocating so a bogus length cannot exhaust the host.
+        if offset
+            .checked_add(len)
+            .is_none_or(|end| end > memory.data(&self.store).len())
+        {
+            anyhow::bail!("{} returned a string outside memory", name);
+        }
+        let mut bytes = vec![0; len];
+        memory
+            .read(&self.store, offset, &mut bytes)
+            .map_err(wasmi::Error::from)
+            .with_context(|| format!("{} returned a string outside memory", name))?;
+        String::from_utf8(bytes).with_context(|| format!("{} returned invalid UTF-8", name))
+    }
+}
+
+/// A study set provided by a WebAssembly plugin.
+#[derive(Debug, Clone)]
+pub struct PluginStudySet {
+    path: PathBuf,
+    engine: Engine,
+    module: Arc<Module>,
+    name: String,
+    prompt: Option<String>,
+}
+
+impl PluginStudySet {
+    /// The plugin at `path`, compiled once per process: registries are
+    /// built for every session and set lookup, and only a plugin that
+    /// changed since is compiled and instantiated again.
+    pub fn cached(path: &Path) -> error::Result<Self> {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|source| LjpError::Io {
+                action: "read plugin",
+                path: path.to_path_buf(),
+                source,
+            })?;
+        let mut compiled = COMPILED.lock().unwrap_or_else(|e| e.into_inner());
+        let compiled = compiled.get_or_insert_with(HashMap::new);
+        if let Some((at, set)) = compiled.get(path) {
+            if *at == modified {
+                return Ok(set.clone());
+            }
+        }
+        let set = Self::from_path(path)?;
+        compiled.insert(path.to_path_buf(), (modified, set.clone()));
+        Ok(set)
+    }
+
+    /// Compiles the plugin at `path` and asks it for its name.
+    pub fn from_path(path: &Path) -> error::Result<Self> {
+        let wasm = fs::read(path).map_err(|source| LjpError::Io {
+            action: "read plugin",
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::compile(path, &wasm).map_err(|e| plugin_error(path, e))
+    }
+
+    fn compile(path: &Path, wasm: &[u8]) -> Result<Self> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)?;
+
+        let mut running = Running::start(&engine, &module)?;
+        let version = running
+            .instance
+            .get_typed_func::<(), i32>(&running.store, "ljp_abi_version")
+            .context("Missing export ljp_abi_version")?
+            .call(&mut running.store, ())?;
+        if version != ABI_VERSION {
+            anyhow::bail!(
+                "Plugin ABI version {} is not supported (expected {})",
+                version,
+                ABI_VERSION
+            );
+        }
+        let name = running.string("ljp_name", ())?;
+        if name.trim().is_empty() {
+            anyhow::bail!("ljp_name returned an empty name");
+        }
+        let prompt = match running.has("ljp_prompt") {
+            true => Some(running.string("ljp_prompt", ())?),
+            false => None,
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine,
+            module: Arc::new(module),
+            name,
+            prompt,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn plugin_error(path: &Path, err: anyhow::Error) -> LjpError {
+    LjpError::Plugin {
+        path: path.to_path_buf(),
+        reason: format!("{:#}", err),
+    }
+}
+
+impl StudySetLoader for PluginStudySet {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        self.prompt.clone()
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> error::Result<Vec<StudyItem>> {
+        let seed: i64 = rng.random();
+        let load = || -> Result<Vec<StudyItem>> {
+            let json = Running::start(&self.engine, &self.module)?.string("ljp_load", seed)?;
+            serde_json::from_str(&json).context("ljp_load returned invalid items")
+        };
+        load().map_err(|e| plugin_error(&self.path, e))
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::{fs, path::PathBuf};
+
+use crate::paths;
+
+/// Returns where profiles live: `profiles/` in the data directory.
+fn profiles_dir() -> Result<PathBuf> {
+    paths::data_dir()
+        .map(|dir| dir.join("profiles"))
+        .context("Could not determine the data directory")
+}
+
+fn validate(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        anyhow::bail!(
+            "Invalid profile name '{}': use letters, digits, '-' and '_'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Returns the state directory of `profile`, which must exist. Without a
+/// profile, state lives directly in the data directory.
+pub fn state_dir(profile: Option<&str>) -> Result<PathBuf> {
+    let Some(name) = profile else {
+        return paths::data_dir().context("Could not determine the data directory");
+    };
+    validate(name)?;
+    let dir = profiles_dir()?.join(name);
+    if !dir.is_dir() {
+        anyhow::bail!(
+            "Profile '{}' does not exist; create it with `ljp profile create {}`",
+            name,
+            name
+        );
+    }
+    Ok(dir)
+}
+
+/// Returns the names of all profiles, sorted.
+pub fn list() -> Result<Vec<String>> {
+    let Ok(entries) = fs::read_dir(profiles_dir()?) else {
+        return Ok(Vec::new());
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+pub fn create(name: &str) -> Result<PathBuf> {
+    validate(name)?;
+    let dir = profiles_dir()?.join(name);
+    if dir.exists() {
+        anyhow::bail!("Profile '{}' already exists", name);
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+/// Deletes `name` along with all of its progress and history.
+pub fn delete(name: &str) -> Result<()> {
+    let dir = state_dir(Some(name))?;
+    fs::remove_dir_all(&dir).with_context(|| format!("Failed to delete {}", dir.display()))
+}
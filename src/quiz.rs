@@ -0,0 +1,290 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::{fs, path::Path};
+
+use crate::StudyItem;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const LINE_HEIGHT: f32 = 26.0;
+const FONT_SIZE: f32 = 14.0;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum QuizFormat {
+    Html,
+    Pdf,
+}
+
+impl QuizFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            QuizFormat::Html => "html",
+            QuizFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// Writes the worksheet to `<stem>.<ext>` and the answer key to
+/// `<stem>-answers.<ext>`, returning both paths.
+pub fn write(
+    stem: &Path,
+    format: QuizFormat,
+    title: &str,
+    items: &[StudyItem],
+) -> Result<(String, String)> {
+    let sheet_path = format!("{}.{}", stem.display(), format.extension());
+    let key_path = format!("{}-answers.{}", stem.display(), format.extension());
+
+    for (path, answers) in [(&sheet_path, false), (&key_path, true)] {
+        let contents = match format {
+            QuizFormat::Html => render_html(title, items, answers).into_bytes(),
+            QuizFormat::Pdf => render_pdf(title, items, answers),
+        };
+        fs::write(path, contents).with_context(|| format!("Failed to write {}", path))?;
+    }
+
+    Ok((sheet_path, key_path))
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(title: &str, items: &[StudyItem], answers: bool) -> String {
+    let heading = if answers {
+        format!("{} — Answer key", title)
+    } else {
+        title.to_string()
+    };
+
+    let mut rows = String::new();
+    for (i, item) in items.iter().enumerate() {
+        let answer = if answers {
+            escape_html(&item.back)
+        } else {
+            String::new()
+        };
+        let prompt = match &item.prompt {
+            Some(prompt) => format!(" <span class=\"prompt\">{}</span>", escape_html(prompt)),
+            None => String::new(),
+        };
+        rows.push_str(&format!(
+            "<tr><td class=\"n\">{}.</td><td class=\"front\">{}{}</td><td class=\"answer\">{}</td></tr>\n",
+            i + 1,
+            escape_html(&item.front),
+            prompt,
+            answer
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>{heading}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2cm; }}
+h1 {{ font-size: 1.4em; }}
+.meta {{ margin-bottom: 1em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+td {{ padding: 0.4em 0.5em; font-size: 1.3em; }}
+td.n {{ width: 2.5em; text-align: right; color: #555; }}
+td.front {{ width: 40%; }}
+.prompt {{ font-size: 0.6em; color: #555; }}
+td.answer {{ border-bottom: 1px solid #000; }}
+tr {{ page-break-inside: avoid; }}
+</style>
+</head>
+<body>
+<h1>{heading}</h1>
+<div class="meta">Name: ____________________ &nbsp; Date: ____________ &nbsp; Score: ____ / {total}</div>
+<table>
+{rows}</table>
+</body>
+</html>
+"#,
+        heading = escape_html(&heading),
+        total = items.len(),
+        rows = rows,
+    )
+}
+
+/// Encodes text as a UCS-2 hex string for the UniJIS-UCS2-H CMap.
+fn pdf_text(s: &str) -> String {
+    let mut hex = String::from("<");
+    for unit in s.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+fn line(content: &mut String, x: f32, y: f32, size: f32, text: &str) {
+    content.push_str(&format!(
+        "BT /F1 {} Tf {} {} Td {} Tj ET\n",
+        size,
+        x,
+        y,
+        pdf_text(text)
+    ));
+}
+
+/// Renders a minimal PDF using the standard HeiseiKakuGo-W5 CID font, which
+/// PDF viewers provide for Japanese text without embedding a font file.
+fn render_pdf(title: &str, items: &[StudyItem], answers: bool) -> Vec<u8> {
+    let heading = if answers {
+        format!("{} — Answer key", title)
+    } else {
+        title.to_string()
+    };
+
+    let mut pages: Vec<String> = Vec::new();
+    let mut content = String::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+
+    line(&mut content, MARGIN, y, FONT_SIZE + 4.0, &heading);
+    y -= LINE_HEIGHT;
+    line(
+        &mut content,
+        MARGIN,
+        y,
+        FONT_SIZE - 4.0,
+        &format!(
+            "Name: ________________  Date: __________  Score: ____ / {}",
+            items.len()
+        ),
+    );
+    y -= LINE_HEIGHT * 1.5;
+
+    for (i, item) in items.iter().enumerate() {
+        if y < MARGIN {
+            pages.push(std::mem::take(&mut content));
+            y = PAGE_HEIGHT - MARGIN;
+        }
+        line(&mut content, MARGIN, y, FONT_SIZE, &format!("{}.", i + 1));
+        line(&mut content, MARGIN + 40.0, y, FONT_SIZE, &item.front);
+        let answer_x = PAGE_WIDTH / 2.0;
+        if answers {
+            line(&mut content, answer_x, y, FONT_SIZE, &item.back);
+        }
+        if let Some(prompt) = &item.prompt {
+            line(&mut content, answer_x, y - 12.0, FONT_SIZE / 2.0, prompt);
+        }
+        content.push_str(&format!(
+            "{} {} m {} {} l S\n",
+            answer_x,
+            y - 4.0,
+            PAGE_WIDTH - MARGIN,
+            y - 4.0
+        ));
+        y -= LINE_HEIGHT;
+    }
+    pages.push(content);
+
+    // Object layout: 1 catalog, 2 page tree, 3-5 font, then a page and a
+    // content stream object per page.
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type0 /BaseFont /HeiseiKakuGo-W5 /Encoding /UniJIS-UCS2-H /DescendantFonts [4 0 R] >>".to_string(),
+        "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /HeiseiKakuGo-W5 /CIDSystemInfo << /Registry (Adobe) /Ordering (Japan1) /Supplement 2 >> /FontDescriptor 5 0 R /DW 1000 >>".to_string(),
+        "<< /Type /FontDescriptor /FontName /HeiseiKakuGo-W5 /Flags 4 /FontBBox [-92 -250 1010 922] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 /StemV 93 >>".to_string(),
+    ];
+
+    let mut kids = Vec::new();
+    for content in &pages {
+        let page_id = objects.len() + 1;
+        let content_id = page_id + 1;
+        kids.push(format!("{} 0 R", page_id));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH, PAGE_HEIGHT, content_id
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        pages.len()
+    );
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+
+    let xref = pdf.len();
+    pdf.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+
+    pdf.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(count: usize) -> Vec<StudyItem> {
+        (0..count)
+            .map(|i| StudyItem::new(format!("か{}", i), format!("ka{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn pdf_xref_offsets_point_at_their_objects() {
+        // Enough items for several pages.
+        let pdf = String::from_utf8(render_pdf("ljp quiz", &items(80), true)).unwrap();
+        let startxref = pdf.rsplit("startxref\n").next().unwrap();
+        let xref: usize = startxref.lines().next().unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with("xref\n"));
+
+        let entries: Vec<&str> = pdf[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .collect();
+        assert!(entries.len() > 7);
+        for (i, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+    }
+
+    #[test]
+    fn html_is_escaped_and_answers_only_in_the_key() {
+        let mut item = StudyItem::new("<b>&</b>", "\"a\"");
+        item.prompt = Some("<i>".to_string());
+        let items = [item];
+
+        let sheet = render_html("Q & A", &items, false);
+        assert!(sheet.contains("&lt;b&gt;&amp;&lt;/b&gt;"));
+        assert!(sheet.contains("&lt;i&gt;"));
+        assert!(sheet.contains("<title>Q &amp; A</title>"));
+        assert!(!sheet.contains("<b>"));
+        assert!(!sheet.contains("&quot;a&quot;"));
+
+        let key = render_html("Q & A", &items, true);
+        assert!(key.contains("&quot;a&quot;"));
+    }
+}
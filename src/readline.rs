@@ -0,0 +1,78 @@
+//! Line editing for terminal sessions: arrow keys, history of earlier
+//! answers and tab-completion of backslash commands.
+use anyhow::{Context as _, Result};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, Helper,
+};
+
+use crate::engine::Input;
+
+/// Completes `\` followed by the start of a command name.
+struct CommandCompleter {
+    names: Vec<String>,
+}
+
+impl Completer for CommandCompleter {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let Some(typed) = line[..pos].strip_prefix('\\') else {
+            return Ok((0, Vec::new()));
+        };
+        let candidates = self
+            .names
+            .iter()
+            .filter(|name| name.starts_with(typed))
+            .map(|name| format!("\\{}", name))
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for CommandCompleter {
+    type Hint = String;
+}
+
+impl Highlighter for CommandCompleter {}
+
+impl Validator for CommandCompleter {}
+
+impl Helper for CommandCompleter {}
+
+/// Reads lines from the terminal with editing and history.
+pub struct LineEditor {
+    editor: Editor<CommandCompleter, DefaultHistory>,
+}
+
+impl LineEditor {
+    /// Returns an editor that completes the given command names. Fails if
+    /// stdin is not a terminal rustyline can drive.
+    pub fn new(commands: Vec<String>) -> Result<Self> {
+        let mut editor = Editor::new().context("Failed to set up line editing")?;
+        editor.set_helper(Some(CommandCompleter { names: commands }));
+        Ok(Self { editor })
+    }
+
+    /// Reads a line after `prompt`, of which `styled` is the colored
+    /// version. Ctrl-C and Ctrl-D quit.
+    pub fn read(&mut self, prompt: &str, styled: &str) -> Result<Input> {
+        match self.editor.readline(&(prompt, styled)) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    self.editor
+                        .add_history_entry(line.as_str())
+                        .context("Failed to record input history")?;
+                }
+                Ok(Input::Line(line))
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => Ok(Input::Quit),
+            Err(e) => Err(e).context("Failed to read line from terminal"),
+        }
+    }
+}
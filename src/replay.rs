@@ -0,0 +1,243 @@
+//! Replaying sessions recorded with `ljp study --events`: the same session,
+//! seed and inputs must ask the same questions and reach the same verdicts.
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{engine::Input, events::Event, StudyItem, StudySession};
+
+/// A question or verdict a replay has to reproduce, by item id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Asked(String),
+    Answered { item: String, correct: bool },
+}
+
+impl Step {
+    /// The step `event` records, if any.
+    pub fn of(event: &Event) -> Option<Self> {
+        match event {
+            Event::Question { item } => Some(Step::Asked(item.id.clone())),
+            Event::Correct { item, .. } => Some(Step::answered(item, true)),
+            Event::Incorrect { item, .. } => Some(Step::answered(item, false)),
+            _ => None,
+        }
+    }
+
+    fn answered(item: &StudyItem, correct: bool) -> Self {
+        Step::Answered {
+            item: item.id.clone(),
+            correct,
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Asked(item) => write!(f, "asked {}", item),
+            Step::Answered {
+                item,
+                correct: true,
+            } => write!(f, "answered {} right", item),
+            Step::Answered { item, .. } => write!(f, "answered {} wrong", item),
+        }
+    }
+}
+
+/// One recorded session.
+#[derive(Debug)]
+pub struct Transcript {
+    /// The command line it was started with.
+    pub args: Vec<String>,
+    pub seed: u64,
+    pub session: StudySession,
+    /// What was typed, in order.
+    pub inputs: Vec<Input>,
+    pub steps: Vec<Step>,
+    /// How many answers counted when it ended, if it did.
+    pub answered: Option<usize>,
+}
+
+/// The parts of an event log line a replay needs.
+#[derive(Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Logged {
+    SessionStart {
+        args: Vec<String>,
+        seed: u64,
+        session: serde_json::Value,
+    },
+    Input {
+        input: Input,
+    },
+    Question {
+        item: StudyItem,
+    },
+    Correct {
+        item: StudyItem,
+    },
+    Incorrect {
+        item: StudyItem,
+    },
+    SessionEnd {
+        answered: usize,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Reads every session in the event log at `path`, oldest first. Events
+/// before the first session start, such as those of logs written before
+/// sessions were recorded, are skipped.
+pub fn read(path: &Path) -> Result<Vec<Transcript>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open event log {}", path.display()))?;
+    let mut transcripts: Vec<Transcript> = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        let logged: Logged = serde_json::from_str(&line)
+            .with_context(|| format!("{} line {} is not an event", path.display(), number + 1))?;
+        if let Logged::SessionStart {
+            args,
+            seed,
+            session,
+        } = logged
+        {
+            let session = StudySession::from_json(&session.to_string()).map_err(|reason| {
+                anyhow::anyhow!(
+                    "{} line {} has an unusable session: {}",
+                    path.display(),
+                    number + 1,
+                    reason
+                )
+            })?;
+            transcripts.push(Transcript {
+                args,
+                seed,
+                session,
+                inputs: Vec::new(),
+                steps: Vec::new(),
+                answered: None,
+            });
+            continue;
+        }
+        let Some(transcript) = transcripts.last_mut() else {
+            continue;
+        };
+        match logged {
+            Logged::Input { input } => transcript.inputs.push(input),
+            Logged::Question { item } => transcript.steps.push(Step::Asked(item.id)),
+            Logged::Correct { item } => transcript.steps.push(Step::answered(&item, true)),
+            Logged::Incorrect { item } => transcript.steps.push(Step::answered(&item, false)),
+            Logged::SessionEnd { answered } => transcript.answered = Some(answered),
+            Logged::SessionStart { .. } | Logged::Other => {}
+        }
+    }
+    Ok(transcripts)
+}
+
+/// Describes the first difference between what was `recorded` and what a
+/// replay did, if there is one.
+pub fn compare(recorded: &[Step], replayed: &[Step]) -> Result<(), String> {
+    for (index, (expected, actual)) in recorded.iter().zip(replayed).enumerate() {
+        if expected != actual {
+            return Err(format!(
+                "step {}: recorded {}, replay {}",
+                index + 1,
+                expected,
+                actual
+            ));
+        }
+    }
+    match recorded.len().cmp(&replayed.len()) {
+        std::cmp::Ordering::Less => Err(format!(
+            "the replay went on to {} after the recording ended",
+            replayed[recorded.len()]
+        )),
+        std::cmp::Ordering::Greater => Err(format!(
+            "the replay ended before the recording {}",
+            recorded[replayed.len()]
+        )),
+        std::cmp::Ordering::Equal => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventLog, Listener};
+    use std::{fs, time::Duration};
+
+    fn item(id: &str) -> StudyItem {
+        StudyItem {
+            id: id.to_string(),
+            ..StudyItem::new(id, "x")
+        }
+    }
+
+    #[test]
+    fn reads_the_sessions_in_a_log() {
+        let path = std::env::temp_dir().join(format!("ljp-replay-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let session = StudySession::from_loaders(Vec::new()).unwrap();
+        let mut log = EventLog::open(&path).unwrap();
+        log.on_event(&Event::Skipped { item: item("old") });
+        for seed in [1, 2] {
+            log.on_event(&Event::SessionStart {
+                args: vec!["ljp".to_string(), "--count".to_string(), "1".to_string()],
+                seed,
+                session: Box::new(session.clone()),
+            });
+            log.on_event(&Event::Question { item: item("a") });
+            log.on_event(&Event::Input {
+                input: Input::Line("x".to_string()),
+            });
+            log.on_event(&Event::Correct {
+                item: item("a"),
+                answer: "x".to_string(),
+            });
+            log.on_event(&Event::SessionEnd {
+                answered: 1,
+                correct: 1,
+                elapsed: Duration::ZERO,
+            });
+        }
+        drop(log);
+
+        let transcripts = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(transcripts.len(), 2);
+        let last = &transcripts[1];
+        assert_eq!((last.seed, last.answered), (2, Some(1)));
+        assert_eq!(last.args[1], "--count");
+        assert_eq!(last.inputs, [Input::Line("x".to_string())]);
+        assert_eq!(
+            last.steps,
+            [
+                Step::Asked("a".to_string()),
+                Step::Answered {
+                    item: "a".to_string(),
+                    correct: true
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_first_difference() {
+        let asked = |id: &str| Step::Asked(id.to_string());
+        assert_eq!(compare(&[asked("a")], &[asked("a")]), Ok(()));
+        assert_eq!(
+            compare(&[asked("a"), asked("b")], &[asked("a"), asked("c")]),
+            Err("step 2: recorded asked b, replay asked c".to_string())
+        );
+        assert!(compare(&[asked("a")], &[]).is_err());
+        assert!(compare(&[], &[asked("a")]).is_err());
+    }
+}
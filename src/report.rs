@@ -0,0 +1,309 @@
+//! `ljp stats report`: progress as a single self-contained HTML page, with
+//! its charts drawn as inline SVG so it can be sent to a tutor as is.
+use chrono::{Days, NaiveDate};
+use clap::ValueEnum;
+
+use crate::{history::Review, quiz::escape_html, store::SetStats};
+
+/// How many days up to today the daily charts cover.
+pub const REPORT_DAYS: u64 = 30;
+
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 160.0;
+/// The height of each set's bar in the mastery chart.
+const SET_ROW: f64 = 24.0;
+/// Room left of the mastery bars for set names.
+const SET_LABEL: f64 = 120.0;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Html,
+}
+
+/// How many reviews were done on one day and how many of them were right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Day {
+    pub day: NaiveDate,
+    pub reviews: u32,
+    pub correct: u32,
+}
+
+/// Totals `reviews` per local day, oldest first. Reviews whose timestamp
+/// cannot be read are left out.
+pub fn daily(reviews: &[Review]) -> Vec<Day> {
+    let mut days: Vec<Day> = Vec::new();
+    for review in reviews {
+        let Some(at) = review.at() else {
+            continue;
+        };
+        let day = at.date_naive();
+        let index = match days.binary_search_by_key(&day, |d| d.day) {
+            Ok(index) => index,
+            Err(index) => {
+                days.insert(
+                    index,
+                    Day {
+                        day,
+                        reviews: 0,
+                        correct: 0,
+                    },
+                );
+                index
+            }
+        };
+        days[index].reviews += 1;
+        days[index].correct += u32::from(review.correct);
+    }
+    days
+}
+
+/// Draws one bar per `(label, value)` from the bottom of the chart, with
+/// `value`s between 0 and `max`. Labels show on hover.
+fn column_chart(values: &[(String, Option<f64>)], max: f64, color: &str) -> String {
+    let slot = CHART_WIDTH / values.len().max(1) as f64;
+    let mut bars = String::new();
+    for (i, (label, value)) in values.iter().enumerate() {
+        let Some(value) = value else {
+            continue;
+        };
+        let height = if max > 0.0 {
+            value / max * CHART_HEIGHT
+        } else {
+            0.0
+        };
+        bars.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{}</title></rect>\n",
+            i as f64 * slot + 1.0,
+            CHART_HEIGHT - height,
+            (slot - 2.0).max(1.0),
+            height,
+            color,
+            escape_html(label)
+        ));
+    }
+    format!(
+        "<svg viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\" role=\"img\">\n\
+         <line x1=\"0\" y1=\"{h}\" x2=\"{w}\" y2=\"{h}\" stroke=\"#999\"/>\n{bars}</svg>",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT,
+        bars = bars
+    )
+}
+
+/// Draws a bar per set for the share of its reviewed items mastered.
+fn mastery_chart(sets: &[SetStats]) -> String {
+    let width = CHART_WIDTH - SET_LABEL;
+    let mut rows = String::new();
+    for (i, set) in sets.iter().enumerate() {
+        let share = if set.items == 0 {
+            0.0
+        } else {
+            f64::from(set.mastered) / f64::from(set.items)
+        };
+        let y = i as f64 * SET_ROW;
+        rows.push_str(&format!(
+            "<text x=\"0\" y=\"{:.1}\">{}</text>\n\
+             <rect x=\"{}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#eee\"/>\n\
+             <rect x=\"{}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#4a7\"><title>{} of {} mastered</title></rect>\n\
+             <text x=\"{:.1}\" y=\"{:.1}\" class=\"value\">{:.0}%</text>\n",
+            y + SET_ROW * 0.7,
+            escape_html(&set.set),
+            SET_LABEL,
+            y + 2.0,
+            width - 40.0,
+            SET_ROW - 4.0,
+            SET_LABEL,
+            y + 2.0,
+            share * (width - 40.0),
+            SET_ROW - 4.0,
+            set.mastered,
+            set.items,
+            CHART_WIDTH - 36.0,
+            y + SET_ROW * 0.7,
+            share * 100.0
+        ));
+    }
+    let height = sets.len() as f64 * SET_ROW;
+    format!(
+        "<svg viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\" role=\"img\">\n{rows}</svg>",
+        w = CHART_WIDTH,
+        h = height,
+        rows = rows
+    )
+}
+
+/// Renders the report: reviews and accuracy for each of the `REPORT_DAYS`
+/// days up to `today`, then how much of each set is mastered.
+pub fn render_html(days: &[Day], sets: &[SetStats], today: NaiveDate) -> String {
+    let first = today - Days::new(REPORT_DAYS - 1);
+    let shown: Vec<(NaiveDate, Option<&Day>)> = (0..REPORT_DAYS)
+        .map(|offset| {
+            let day = first + Days::new(offset);
+            (day, days.iter().find(|d| d.day == day))
+        })
+        .collect();
+    let reviews: Vec<(String, Option<f64>)> = shown
+        .iter()
+        .map(|(day, stats)| {
+            let reviews = stats.map_or(0, |d| d.reviews);
+            (
+                format!("{}: {} reviews", day, reviews),
+                Some(f64::from(reviews)),
+            )
+        })
+        .collect();
+    let accuracy: Vec<(String, Option<f64>)> = shown
+        .iter()
+        .map(|(day, stats)| match stats {
+            Some(d) if d.reviews > 0 => {
+                let percent = f64::from(d.correct) / f64::from(d.reviews) * 100.0;
+                (format!("{}: {:.0}% correct", day, percent), Some(percent))
+            }
+            _ => (format!("{}: no reviews", day), None),
+        })
+        .collect();
+    let most = reviews
+        .iter()
+        .filter_map(|(_, value)| *value)
+        .fold(0.0, f64::max);
+
+    let total_reviews: u32 = shown
+        .iter()
+        .filter_map(|(_, d)| *d)
+        .map(|d| d.reviews)
+        .sum();
+    let total_correct: u32 = shown
+        .iter()
+        .filter_map(|(_, d)| *d)
+        .map(|d| d.correct)
+        .sum();
+    let overall = if total_reviews == 0 {
+        0.0
+    } else {
+        f64::from(total_correct) / f64::from(total_reviews) * 100.0
+    };
+
+    let mut table = String::new();
+    for set in sets {
+        table.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.0}%</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&set.set),
+            set.items,
+            set.reviews,
+            set.accuracy() * 100.0,
+            set.mastered,
+            set.struggling
+        ));
+    }
+    let mastery = if sets.is_empty() {
+        "<p>No progress recorded yet.</p>".to_string()
+    } else {
+        format!(
+            "{}\n<table>\n<tr><th>Set</th><th>Items</th><th>Reviews</th><th>Accuracy</th><th>Mastered</th><th>Struggling</th></tr>\n{}</table>",
+            mastery_chart(sets),
+            table
+        )
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>ljp progress report</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em auto; max-width: 680px; color: #222; }}
+h1 {{ font-size: 1.4em; }}
+h2 {{ font-size: 1.1em; margin-top: 2em; }}
+.meta, .axis {{ color: #555; font-size: 0.9em; }}
+.axis {{ display: flex; justify-content: space-between; width: {width}px; }}
+svg {{ display: block; font-size: 13px; }}
+svg .value {{ fill: #555; }}
+table {{ border-collapse: collapse; margin-top: 1em; }}
+th, td {{ padding: 0.3em 0.8em; text-align: right; }}
+th:first-child, td:first-child {{ text-align: left; }}
+tr + tr {{ border-top: 1px solid #ddd; }}
+</style>
+</head>
+<body>
+<h1>ljp progress report</h1>
+<div class="meta">{first} to {today}: {total_reviews} reviews, {overall:.0}% correct overall</div>
+<h2>Reviews per day</h2>
+{reviews_chart}
+<div class="axis"><span>{first}</span><span>most: {most}</span><span>{today}</span></div>
+<h2>Accuracy per day</h2>
+{accuracy_chart}
+<div class="axis"><span>{first}</span><span>0–100%</span><span>{today}</span></div>
+<h2>Mastery per set</h2>
+{mastery}
+</body>
+</html>
+"#,
+        width = CHART_WIDTH,
+        first = first,
+        today = today,
+        total_reviews = total_reviews,
+        overall = overall,
+        reviews_chart = column_chart(&reviews, most, "#47a"),
+        most = most,
+        accuracy_chart = column_chart(&accuracy, 100.0, "#4a7"),
+        mastery = mastery,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(timestamp: &str, correct: bool) -> Review {
+        Review {
+            timestamp: timestamp.to_string(),
+            set: "hiragana".to_string(),
+            front: "あ".to_string(),
+            back: "a".to_string(),
+            answer: "a".to_string(),
+            correct,
+            latency_ms: 0,
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn reviews_are_totalled_per_day() {
+        let days = daily(&[
+            review("2026-03-02T10:00:00+00:00", true),
+            review("2026-03-01T10:00:00+00:00", false),
+            review("not a time", true),
+            review("2026-03-02T11:00:00+00:00", false),
+        ]);
+        let totals: Vec<(u32, u32)> = days.iter().map(|d| (d.reviews, d.correct)).collect();
+        assert_eq!(totals, [(1, 0), (2, 1)]);
+        assert!(days[0].day < days[1].day);
+    }
+
+    #[test]
+    fn reports_are_self_contained() {
+        let today = date("2026-03-10");
+        let days = [Day {
+            day: date("2026-03-09"),
+            reviews: 4,
+            correct: 3,
+        }];
+        let sets = [SetStats {
+            set: "<kana>".to_string(),
+            items: 4,
+            mastered: 1,
+            reviews: 4,
+            ..SetStats::default()
+        }];
+        let html = render_html(&days, &sets, today);
+        assert!(html.contains("4 reviews, 75% correct overall"));
+        assert!(html.contains("2026-03-09: 75% correct"));
+        assert!(html.contains("&lt;kana&gt;") && !html.contains("<kana>"));
+        assert!(!html.contains("<script") && !html.contains("src="));
+        assert_eq!(html.matches("<svg").count(), 3);
+    }
+}
@@ -0,0 +1,199 @@
+//! `ljp stats retention`: how often items were recalled against how long
+//! it had been since they were last reviewed, per set, from the review
+//! history. A forgetting curve that drops early means intervals are too
+//! long for that set.
+use chrono::{DateTime, Local, TimeDelta};
+use std::collections::HashMap;
+
+use crate::history::Review;
+
+/// The upper bounds of the time-since-last-review buckets, with labels.
+/// Longer gaps fall in a last, open bucket.
+const BUCKETS: [(i64, &str); 6] = [
+    (10 * 60, "< 10 min"),
+    (60 * 60, "< 1 hour"),
+    (24 * 60 * 60, "< 1 day"),
+    (3 * 24 * 60 * 60, "< 3 days"),
+    (7 * 24 * 60 * 60, "< 1 week"),
+    (30 * 24 * 60 * 60, "< 30 days"),
+];
+const LAST_BUCKET: &str = "30+ days";
+/// The width of a bar standing for 100% recall.
+const BAR_WIDTH: usize = 30;
+
+/// Reviews that came a similar time after the one before, and how many
+/// of them were answered right.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Point {
+    pub reviews: u32,
+    pub recalled: u32,
+}
+
+impl Point {
+    pub fn recall(&self) -> f64 {
+        if self.reviews == 0 {
+            0.0
+        } else {
+            f64::from(self.recalled) / f64::from(self.reviews)
+        }
+    }
+}
+
+/// One set's forgetting curve: a point per bucket, shortest gap first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub set: String,
+    pub points: Vec<Point>,
+}
+
+/// The bucket labels, in the order of each curve's points.
+pub fn labels() -> Vec<&'static str> {
+    BUCKETS
+        .iter()
+        .map(|&(_, label)| label)
+        .chain(std::iter::once(LAST_BUCKET))
+        .collect()
+}
+
+fn bucket(gap: TimeDelta) -> usize {
+    BUCKETS
+        .iter()
+        .position(|&(bound, _)| gap.num_seconds() < bound)
+        .unwrap_or(BUCKETS.len())
+}
+
+/// Builds a curve per set from `reviews`, oldest first, by set name. The
+/// first review of an item has nothing to be measured against and only
+/// starts its clock.
+pub fn curves(reviews: &[Review]) -> Vec<Curve> {
+    let mut last_seen: HashMap<(&str, &str, &str), DateTime<Local>> = HashMap::new();
+    let mut curves: Vec<Curve> = Vec::new();
+    for review in reviews {
+        let Some(at) = review.at() else {
+            continue;
+        };
+        let key = (
+            review.set.as_str(),
+            review.front.as_str(),
+            review.back.as_str(),
+        );
+        let Some(before) = last_seen.insert(key, at) else {
+            continue;
+        };
+        let index = match curves.binary_search_by(|curve| curve.set.as_str().cmp(&review.set)) {
+            Ok(index) => index,
+            Err(index) => {
+                let curve = Curve {
+                    set: review.set.clone(),
+                    points: vec![Point::default(); BUCKETS.len() + 1],
+                };
+                curves.insert(index, curve);
+                index
+            }
+        };
+        let point = &mut curves[index].points[bucket(at - before)];
+        point.reviews += 1;
+        point.recalled += u32::from(review.correct);
+    }
+    curves
+}
+
+/// Draws each curve as a bar per bucket, as long as the share recalled,
+/// with the number of reviews it is based on.
+pub fn render(curves: &[Curve]) -> String {
+    let labels = labels();
+    let mut chart = String::new();
+    for curve in curves {
+        let name = if curve.set.is_empty() {
+            "(no set)"
+        } else {
+            &curve.set
+        };
+        chart.push_str(&format!("{}\n", name));
+        for (label, point) in labels.iter().zip(&curve.points) {
+            if point.reviews == 0 {
+                chart.push_str(&format!(
+                    "  {:<9} {:<width$}    -\n",
+                    label,
+                    "",
+                    width = BAR_WIDTH
+                ));
+                continue;
+            }
+            let filled = (point.recall() * BAR_WIDTH as f64).round() as usize;
+            chart.push_str(&format!(
+                "  {:<9} {}{} {:>3.0}% ({} reviews)\n",
+                label,
+                "█".repeat(filled),
+                "░".repeat(BAR_WIDTH - filled),
+                point.recall() * 100.0,
+                point.reviews
+            ));
+        }
+    }
+    chart
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(set: &str, front: &str, timestamp: &str, correct: bool) -> Review {
+        Review {
+            timestamp: timestamp.to_string(),
+            set: set.to_string(),
+            front: front.to_string(),
+            back: "x".to_string(),
+            answer: "x".to_string(),
+            correct,
+            latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn gaps_between_reviews_of_an_item_are_bucketed_per_set() {
+        let curves = curves(&[
+            review("kana", "あ", "2026-03-01T10:00:00+00:00", true),
+            review("kana", "い", "2026-03-01T10:01:00+00:00", true),
+            review("kana", "あ", "2026-03-01T10:05:00+00:00", false),
+            review("kana", "あ", "2026-03-03T10:05:00+00:00", true),
+            review("dates", "あ", "2026-03-03T10:06:00+00:00", true),
+        ]);
+        assert_eq!(curves.len(), 1);
+        assert_eq!(curves[0].set, "kana");
+        let points = &curves[0].points;
+        assert_eq!(
+            points[0],
+            Point {
+                reviews: 1,
+                recalled: 0
+            }
+        );
+        assert_eq!(
+            points[3],
+            Point {
+                reviews: 1,
+                recalled: 1
+            }
+        );
+        assert_eq!(points.iter().map(|p| p.reviews).sum::<u32>(), 2);
+    }
+
+    #[test]
+    fn bars_follow_recall() {
+        let mut points = vec![Point::default(); BUCKETS.len() + 1];
+        points[1] = Point {
+            reviews: 4,
+            recalled: 2,
+        };
+        let chart = render(&[Curve {
+            set: "kana".to_string(),
+            points,
+        }]);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines[0], "kana");
+        assert!(lines[1].trim_end().ends_with('-'));
+        assert_eq!(lines[2].matches('█').count(), BAR_WIDTH / 2);
+        assert!(lines[2].ends_with(" 50% (4 reviews)"));
+    }
+}
@@ -0,0 +1,137 @@
+//! Romaji to kana transliteration, as done by a Japanese IME: `kya` →
+//! きゃ, `kka` → っか, `nn` or `n'` → ん.
+use anyhow::Result;
+
+use crate::{middleware::Middleware, StudyItem};
+
+/// `romaji,kana` pairs, including Kunrei spellings and `x`/`l`-prefixed
+/// small kana.
+const TABLE: &str = include_str!("../assets/romaji.csv");
+/// The longest romaji spelling in `TABLE`.
+const LONGEST: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    Hiragana,
+    Katakana,
+}
+
+impl Script {
+    /// Katakana if `text` contains any, so answers are typed in the script
+    /// they are expected in.
+    pub fn of(text: &str) -> Self {
+        if text.chars().any(|c| ('\u{30A1}'..='\u{30FA}').contains(&c)) {
+            Script::Katakana
+        } else {
+            Script::Hiragana
+        }
+    }
+}
+
+fn lookup(romaji: &str) -> Option<&'static str> {
+    TABLE
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .find(|(r, _)| *r == romaji)
+        .map(|(_, kana)| kana)
+}
+
+fn to_katakana(hiragana: &str) -> String {
+    hiragana
+        .chars()
+        .map(|c| match c {
+            'ぁ'..='ゖ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+fn is_consonant(c: char) -> bool {
+    c.is_ascii_alphabetic() && !"aiueo".contains(c)
+}
+
+/// Converts `romaji` to kana. Anything that does not spell kana is kept
+/// as typed. Unless `finished`, a trailing `n` is kept too, since it may
+/// still become な, に, etc.
+fn convert(romaji: &str, script: Script, finished: bool) -> String {
+    let chars: Vec<char> = romaji.to_lowercase().chars().collect();
+    let mut kana = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let next = chars.get(i + 1).copied();
+        if chars[i] == 'n' {
+            match next {
+                // `nna` is んな, as in おんな; `nn` on its own is ん.
+                Some('n') if chars.get(i + 2).is_some_and(|&c| "aiueoy".contains(c)) => {
+                    kana.push('ん');
+                    i += 1;
+                    continue;
+                }
+                Some('n' | '\'') => {
+                    kana.push('ん');
+                    i += 2;
+                    continue;
+                }
+                Some(c) if c != 'y' && is_consonant(c) => {
+                    kana.push('ん');
+                    i += 1;
+                    continue;
+                }
+                None if finished => {
+                    kana.push('ん');
+                    i += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        if is_consonant(chars[i]) && next == Some(chars[i]) {
+            kana.push('っ');
+            i += 1;
+            continue;
+        }
+        let matched = (1..=LONGEST.min(chars.len() - i)).rev().find_map(|len| {
+            let spelling: String = chars[i..i + len].iter().collect();
+            lookup(&spelling).map(|k| (k, len))
+        });
+        match matched {
+            Some((k, len)) => {
+                kana.push_str(k);
+                i += len;
+            }
+            None => {
+                kana.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    match script {
+        Script::Hiragana => kana,
+        Script::Katakana => to_katakana(&kana),
+    }
+}
+
+/// Converts a complete answer typed in romaji to kana.
+pub fn to_kana(romaji: &str, script: Script) -> String {
+    convert(romaji, script, true)
+}
+
+/// Converts romaji that is still being typed, for live display.
+pub fn preview(romaji: &str, script: Script) -> String {
+    convert(romaji, script, false)
+}
+
+/// Accepts kana that the romaji front spells just as well as the expected
+/// back, e.g. ず for a づ card asked as `zu`, since dictation cannot tell
+/// them apart.
+#[derive(Debug, Default)]
+pub struct DictationMiddleware;
+
+impl Middleware for DictationMiddleware {
+    fn before_answer(&mut self, item: &StudyItem, answer: &mut String) -> Result<()> {
+        if *answer != item.back && to_kana(&item.front, Script::of(&item.back)) == *answer {
+            *answer = item.back.clone();
+        }
+        Ok(())
+    }
+}
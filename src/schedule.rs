@@ -0,0 +1,211 @@
+//! The per-item scheduling view behind `\w` and `ljp stats --items`: each
+//! item's weight, ease, interval, due date, lapses and box, sortable by
+//! any of them.
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+
+use crate::fsrs::Memory;
+
+/// What is known about scheduling one item. Columns the scheduler in use
+/// does not have are `None`: only FSRS has an ease, interval and due date
+/// and only Leitner boxes have a box.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ItemSchedule {
+    pub front: String,
+    pub back: String,
+    pub weight: Option<u32>,
+    /// The FSRS difficulty, from 1 (easy) to 10.
+    pub ease: Option<f64>,
+    /// The FSRS stability: days after a review until recall drops to 90%.
+    pub interval: Option<f64>,
+    /// When recall drops to 90%.
+    pub due: Option<DateTime<Utc>>,
+    pub lapses: u32,
+    pub leitner_box: Option<u8>,
+}
+
+impl ItemSchedule {
+    /// Fills in the ease, interval and due date from an FSRS memory.
+    pub fn with_memory(mut self, memory: Option<Memory>) -> Self {
+        if let Some(memory) = memory {
+            self.ease = Some(memory.difficulty);
+            self.interval = Some(memory.stability);
+            self.due = DateTime::from_timestamp(memory.due() as i64, 0);
+        }
+        self
+    }
+
+    /// The value shown in `column`, if the item has one.
+    pub fn cell(&self, column: Column) -> Option<String> {
+        match column {
+            Column::Weight => self.weight.map(|weight| weight.to_string()),
+            Column::Ease => self.ease.map(|ease| format!("{:.1}", ease)),
+            Column::Interval => self.interval.map(|days| format!("{:.1}d", days)),
+            Column::Due => self.due.map(|due| {
+                due.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            }),
+            Column::Lapses => Some(self.lapses.to_string()),
+            Column::Box => self.leitner_box.map(|b| b.to_string()),
+        }
+    }
+
+    /// Orders by `column` so the item most in need of study comes first.
+    fn key(&self, column: Column) -> Option<f64> {
+        match column {
+            Column::Weight => self.weight.map(|weight| -f64::from(weight)),
+            Column::Ease => self.ease.map(|ease| -ease),
+            Column::Interval => self.interval,
+            Column::Due => self.due.map(|due| due.timestamp() as f64),
+            Column::Lapses => Some(-f64::from(self.lapses)),
+            Column::Box => self.leitner_box.map(f64::from),
+        }
+    }
+}
+
+/// A column of the schedule, in the order they are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    /// Heaviest first
+    Weight,
+    /// Hardest first
+    Ease,
+    /// Shortest first
+    Interval,
+    /// Soonest first
+    Due,
+    /// Most first
+    Lapses,
+    /// Lowest first
+    Box,
+}
+
+impl Column {
+    fn header(self) -> &'static str {
+        match self {
+            Column::Weight => "Weight",
+            Column::Ease => "Ease",
+            Column::Interval => "Interval",
+            Column::Due => "Due",
+            Column::Lapses => "Lapses",
+            Column::Box => "Box",
+        }
+    }
+
+    fn width(self) -> usize {
+        match self {
+            Column::Due => 16,
+            _ => 8,
+        }
+    }
+
+    /// The names columns are sorted by, e.g. for help texts.
+    pub fn names() -> Vec<String> {
+        Self::value_variants()
+            .iter()
+            .filter_map(|column| column.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect()
+    }
+}
+
+/// Sorts `rows` by `column`, most in need of study first. Rows without a
+/// value in it go last, in their previous order.
+pub fn sort(rows: &mut [ItemSchedule], column: Column) {
+    rows.sort_by(|a, b| match (a.key(column), b.key(column)) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    });
+}
+
+/// The columns any of `rows` has a value in.
+pub fn columns(rows: &[ItemSchedule]) -> Vec<Column> {
+    Column::value_variants()
+        .iter()
+        .copied()
+        .filter(|&column| rows.iter().any(|row| row.cell(column).is_some()))
+        .collect()
+}
+
+/// Lays `rows` out as a table with a header, leaving out columns none of
+/// them has. The item goes last, where its width cannot misalign the rest.
+pub fn table(rows: &[ItemSchedule]) -> String {
+    let columns = columns(rows);
+    let line = |cells: Vec<String>, item: &str| {
+        let cells: Vec<String> = columns
+            .iter()
+            .zip(cells)
+            .map(|(column, cell)| format!("{:>width$}", cell, width = column.width()))
+            .collect();
+        format!("{}  {}", cells.join(" "), item)
+    };
+    let header = columns.iter().map(|c| c.header().to_string()).collect();
+    std::iter::once(line(header, "Item"))
+        .chain(rows.iter().map(|row| {
+            let cells = columns
+                .iter()
+                .map(|&column| row.cell(column).unwrap_or_else(|| "-".to_string()))
+                .collect();
+            line(cells, &format!("{} / {}", row.front, row.back))
+        }))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(front: &str, weight: Option<u32>, interval: Option<f64>) -> ItemSchedule {
+        ItemSchedule {
+            front: front.to_string(),
+            back: "x".to_string(),
+            weight,
+            interval,
+            ..ItemSchedule::default()
+        }
+    }
+
+    fn fronts(rows: &[ItemSchedule]) -> Vec<&str> {
+        rows.iter().map(|row| row.front.as_str()).collect()
+    }
+
+    #[test]
+    fn sorts_most_in_need_first_and_missing_values_last() {
+        let mut rows = vec![
+            row("a", Some(1), None),
+            row("b", Some(5), Some(3.0)),
+            row("c", Some(2), Some(0.5)),
+        ];
+        sort(&mut rows, Column::Weight);
+        assert_eq!(fronts(&rows), ["b", "c", "a"]);
+        sort(&mut rows, Column::Interval);
+        assert_eq!(fronts(&rows), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn memories_fill_in_ease_interval_and_due() {
+        let memory = Memory {
+            stability: 2.0,
+            difficulty: 5.0,
+            last_review: 0,
+        };
+        let row = row("a", None, None).with_memory(Some(memory));
+        assert_eq!((row.ease, row.interval), (Some(5.0), Some(2.0)));
+        assert_eq!(row.due.unwrap().timestamp(), 2 * 86_400);
+    }
+
+    #[test]
+    fn tables_leave_out_empty_columns() {
+        let rows = [row("a", Some(3), None), row("b", Some(1), Some(1.5))];
+        let table = table(&rows);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(
+            lines[0].split_whitespace().collect::<Vec<_>>(),
+            ["Weight", "Interval", "Lapses", "Item"]
+        );
+        assert!(lines[1].contains(" - ") && lines[1].ends_with("a / x"));
+        assert!(lines[2].contains("1.5d"));
+    }
+}
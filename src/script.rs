@@ -0,0 +1,235 @@
+//! Deck scripts in [Rhai](https://rhai.rs), which generate items or grade
+//! answers in ways a CSV file cannot.
+//!
+//! A script may define either hook, or both:
+//!
+//! ```rhai
+//! // Items added to the deck's own each time it is loaded, as
+//! // [front, back] pairs or maps with any `StudyItem` fields.
+//! fn generate() {
+//!     let items = [];
+//!     for n in 1..=9 {
+//!         items.push([`${n} × 2`, `${n * 2}`]);
+//!     }
+//!     items.push(#{ front: "十", back: "じゅう", tags: ["n5"] });
+//!     items
+//! }
+//!
+//! // Whether `given` counts as `expected`, replacing the deck's grading.
+//! fn grade(expected, given) {
+//!     given == expected || given == expected + "ます"
+//! }
+//! ```
+//!
+//! Scripts cannot touch files or the network, nor `import` other
+//! scripts or print to the terminal, and each call is limited in how many
+//! operations it may run and how large its strings, arrays and maps may
+//! grow.
+use rhai::{module_resolvers::DummyModuleResolver, Array, Dynamic, Engine, AST};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{error::Result, grader::Grader, LjpError, StudyItem};
+
+/// The file extension of deck scripts.
+pub const EXTENSION: &str = "rhai";
+/// How many operations a single call may run before it is stopped.
+const MAX_OPERATIONS: u64 = 10_000_000;
+/// How deeply expressions may nest, the same in debug and release builds.
+const MAX_DEPTH: usize = 64;
+/// The largest string, in bytes, array and map a script may build; enough
+/// for any deck, far from enough to run out of memory.
+const MAX_STRING_SIZE: usize = 1 << 20;
+const MAX_ARRAY_SIZE: usize = 100_000;
+const MAX_MAP_SIZE: usize = 10_000;
+
+/// A compiled deck script.
+#[derive(Debug, Clone)]
+pub struct Script {
+    path: PathBuf,
+    engine: Rc<Engine>,
+    ast: Rc<AST>,
+}
+
+impl Script {
+    /// Compiles the script at `path`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path).map_err(|source| LjpError::Io {
+            action: "read script",
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::compile(path, &source)
+    }
+
+    fn compile(path: &Path, source: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_expr_depths(MAX_DEPTH, MAX_DEPTH);
+        engine.set_max_string_size(MAX_STRING_SIZE);
+        engine.set_max_array_size(MAX_ARRAY_SIZE);
+        engine.set_max_map_size(MAX_MAP_SIZE);
+        // Output would land in the middle of the quiz.
+        engine.on_print(|_| {});
+        engine.on_debug(|_, _, _| {});
+        // The default resolver loads modules from the file system.
+        engine.set_module_resolver(DummyModuleResolver::new());
+        let ast = engine
+            .compile(source)
+            .map_err(|e| script_error(path, e.to_string()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            engine: Rc::new(engine),
+            ast: Rc::new(ast),
+        })
+    }
+
+    fn defines(&self, name: &str, params: usize) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|f| f.name == name && f.params.len() == params)
+    }
+
+    /// Whether the script has a `grade(expected, given)` hook.
+    pub fn grades(&self) -> bool {
+        self.defines("grade", 2)
+    }
+
+    /// Runs `generate()`, or returns no items if the script has none.
+    pub fn generate(&self) -> Result<Vec<StudyItem>> {
+        if !self.defines("generate", 0) {
+            return Ok(Vec::new());
+        }
+        let items: Array = self
+            .engine
+            .call_fn(&mut rhai::Scope::new(), &self.ast, "generate", ())
+            .map_err(|e| script_error(&self.path, format!("generate() failed: {}", e)))?;
+        items
+            .into_iter()
+            .map(|item| {
+                to_item(item).map_err(|reason| {
+                    script_error(&self.path, format!("generate() returned {}", reason))
+                })
+            })
+            .collect()
+    }
+}
+
+/// Converts a `[front, back]` pair or a map of item fields.
+fn to_item(value: Dynamic) -> std::result::Result<StudyItem, String> {
+    if value.is_array() {
+        let pair = value.cast::<Array>();
+        return match pair.as_slice() {
+            [front, back] => Ok(StudyItem::new(front.to_string(), back.to_string())),
+            _ => Err(format!("a list of {} values, not a pair", pair.len())),
+        };
+    }
+    rhai::serde::from_dynamic(&value).map_err(|e| format!("an invalid item: {}", e))
+}
+
+fn script_error(path: &Path, reason: String) -> LjpError {
+    LjpError::Script {
+        path: path.to_path_buf(),
+        reason,
+    }
+}
+
+/// Grades with the script's `grade` hook. A hook that fails or returns
+/// something other than a bool is reported and counts the answer wrong.
+impl Grader for Script {
+    fn accepts(&self, expected: &str, answer: &str) -> bool {
+        let args = (expected.to_string(), answer.to_string());
+        match self
+            .engine
+            .call_fn::<bool>(&mut rhai::Scope::new(), &self.ast, "grade", args)
+        {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!(
+                    "Warning: {}",
+                    script_error(&self.path, format!("grade() failed: {}", e))
+                );
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(source: &str) -> Script {
+        Script::compile(Path::new("test.rhai"), source).unwrap()
+    }
+
+    #[test]
+    fn generates_pairs_and_maps() {
+        let items = script(
+            r#"fn generate() { [["一", "いち"], #{ front: "二", back: "に", tags: ["n5"] }] }"#,
+        )
+        .generate()
+        .unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            (items[0].front.as_str(), items[0].back.as_str()),
+            ("一", "いち")
+        );
+        assert_eq!(items[1].tags, ["n5"]);
+    }
+
+    #[test]
+    fn grades_with_the_hook() {
+        let script = script(r#"fn grade(expected, given) { given == expected + "ます" }"#);
+        assert!(script.grades());
+        assert!(script.accepts("食べ", "食べます"));
+        assert!(!script.accepts("食べ", "食べ"));
+    }
+
+    #[test]
+    fn cannot_import_modules() {
+        let dir = std::env::temp_dir().join(format!("ljp-script-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("secret.rhai"),
+            "export const ITEMS = [[\"a\", \"b\"]];",
+        )
+        .unwrap();
+        let path = dir.join("deck.rhai");
+        fs::write(
+            &path,
+            format!(
+                "fn generate() {{ import {:?} as secret; secret::ITEMS }}",
+                dir.join("secret").display().to_string()
+            ),
+        )
+        .unwrap();
+
+        let err = Script::from_path(&path).unwrap().generate();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(err, Err(LjpError::Script { .. })), "{:?}", err);
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        assert!(script("fn generate() { loop {} }").generate().is_err());
+    }
+
+    #[test]
+    fn scripts_cannot_grow_strings_without_bound() {
+        let err =
+            script(r#"fn generate() { let s = "x"; for i in 0..40 { s += s; } [] }"#).generate();
+        assert!(matches!(err, Err(LjpError::Script { .. })), "{:?}", err);
+    }
+
+    #[test]
+    fn scripts_cannot_print() {
+        let items = script(r#"fn generate() { print("hi"); debug("hi"); [["a", "b"]] }"#)
+            .generate()
+            .unwrap();
+        assert_eq!(items.len(), 1);
+    }
+}
@@ -0,0 +1,365 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    engine::{Input, Render, SessionEngine, State},
+    StudySession,
+};
+
+const INDEX_HTML: &str = include_str!("../web/index.html");
+const APP_JS: &str = include_str!("../web/app.js");
+const STYLE_CSS: &str = include_str!("../web/style.css");
+
+/// The largest request body accepted; answers are a few bytes.
+const MAX_BODY: usize = 16 * 1024;
+/// How much of the request line and headers is read before giving up.
+const MAX_HEAD: u64 = 16 * 1024;
+/// How long a connection may stall, so an idle client cannot block the
+/// others.
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many sessions are kept at once; the least recently used goes
+/// first.
+const MAX_CLIENTS: usize = 64;
+/// How long an unused session is kept.
+const CLIENT_IDLE: Duration = Duration::from_secs(60 * 60);
+
+struct Request {
+    method: String,
+    path: String,
+    client: String,
+    body: String,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn json(value: serde_json::Value) -> Self {
+        Self::ok("application/json", value.to_string())
+    }
+
+    fn error(status: &'static str, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: json!({ "error": message }).to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AnswerBody {
+    answer: String,
+}
+
+/// A request whose Content-Length is over [`MAX_BODY`].
+#[derive(Debug)]
+struct BodyTooLarge;
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request body is over {} bytes", MAX_BODY)
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+/// One browser tab's session and when it was last used.
+struct Client {
+    engine: SessionEngine,
+    last_seen: Instant,
+}
+
+fn read_request(stream: impl Read) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut head = (&mut reader).take(MAX_HEAD);
+
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)
+        .context("Failed to read request line")?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        let read = head
+            .read_line(&mut header)
+            .context("Failed to read request header")?;
+        if read == 0 {
+            anyhow::bail!("Request headers ended early or were too long");
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+
+    if content_length > MAX_BODY {
+        return Err(BodyTooLarge.into());
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .context("Failed to read request body")?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let client = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "client")
+        .map(|(_, value)| value.to_string())
+        .unwrap_or_default();
+
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        client,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn write_response(stream: &mut TcpStream, response: Response) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )
+    .context("Failed to write response")
+}
+
+/// Drops sessions unused for [`CLIENT_IDLE`], then the least recently
+/// used ones until there is room for another.
+fn evict(clients: &mut HashMap<String, Client>, now: Instant) {
+    clients.retain(|_, client| now.duration_since(client.last_seen) < CLIENT_IDLE);
+    while clients.len() >= MAX_CLIENTS {
+        let Some(oldest) = clients
+            .iter()
+            .min_by_key(|(_, client)| client.last_seen)
+            .map(|(id, _)| id.clone())
+        else {
+            break;
+        };
+        clients.remove(&oldest);
+    }
+}
+
+fn handle(
+    request: Request,
+    set_names: &[String],
+    clients: &mut HashMap<String, Client>,
+) -> Result<Response> {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => return Ok(Response::ok("text/html", INDEX_HTML)),
+        ("GET", "/app.js") => return Ok(Response::ok("text/javascript", APP_JS)),
+        ("GET", "/style.css") => return Ok(Response::ok("text/css", STYLE_CSS)),
+        _ => {}
+    }
+
+    if request.client.is_empty() {
+        return Ok(Response::error("400 Bad Request", "Missing client id"));
+    }
+    // Each browser tab studies in its own session, identified by the random
+    // `client` query parameter the front-end sends with every request.
+    let now = Instant::now();
+    if !clients.contains_key(&request.client) {
+        evict(clients, now);
+        let mut engine = SessionEngine::new(StudySession::new(set_names.to_vec())?);
+        engine.start()?;
+        clients.insert(
+            request.client.clone(),
+            Client {
+                engine,
+                last_seen: now,
+            },
+        );
+    }
+    let client = clients
+        .get_mut(&request.client)
+        .expect("client was just inserted");
+    client.last_seen = now;
+    let engine = &mut client.engine;
+
+    let response = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/card") => {
+            if let State::ShowingFeedback = engine.state() {
+                engine.handle(Input::Continue)?;
+            }
+            match engine.state() {
+                State::AwaitingAnswer { item, .. } => Response::json(json!({
+                    "front": item.front,
+                    "prompt": item.prompt,
+                    "label": item.label,
+                })),
+                _ => Response::json(json!({})),
+            }
+        }
+        ("POST", "/api/answer") => {
+            let body: AnswerBody = match serde_json::from_str(&request.body) {
+                Ok(body) => body,
+                Err(_) => return Ok(Response::error("400 Bad Request", "Invalid answer body")),
+            };
+            match engine.answer(body.answer)?.first() {
+                Some(Render::Correct(item)) => {
+                    Response::json(json!({ "correct": true, "expected": item.back }))
+                }
+                Some(Render::Incorrect(item)) => {
+                    Response::json(json!({ "correct": false, "expected": item.back }))
+                }
+                _ => Response::error("409 Conflict", "No card is being shown"),
+            }
+        }
+        ("GET", "/api/weights") => {
+            let weighted_items = engine
+                .weights()
+                .into_iter()
+                .map(|(weight, front, back)| {
+                    json!({ "front": front, "back": back, "weight": weight })
+                })
+                .collect();
+            Response::json(serde_json::Value::Array(weighted_items))
+        }
+        _ => Response::error("404 Not Found", "Not found"),
+    };
+
+    Ok(response)
+}
+
+/// Serves the bundled web front-end on `bind` until the process is killed.
+/// Requests are handled one at a time, which is plenty for a few devices;
+/// slow connections time out rather than hold up the rest.
+pub fn run(bind: &str, set_names: Vec<String>) -> Result<()> {
+    // Unknown sets would otherwise fail every request.
+    StudySession::new(set_names.clone())?;
+    let listener = TcpListener::bind(bind).with_context(|| format!("Failed to bind {}", bind))?;
+    println!("Serving ljp on http://{}", bind);
+
+    let mut clients = HashMap::new();
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Warning: Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = stream
+            .set_read_timeout(Some(IO_TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+        {
+            eprintln!("Warning: Failed to set connection timeouts: {}", e);
+            continue;
+        }
+
+        let response = read_request(&mut stream)
+            .and_then(|request| handle(request, &set_names, &mut clients))
+            .unwrap_or_else(|e| {
+                if e.is::<BodyTooLarge>() {
+                    return Response::error("413 Payload Too Large", "Request body is too large");
+                }
+                eprintln!("Warning: {:#}", e);
+                Response::error("500 Internal Server Error", "Internal error")
+            });
+
+        if let Err(e) = write_response(&mut stream, response) {
+            eprintln!("Warning: {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_request_with_its_body_and_client() {
+        let raw =
+            "POST /api/answer?client=abc HTTP/1.1\r\nContent-Length: 14\r\n\r\n{\"answer\":\"a\"}";
+        let request = read_request(raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/answer");
+        assert_eq!(request.client, "abc");
+        assert_eq!(request.body, "{\"answer\":\"a\"}");
+    }
+
+    #[test]
+    fn refuses_oversized_bodies_before_reading_them() {
+        let raw = format!(
+            "POST /api/answer HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            usize::MAX
+        );
+        let err = read_request(raw.as_bytes()).err().unwrap();
+        assert!(err.is::<BodyTooLarge>());
+    }
+
+    #[test]
+    fn refuses_endless_headers() {
+        let raw = format!(
+            "GET / HTTP/1.1\r\nX-Junk: {}",
+            "a".repeat(MAX_HEAD as usize * 2)
+        );
+        assert!(read_request(raw.as_bytes()).is_err());
+    }
+
+    fn client(last_seen: Instant) -> Client {
+        Client {
+            engine: SessionEngine::new(StudySession::from_loaders(Vec::new()).unwrap()),
+            last_seen,
+        }
+    }
+
+    #[test]
+    fn evicts_idle_and_least_recent_clients() {
+        let now = Instant::now() + CLIENT_IDLE * 2;
+        let mut clients = HashMap::new();
+        clients.insert("idle".to_string(), client(now - CLIENT_IDLE));
+        for i in 0..MAX_CLIENTS - 1 {
+            clients.insert(
+                i.to_string(),
+                client(now - Duration::from_secs(i as u64 + 1)),
+            );
+        }
+
+        evict(&mut clients, now);
+
+        assert!(!clients.contains_key("idle"));
+        assert_eq!(clients.len(), MAX_CLIENTS - 1);
+
+        clients.insert("new".to_string(), client(now));
+        evict(&mut clients, now);
+        assert_eq!(clients.len(), MAX_CLIENTS - 1);
+        assert!(!clients.contains_key(&(MAX_CLIENTS - 2).to_string()));
+        assert!(clients.contains_key("new"));
+    }
+}
@@ -0,0 +1,39 @@
+//! The tables behind the built-in sets. A file in the assets directory
+//! takes precedence; otherwise the copy built into the binary is used,
+//! when built with the `embedded-assets` feature.
+use std::{borrow::Cow, fs};
+
+use crate::{error::Result, paths, LjpError};
+
+#[cfg(feature = "embedded-assets")]
+static EMBEDDED: include_dir::Dir<'_> = include_dir::include_dir!("$CARGO_MANIFEST_DIR/assets");
+
+/// Returns the contents of the asset `name`, e.g. `hiragana.csv`.
+pub fn read(name: &'static str) -> Result<Cow<'static, str>> {
+    if let Some(path) = paths::assets_dir().map(|dir| dir.join(name)) {
+        match fs::read_to_string(&path) {
+            Ok(data) => return Ok(Cow::Owned(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(source) => {
+                return Err(LjpError::Io {
+                    action: "read asset",
+                    path,
+                    source,
+                })
+            }
+        }
+    }
+    embedded(name)
+        .map(Cow::Borrowed)
+        .ok_or(LjpError::MissingAsset(name))
+}
+
+#[cfg(feature = "embedded-assets")]
+fn embedded(name: &str) -> Option<&'static str> {
+    EMBEDDED.get_file(name)?.contents_utf8()
+}
+
+#[cfg(not(feature = "embedded-assets"))]
+fn embedded(_name: &str) -> Option<&'static str> {
+    None
+}
@@ -0,0 +1,42 @@
+use super::assets;
+use crate::{error::Result, StudyItem, StudySetLoader};
+use rand::RngCore;
+
+#[derive(Debug, Clone)]
+pub struct CompoundsStudySet;
+
+impl StudySetLoader for CompoundsStudySet {
+    fn name(&self) -> String {
+        "compounds".to_string()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        Some("Reading?".to_string())
+    }
+
+    fn load(&self, _rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        let data = assets::read("compounds.csv")?;
+
+        let mut items = Vec::new();
+
+        for line in data.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() == 2 {
+                items.push(StudyItem::new(
+                    parts[0].trim().to_string(),
+                    parts[1].trim().to_string(),
+                ));
+            } else {
+                eprintln!(
+                    "Warning: Skipping malformed line in compounds.csv: {}",
+                    line
+                );
+            }
+        }
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{error::Result, StudyItem, StudySetLoader};
+use rand::prelude::*;
+
+use super::reading;
+
+const DAYS_IN_MONTH: [u32; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+const WEEKDAYS: [(&str, &str); 7] = [
+    ("月曜日", "げつようび"),
+    ("火曜日", "かようび"),
+    ("水曜日", "すいようび"),
+    ("木曜日", "もくようび"),
+    ("金曜日", "きんようび"),
+    ("土曜日", "どようび"),
+    ("日曜日", "にちようび"),
+];
+
+const WEEKS: [(&str, &str); 3] = [
+    ("先週", "せんしゅう"),
+    ("今週", "こんしゅう"),
+    ("来週", "らいしゅう"),
+];
+
+const RELATIVE_MONTHS: [(&str, &str); 3] = [
+    ("先月", "せんげつ"),
+    ("今月", "こんげつ"),
+    ("来月", "らいげつ"),
+];
+
+const RELATIVE_COUNT: usize = 15;
+
+#[derive(Debug, Clone)]
+pub struct DatesStudySet;
+
+impl StudySetLoader for DatesStudySet {
+    fn name(&self) -> String {
+        "dates".to_string()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        Some("Reading?".to_string())
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        let mut items = Vec::new();
+
+        // Every day of the month appears once so the irregular readings
+        // (ついたち, はつか, じゅうよっか, …) are always covered.
+        for day in 1..=31 {
+            let months: Vec<u32> = (1..=12)
+                .filter(|&m| DAYS_IN_MONTH[(m - 1) as usize] >= day)
+                .collect();
+            let month = *months.choose(rng).expect("every day fits some month");
+            items.push(StudyItem::new(
+                format!("{}月{}日", month, day),
+                format!("{}{}", reading::month(month), reading::day(day)),
+            ));
+        }
+
+        for _ in 0..RELATIVE_COUNT {
+            let item = if rng.random_bool(0.5) {
+                let (week, week_reading) = WEEKS.choose(rng).unwrap();
+                let (weekday, weekday_reading) = WEEKDAYS.choose(rng).unwrap();
+                StudyItem::new(
+                    format!("{}の{}", week, weekday),
+                    format!("{}の{}", week_reading, weekday_reading),
+                )
+            } else {
+                let (month, month_reading) = RELATIVE_MONTHS.choose(rng).unwrap();
+                let day = rng.random_range(1..=31);
+                StudyItem::new(
+                    format!("{}の{}日", month, day),
+                    format!("{}の{}", month_reading, reading::day(day)),
+                )
+            };
+            items.push(item);
+        }
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{
+    audio::Bundle,
+    grader::{Grader, Grading},
+    paths,
+    script::{self, Script},
+    LjpError, StudyItem, StudySetLoader,
+};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rand::RngCore;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+/// File extensions recognised as decks, with their field delimiters.
+const EXTENSIONS: [(&str, char); 2] = [("csv", ','), ("tsv", '\t')];
+/// How many items without a clip are named when reporting them.
+const MISSING_SHOWN: usize = 5;
+
+/// A user-provided deck read from a front,back CSV (or TSV) file at
+/// runtime. An optional third field is the item's mnemonic, and a back
+/// may list other accepted answers after it, separated by `|`, e.g.
+/// `今日,きょう|こんにち`.
+///
+/// Fields may be quoted as in `ljp export` output. Blank lines and lines
+/// starting with `#` are skipped, except for `# prompt: …`, `# label: …`,
+/// `# grading: …` and `# tags: …`, which set the deck's answer prompt,
+/// input label, how its answers are compared (e.g. `fuzzy`) and the
+/// comma-separated tags of the items below them. `# note: …` and
+/// `# media: …` give the next item a note and comma-separated media
+/// files. `# audio: …` names an audio bundle, relative to the deck, whose
+/// clips `--mode listen` plays; see [`crate::audio`].
+///
+/// A front with `{{…}}` cloze markers needs no back; see [`crate::cloze`].
+///
+/// A Rhai script beside the deck with the same stem, e.g. `verbs.rhai`
+/// for `verbs.csv`, may generate more items or grade answers; see
+/// [`crate::script`]. A script on its own is a deck too.
+#[derive(Debug, Clone)]
+pub struct DeckStudySet {
+    name: String,
+    items: Vec<StudyItem>,
+    prompt: Option<String>,
+    label: Option<String>,
+    grading: Option<Grading>,
+    script: Option<Script>,
+    /// The audio bundle named in the header, before it is opened.
+    audio: Option<String>,
+    bundle: Option<Bundle>,
+}
+
+impl DeckStudySet {
+    /// Reads the deck at `path`, named after the file stem, with its
+    /// script if it has one.
+    pub fn from_path(path: &Path) -> Result<Self, LjpError> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "deck".to_string());
+        if is_script(path) {
+            return Ok(Self {
+                script: Some(Script::from_path(path)?),
+                ..Self::parse(name, "", ',')
+            });
+        }
+
+        let data = fs::read_to_string(path).map_err(|source| LjpError::Io {
+            action: "read deck",
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let script_path = path.with_extension(script::EXTENSION);
+        let script = match script_path.is_file() {
+            true => Some(Script::from_path(&script_path)?),
+            false => None,
+        };
+        let mut deck = Self {
+            script,
+            ..Self::parse(name, &data, delimiter(path).unwrap_or(','))
+        };
+        if let Some(audio) = &deck.audio {
+            let bundle = path.parent().unwrap_or(Path::new(".")).join(audio);
+            match Bundle::open(&bundle) {
+                Ok(bundle) => deck.bundle = Some(bundle),
+                Err(e) => eprintln!(
+                    "Warning: Ignoring audio bundle in deck {}: {:#}",
+                    deck.name, e
+                ),
+            }
+        }
+        Ok(deck)
+    }
+
+    fn parse(name: String, data: &str, delimiter: char) -> Self {
+        let mut deck = Self {
+            name,
+            items: Vec::new(),
+            prompt: None,
+            label: None,
+            grading: None,
+            script: None,
+            audio: None,
+            bundle: None,
+        };
+        let mut tags: Vec<String> = Vec::new();
+        let mut note: Option<String> = None;
+        let mut media: Vec<String> = Vec::new();
+
+        for (number, line) in data.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.trim().strip_prefix('#') {
+                match comment.split_once(':') {
+                    Some((key, value)) if key.trim() == "prompt" => {
+                        deck.prompt = Some(value.trim().to_string());
+                    }
+                    Some((key, value)) if key.trim() == "label" => {
+                        deck.label = Some(value.trim().to_string());
+                    }
+                    Some((key, value)) if key.trim() == "tags" => tags = split_list(value),
+                    Some((key, value)) if key.trim() == "note" => {
+                        note = Some(value.trim().to_string()).filter(|note| !note.is_empty());
+                    }
+                    Some((key, value)) if key.trim() == "media" => media = split_list(value),
+                    Some((key, value)) if key.trim() == "audio" => {
+                        deck.audio =
+                            Some(value.trim().to_string()).filter(|audio| !audio.is_empty());
+                    }
+                    Some((key, value)) if key.trim() == "grading" => {
+                        match Grading::from_str(value.trim(), true) {
+                            Ok(grading) => deck.grading = Some(grading),
+                            Err(e) => {
+                                eprintln!("Warning: Ignoring grading in deck {}: {}", deck.name, e)
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let fields = parse_record(line, delimiter);
+            let item = StudyItem {
+                tags: tags.clone(),
+                note: note.take(),
+                media: std::mem::take(&mut media),
+                ..StudyItem::default()
+            };
+            if fields.len() == 2 {
+                let (back, alternates) = split_answers(&fields[1]);
+                deck.items.push(StudyItem {
+                    front: fields[0].trim().to_string(),
+                    back,
+                    alternates,
+                    ..item
+                });
+            } else if fields.len() == 3 {
+                let (back, alternates) = split_answers(&fields[1]);
+                let mnemonic = fields[2].trim();
+                deck.items.push(StudyItem {
+                    front: fields[0].trim().to_string(),
+                    back,
+                    alternates,
+                    mnemonic: (!mnemonic.is_empty()).then(|| mnemonic.to_string()),
+                    ..item
+                });
+            } else if fields.len() == 1 && fields[0].contains("{{") {
+                deck.items.push(StudyItem {
+                    front: fields[0].trim().to_string(),
+                    ..item
+                });
+            } else {
+                let malformed = LjpError::MalformedDeck {
+                    deck: deck.name.clone(),
+                    line: number + 1,
+                    text: line.to_string(),
+                };
+                eprintln!("Warning: {} (skipped)", malformed);
+            }
+        }
+
+        deck
+    }
+}
+
+/// Splits a comma-separated header value, dropping empty entries.
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// Splits a back field into the back and its alternates.
+fn split_answers(field: &str) -> (String, Vec<String>) {
+    let mut answers = field
+        .split('|')
+        .map(str::trim)
+        .filter(|answer| !answer.is_empty())
+        .map(String::from);
+    let back = answers.next().unwrap_or_default();
+    (back, answers.collect())
+}
+
+/// Returns the delimiter for `path` if its extension marks it as a deck.
+fn delimiter(path: &Path) -> Option<char> {
+    let extension = path.extension()?.to_str()?;
+    EXTENSIONS
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+        .map(|&(_, delimiter)| delimiter)
+}
+
+fn is_script(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(script::EXTENSION))
+}
+
+/// Returns the decks in the user's deck directory, sorted by name. A
+/// script beside a deck of the same name is part of that deck.
+pub fn discover() -> Vec<(String, PathBuf)> {
+    let Some(entries) = paths::decks_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut decks: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && (delimiter(path).is_some() || is_script(path)))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some((name, path))
+        })
+        .collect();
+    // Scripts sort after a deck of the same name, so only lone ones stay.
+    decks.sort_by_key(|(name, path)| (name.clone(), is_script(path)));
+    decks.dedup_by(|later, earlier| later.0 == earlier.0);
+    decks
+}
+
+/// Copies the deck at `path` into the deck directory, returning where it
+/// was written. Refuses to replace an existing deck.
+pub fn import(path: &Path) -> Result<PathBuf> {
+    if delimiter(path).is_none() && !is_script(path) {
+        anyhow::bail!("{} is not a .csv, .tsv or .rhai file", path.display());
+    }
+    let dir = paths::decks_dir().context("Could not determine the data directory")?;
+    let file_name = path.file_name().context("Deck path has no file name")?;
+    let target = dir.join(file_name);
+    if target.exists() {
+        anyhow::bail!("A deck already exists at {}", target.display());
+    }
+
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    fs::copy(path, &target)
+        .with_context(|| format!("Failed to copy {} to {}", path.display(), target.display()))?;
+    Ok(target)
+}
+
+/// Writes `csv` to a new deck called `name` in the deck directory,
+/// returning where it was written. Refuses to replace an existing deck.
+pub fn create(name: &str, csv: &str) -> Result<PathBuf> {
+    let dir = paths::decks_dir().context("Could not determine the data directory")?;
+    let target = dir.join(format!("{}.csv", name));
+    if target.exists() {
+        anyhow::bail!("A deck already exists at {}", target.display());
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    fs::write(&target, csv).with_context(|| format!("Failed to write {}", target.display()))?;
+    Ok(target)
+}
+
+/// Quotes a CSV field if it contains a comma, quote or newline.
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits a line into fields, unquoting `"…"` fields and `""` escapes.
+fn parse_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (c, false) if c == delimiter => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+impl StudySetLoader for DeckStudySet {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        self.prompt.clone()
+    }
+
+    fn input_label(&self) -> Option<String> {
+        self.label.clone()
+    }
+
+    fn grading(&self) -> Option<Grading> {
+        self.grading
+    }
+
+    fn grader(&self) -> Option<Rc<dyn Grader>> {
+        let script = self.script.as_ref().filter(|script| script.grades())?;
+        Some(Rc::new(script.clone()))
+    }
+
+    fn load(&self, _rng: &mut dyn RngCore) -> Result<Vec<StudyItem>, LjpError> {
+        let mut items = self.items.clone();
+        if let Some(script) = &self.script {
+            items.extend(script.generate()?);
+        }
+        if let Some(bundle) = &self.bundle {
+            let missing: Vec<&str> = bundle
+                .attach(&mut items)
+                .into_iter()
+                .map(|item| item.front.as_str())
+                .collect();
+            if !missing.is_empty() {
+                eprintln!(
+                    "Warning: No clips in {} for {} items of deck {}: {}{}",
+                    bundle.path().display(),
+                    missing.len(),
+                    self.name,
+                    missing[..missing.len().min(MISSING_SHOWN)].join(", "),
+                    if missing.len() > MISSING_SHOWN {
+                        ", …"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &str) -> Vec<StudyItem> {
+        DeckStudySet::parse("test".to_string(), data, ',').items
+    }
+
+    #[test]
+    fn reads_fronts_backs_and_mnemonics() {
+        let items = parse("犬,いぬ\n\n\"猫, cat\",ねこ,\"says \"\"nya\"\"\"\n");
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            (items[0].front.as_str(), items[0].back.as_str()),
+            ("犬", "いぬ")
+        );
+        assert_eq!(items[1].front, "猫, cat");
+        assert_eq!(items[1].mnemonic.as_deref(), Some("says \"nya\""));
+    }
+
+    #[test]
+    fn reads_alternates_after_the_back() {
+        let items = parse("今日,きょう | こんにち|\n");
+        assert_eq!(items[0].back, "きょう");
+        assert_eq!(items[0].alternates, ["こんにち"]);
+        assert_eq!(
+            items[0].answers().collect::<Vec<_>>(),
+            ["きょう", "こんにち"]
+        );
+    }
+
+    #[test]
+    fn tags_apply_below_and_notes_to_the_next_item() {
+        let items = parse(
+            "# tags: n5, food\n# note: Counted with 杯\n# media: tea.png, tea.mp3\nお茶,おちゃ\n水,みず\n# tags:\n肉,にく\n",
+        );
+        assert_eq!(items[0].tags, ["n5", "food"]);
+        assert_eq!(items[0].note.as_deref(), Some("Counted with 杯"));
+        assert_eq!(items[0].media, ["tea.png", "tea.mp3"]);
+        assert_eq!(items[1].tags, ["n5", "food"]);
+        assert_eq!(items[1].note, None);
+        assert!(items[1].media.is_empty());
+        assert!(items[2].tags.is_empty());
+    }
+
+    #[test]
+    fn reads_headers_and_skips_malformed_lines() {
+        let deck = DeckStudySet::parse(
+            "test".to_string(),
+            "# prompt: Reading?\n# grading: fuzzy\n# a comment\nonly a front\n{{犬}}が好き\na,b,c,d\n",
+            ',',
+        );
+        assert_eq!(deck.prompt.as_deref(), Some("Reading?"));
+        assert_eq!(deck.grading, Some(Grading::Fuzzy));
+        assert_eq!(deck.items.len(), 1);
+        assert_eq!(deck.items[0].front, "{{犬}}が好き");
+    }
+
+    #[test]
+    fn items_get_clips_from_the_decks_audio_bundle() {
+        let dir = std::env::temp_dir().join(format!("ljp-deck-audio-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("clips")).unwrap();
+        fs::write(dir.join("clips/犬.mp3"), "").unwrap();
+        let path = dir.join("animals.csv");
+        fs::write(&path, "# audio: clips\n犬,いぬ\n猫,ねこ\n").unwrap();
+
+        let deck = DeckStudySet::from_path(&path).unwrap();
+        let items = deck.load(&mut rand::rng()).unwrap();
+        assert_eq!(
+            items[0].media,
+            [dir.join("clips/犬.mp3").display().to_string()]
+        );
+        assert!(items[1].media.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+use super::{hiragana::HiraganaStudySet, kana_difficulty, katakana::KatakanaStudySet};
+use crate::{error::Result, StudyItem, StudySetLoader};
+use rand::RngCore;
+
+/// Hiragana and katakana shown side by side (あ / ア) with their shared
+/// romaji as the answer, built from the two kana sets.
+#[derive(Debug, Clone)]
+pub struct DualScriptStudySet;
+
+impl StudySetLoader for DualScriptStudySet {
+    fn name(&self) -> String {
+        "dual".to_string()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        Some("Romaji?".to_string())
+    }
+
+    fn reverse_prompt(&self) -> Option<String> {
+        Some("Kana?".to_string())
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        let mut katakana = KatakanaStudySet.load(rng)?;
+
+        Ok(HiraganaStudySet
+            .load(rng)?
+            .into_iter()
+            .filter_map(|hiragana| {
+                let position = katakana.iter().position(|k| k.back == hiragana.back)?;
+                let katakana = katakana.remove(position);
+                Some(StudyItem::new(
+                    format!("{} / {}", hiragana.front, katakana.front),
+                    hiragana.back,
+                ))
+            })
+            .collect())
+    }
+
+    fn difficulty(&self, item: &StudyItem) -> u32 {
+        item.front
+            .split(" / ")
+            .map(|kana| kana_difficulty(&StudyItem::new(kana.to_string(), item.back.clone())))
+            .max()
+            .unwrap_or(1)
+    }
+}
@@ -1,7 +1,6 @@
-use crate::{StudyItem, StudySetLoader};
-use include_dir::{include_dir, Dir, File};
-
-static ASSETS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/assets");
+use super::{assets, kana_difficulty};
+use crate::{error::Result, StudyItem, StudySetLoader};
+use rand::RngCore;
 
 #[derive(Debug, Clone)]
 pub struct HiraganaStudySet;
@@ -11,14 +10,16 @@ impl StudySetLoader for HiraganaStudySet {
         "hiragana".to_string()
     }
 
-    fn load(&self) -> Vec<StudyItem> {
-        let hiragana_file: &File = ASSETS_DIR
-            .get_file("hiragana.csv")
-            .expect("hiragana.csv not found in assets directory");
+    fn prompt(&self) -> Option<String> {
+        Some("Romaji?".to_string())
+    }
+
+    fn reverse_prompt(&self) -> Option<String> {
+        Some("Kana?".to_string())
+    }
 
-        let data = hiragana_file
-            .contents_utf8()
-            .expect("Failed to read hiragana.csv as UTF-8");
+    fn load(&self, _rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        let data = assets::read("hiragana.csv")?;
 
         let mut items = Vec::new();
 
@@ -28,15 +29,19 @@ impl StudySetLoader for HiraganaStudySet {
             }
             let parts: Vec<&str> = line.split(',').collect();
             if parts.len() == 2 {
-                items.push(StudyItem {
-                    front: parts[1].trim().to_string(),
-                    back: parts[0].trim().to_string(),
-                });
+                items.push(StudyItem::new(
+                    parts[1].trim().to_string(),
+                    parts[0].trim().to_string(),
+                ));
             } else {
                 eprintln!("Warning: Skipping malformed line in hiragana.csv: {}", line);
             }
         }
 
-        items
+        Ok(items)
+    }
+
+    fn difficulty(&self, item: &StudyItem) -> u32 {
+        kana_difficulty(item)
     }
 }
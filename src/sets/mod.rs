@@ -1,2 +1,51 @@
+pub mod assets;
+pub mod compounds;
+pub mod dates;
+pub mod deck;
+pub mod dual;
 pub mod hiragana;
 pub mod katakana;
+pub mod prices;
+mod reading;
+pub mod registry;
+pub mod reversed;
+pub mod strings;
+pub mod times;
+
+pub use registry::SetRegistry;
+
+use crate::StudyItem;
+
+const DIFFICULTY: &str = include_str!("../../assets/difficulty.csv");
+const SIMILAR: &str = include_str!("../../assets/similar.csv");
+
+/// Returns the prior difficulty of a kana item — how much more often a
+/// beginner should see it than an easy one — from the bundled
+/// `difficulty.csv`. Items not listed there have difficulty 1.
+pub fn kana_difficulty(item: &StudyItem) -> u32 {
+    DIFFICULTY
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .find(|(kana, _)| *kana == item.front || *kana == item.back)
+        .and_then(|(_, weight)| weight.trim().parse().ok())
+        .unwrap_or(1)
+}
+
+/// Parses groups of easily confused fronts, one comma-separated group per
+/// line.
+pub fn parse_similarity_groups(data: &str) -> Vec<Vec<String>> {
+    data.lines()
+        .map(|line| {
+            line.split(',')
+                .map(|front| front.trim().to_string())
+                .filter(|front| !front.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Returns the bundled groups of visually similar kana.
+pub fn similarity_groups() -> Vec<Vec<String>> {
+    parse_similarity_groups(SIMILAR)
+}
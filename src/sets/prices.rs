@@ -0,0 +1,56 @@
+use crate::{error::Result, StudyItem, StudySetLoader};
+use rand::prelude::*;
+
+use super::reading;
+
+const COUNT: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct PricesStudySet;
+
+/// Formats `n` with thousands separators, e.g. 3800 → 3,800.
+fn with_commas(n: u64) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(c);
+    }
+    formatted
+}
+
+/// Picks a price shaped like a real one: one to three significant digits
+/// scaled anywhere from single yen up to tens of millions.
+fn random_price(rng: &mut dyn RngCore) -> u64 {
+    let significant = match rng.random_range(0..3) {
+        0 => rng.random_range(1..10),
+        1 => rng.random_range(10..100),
+        _ => rng.random_range(100..1000),
+    };
+    let scale = 10u64.pow(rng.random_range(0..6));
+    significant * scale
+}
+
+impl StudySetLoader for PricesStudySet {
+    fn name(&self) -> String {
+        "prices".to_string()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        Some("Reading?".to_string())
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        Ok((0..COUNT)
+            .map(|_| {
+                let price = random_price(rng);
+                StudyItem::new(
+                    format!("¥{}", with_commas(price)),
+                    format!("{}えん", reading::number_yo(price)),
+                )
+            })
+            .collect())
+    }
+}
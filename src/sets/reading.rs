@@ -0,0 +1,202 @@
+const DIGITS: [&str; 10] = [
+    "",
+    "いち",
+    "に",
+    "さん",
+    "よん",
+    "ご",
+    "ろく",
+    "なな",
+    "はち",
+    "きゅう",
+];
+
+const HUNDREDS: [&str; 10] = [
+    "",
+    "ひゃく",
+    "にひゃく",
+    "さんびゃく",
+    "よんひゃく",
+    "ごひゃく",
+    "ろっぴゃく",
+    "ななひゃく",
+    "はっぴゃく",
+    "きゅうひゃく",
+];
+
+const THOUSANDS: [&str; 10] = [
+    "",
+    "せん",
+    "にせん",
+    "さんぜん",
+    "よんせん",
+    "ごせん",
+    "ろくせん",
+    "ななせん",
+    "はっせん",
+    "きゅうせん",
+];
+
+const MONTHS: [&str; 12] = [
+    "いちがつ",
+    "にがつ",
+    "さんがつ",
+    "しがつ",
+    "ごがつ",
+    "ろくがつ",
+    "しちがつ",
+    "はちがつ",
+    "くがつ",
+    "じゅうがつ",
+    "じゅういちがつ",
+    "じゅうにがつ",
+];
+
+const DAYS: [&str; 31] = [
+    "ついたち",
+    "ふつか",
+    "みっか",
+    "よっか",
+    "いつか",
+    "むいか",
+    "なのか",
+    "ようか",
+    "ここのか",
+    "とおか",
+    "じゅういちにち",
+    "じゅうににち",
+    "じゅうさんにち",
+    "じゅうよっか",
+    "じゅうごにち",
+    "じゅうろくにち",
+    "じゅうしちにち",
+    "じゅうはちにち",
+    "じゅうくにち",
+    "はつか",
+    "にじゅういちにち",
+    "にじゅうににち",
+    "にじゅうさんにち",
+    "にじゅうよっか",
+    "にじゅうごにち",
+    "にじゅうろくにち",
+    "にじゅうしちにち",
+    "にじゅうはちにち",
+    "にじゅうくにち",
+    "さんじゅうにち",
+    "さんじゅういちにち",
+];
+
+const HOURS: [&str; 12] = [
+    "いちじ",
+    "にじ",
+    "さんじ",
+    "よじ",
+    "ごじ",
+    "ろくじ",
+    "しちじ",
+    "はちじ",
+    "くじ",
+    "じゅうじ",
+    "じゅういちじ",
+    "じゅうにじ",
+];
+
+const MINUTES: [&str; 10] = [
+    "",
+    "いっぷん",
+    "にふん",
+    "さんぷん",
+    "よんぷん",
+    "ごふん",
+    "ろっぷん",
+    "ななふん",
+    "はっぷん",
+    "きゅうふん",
+];
+
+/// Reads a number below 10,000 (one 万 group).
+fn group(n: u64) -> String {
+    let mut reading = String::new();
+    reading.push_str(THOUSANDS[(n / 1000 % 10) as usize]);
+    reading.push_str(HUNDREDS[(n / 100 % 10) as usize]);
+    match n / 10 % 10 {
+        0 => {}
+        1 => reading.push_str("じゅう"),
+        tens => {
+            reading.push_str(DIGITS[tens as usize]);
+            reading.push_str("じゅう");
+        }
+    }
+    reading.push_str(DIGITS[(n % 10) as usize]);
+    reading
+}
+
+/// Reads a group that is followed by a large unit (万, 億), where a
+/// leading 1 is always spoken and 1000 becomes いっせん.
+fn unit_group(n: u64) -> String {
+    match n {
+        1 => "いち".to_string(),
+        _ if n / 1000 == 1 => format!("いっ{}", group(n)),
+        _ => group(n),
+    }
+}
+
+/// Returns the hiragana reading of `n`, e.g. 3800 → さんぜんはっぴゃく.
+pub fn number(n: u64) -> String {
+    if n == 0 {
+        return "ゼロ".to_string();
+    }
+
+    let mut reading = String::new();
+    let oku = n / 100_000_000;
+    let man = n / 10_000 % 10_000;
+    let rest = n % 10_000;
+
+    if oku > 0 {
+        reading.push_str(&number(oku));
+        reading.push_str("おく");
+    }
+    if man > 0 {
+        reading.push_str(&unit_group(man));
+        reading.push_str("まん");
+    }
+    reading.push_str(&group(rest));
+    reading
+}
+
+/// Returns the reading of `n` with a counter whose 4 is read よ (円, 時).
+pub fn number_yo(n: u64) -> String {
+    let reading = number(n);
+    match reading.strip_suffix("よん") {
+        Some(stem) => format!("{}よ", stem),
+        None => reading,
+    }
+}
+
+/// Returns the reading of month `m` (1–12) followed by がつ.
+pub fn month(m: u32) -> &'static str {
+    MONTHS[(m - 1) as usize]
+}
+
+/// Returns the reading of day-of-month `d` (1–31), including the
+/// irregular native readings (ついたち, はつか, …).
+pub fn day(d: u32) -> &'static str {
+    DAYS[(d - 1) as usize]
+}
+
+/// Returns the reading of clock hour `h` (1–12) followed by じ.
+pub fn hour(h: u32) -> &'static str {
+    HOURS[(h - 1) as usize]
+}
+
+/// Returns the reading of `m` minutes (1–59), alternating ふん/ぷん.
+pub fn minutes(m: u32) -> String {
+    let tens = (m / 10) as usize;
+    let ones = (m % 10) as usize;
+    let tens_digit = if tens == 1 { "" } else { DIGITS[tens] };
+    match (tens, ones) {
+        (_, 0) => format!("{}じゅっぷん", tens_digit),
+        (0, _) => MINUTES[ones].to_string(),
+        _ => format!("{}じゅう{}", tens_digit, MINUTES[ones]),
+    }
+}
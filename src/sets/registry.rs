@@ -0,0 +1,121 @@
+use super::{
+    compounds::CompoundsStudySet, dates::DatesStudySet, deck::DeckStudySet,
+    dual::DualScriptStudySet, hiragana::HiraganaStudySet, katakana::KatakanaStudySet,
+    prices::PricesStudySet, strings::StringsStudySet, times::TimesStudySet,
+};
+use crate::{error::Result, plugin, plugin::PluginStudySet, LjpError, StudySetLoader};
+
+/// Makes a fresh loader for a registered set.
+pub type Factory = Box<dyn Fn() -> Result<Box<dyn StudySetLoader>>>;
+
+struct SetEntry {
+    name: String,
+    description: String,
+    factory: Factory,
+}
+
+/// The sets that can be studied by name. Built-in sets, the user's decks
+/// and WebAssembly plugins are registered by `with_builtins`; embedders
+/// add their own with `register`, and `ljp list` shows whatever is
+/// registered.
+#[derive(Default)]
+pub struct SetRegistry {
+    sets: Vec<SetEntry>,
+}
+
+impl SetRegistry {
+    /// Returns a registry holding the built-in sets followed by the decks
+    /// and plugins in the user's data directory.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::default();
+
+        registry.register("hiragana", "Hiragana and their romaji", || {
+            Ok(Box::new(HiraganaStudySet))
+        });
+        registry.register("katakana", "Katakana and their romaji", || {
+            Ok(Box::new(KatakanaStudySet))
+        });
+        registry.register(
+            "dual",
+            "Hiragana and katakana side by side, with their romaji",
+            || Ok(Box::new(DualScriptStudySet)),
+        );
+        registry.register(
+            "strings",
+            "Random strings of hiragana, read as romaji",
+            || Ok(Box::new(StringsStudySet)),
+        );
+        registry.register("compounds", "Kanji compounds and their readings", || {
+            Ok(Box::new(CompoundsStudySet))
+        });
+        registry.register("dates", "Random dates and their readings", || {
+            Ok(Box::new(DatesStudySet))
+        });
+        registry.register("prices", "Random prices in yen and their readings", || {
+            Ok(Box::new(PricesStudySet))
+        });
+        registry.register("times", "Random clock times and their readings", || {
+            Ok(Box::new(TimesStudySet))
+        });
+
+        for (name, path) in super::deck::discover() {
+            let description = format!("Deck {}", path.display());
+            registry.register(&name, &description, move || {
+                Ok(Box::new(DeckStudySet::from_path(&path)?))
+            });
+        }
+
+        for path in plugin::discover() {
+            match PluginStudySet::cached(&path) {
+                Ok(set) => {
+                    let description = format!("Plugin {}", path.display());
+                    registry.register(&set.name(), &description, move || Ok(Box::new(set.clone())));
+                }
+                Err(err) => eprintln!("Warning: {}", err),
+            }
+        }
+
+        registry
+    }
+
+    /// Adds a set. A name that is already registered keeps its first set,
+    /// so built-in sets shadow decks of the same name.
+    pub fn register(
+        &mut self,
+        name: &str,
+        description: &str,
+        factory: impl Fn() -> Result<Box<dyn StudySetLoader>> + 'static,
+    ) {
+        if self.sets.iter().any(|entry| entry.name == name) {
+            return;
+        }
+        self.sets.push(SetEntry {
+            name: name.to_string(),
+            description: description.to_string(),
+            factory: Box::new(factory),
+        });
+    }
+
+    /// Loads the set registered as `name`.
+    pub fn get(&self, name: &str) -> Result<Box<dyn StudySetLoader>> {
+        let entry = self
+            .sets
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| LjpError::UnknownSet(name.to_string()))?;
+        (entry.factory)()
+    }
+
+    /// The registered names, in the order they were registered.
+    pub fn names(&self) -> Vec<String> {
+        self.sets.iter().map(|entry| entry.name.clone()).collect()
+    }
+
+    /// `(name, description)` for every registered set.
+    pub fn descriptions(&self) -> Vec<(String, String)> {
+        self.sets
+            .iter()
+            .map(|entry| (entry.name.clone(), entry.description.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    error::Result,
+    grader::{Grader, Grading},
+    StudyItem, StudySetLoader,
+};
+use rand::RngCore;
+use std::rc::Rc;
+
+/// Wraps a set so its items are asked the other way round, e.g. romaji to
+/// kana.
+pub struct Reversed(pub Box<dyn StudySetLoader>);
+
+/// Turns `item` round. Its alternates were other backs, so they do not
+/// carry over, and its id is left to be made from the new front.
+fn swap(item: &StudyItem) -> StudyItem {
+    StudyItem {
+        id: String::new(),
+        front: item.back.clone(),
+        back: item.front.clone(),
+        alternates: Vec::new(),
+        ..item.clone()
+    }
+}
+
+impl StudySetLoader for Reversed {
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        Ok(self.0.load(rng)?.iter().map(swap).collect())
+    }
+
+    fn difficulty(&self, item: &StudyItem) -> u32 {
+        self.0.difficulty(&swap(item))
+    }
+
+    fn prompt(&self) -> Option<String> {
+        self.0.reverse_prompt()
+    }
+
+    fn input_label(&self) -> Option<String> {
+        None
+    }
+
+    fn grading(&self) -> Option<Grading> {
+        self.0.grading()
+    }
+
+    fn grader(&self) -> Option<Rc<dyn Grader>> {
+        self.0.grader()
+    }
+}
+
+/// Wraps a set so each item is asked both ways, as two separate cards.
+pub struct BothWays(pub Box<dyn StudySetLoader>);
+
+impl StudySetLoader for BothWays {
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        let items = self.0.load(rng)?;
+        let prompt = self.0.reverse_prompt();
+        let reversed: Vec<StudyItem> = items
+            .iter()
+            .map(|item| StudyItem {
+                prompt: prompt.clone(),
+                ..swap(item)
+            })
+            .collect();
+        Ok(items.into_iter().chain(reversed).collect())
+    }
+
+    /// Items of either direction may be passed, so this takes the larger
+    /// of the two.
+    fn difficulty(&self, item: &StudyItem) -> u32 {
+        self.0.difficulty(item).max(self.0.difficulty(&swap(item)))
+    }
+
+    fn prompt(&self) -> Option<String> {
+        self.0.prompt()
+    }
+
+    fn input_label(&self) -> Option<String> {
+        self.0.input_label()
+    }
+
+    fn grading(&self) -> Option<Grading> {
+        self.0.grading()
+    }
+
+    fn grader(&self) -> Option<Rc<dyn Grader>> {
+        self.0.grader()
+    }
+}
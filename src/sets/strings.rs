@@ -0,0 +1,120 @@
+use super::hiragana::HiraganaStudySet;
+use crate::{error::Result, StudyItem, StudySetLoader};
+use rand::prelude::*;
+
+const COUNT: usize = 40;
+const MIN_LENGTH: usize = 2;
+const MAX_LENGTH: usize = 5;
+
+/// Kana that don't start ordinary syllables in words: ん and っ are added
+/// by their own rules, and を/ぢ/づ are too rare to drill in isolation.
+const EXCLUDED: [&str; 4] = ["ん", "を", "ぢ", "づ"];
+
+/// Random hiragana strings with their romaji, following basic
+/// phonotactics: ん never starts a word, っ only doubles a following
+/// k/s/t/p/ch consonant, and long vowels repeat the previous vowel.
+#[derive(Debug, Clone)]
+pub struct StringsStudySet;
+
+#[derive(PartialEq)]
+enum Last {
+    Start,
+    /// An ordinary syllable ending in the given vowel.
+    Syllable(char),
+    LongVowel,
+    N,
+}
+
+fn long_vowel(vowel: char) -> (&'static str, &'static str) {
+    match vowel {
+        'a' => ("あ", "a"),
+        'i' | 'e' => ("い", "i"),
+        _ => ("う", "u"),
+    }
+}
+
+fn generate(syllables: &[StudyItem], rng: &mut dyn RngCore) -> StudyItem {
+    let length = rng.random_range(MIN_LENGTH..=MAX_LENGTH);
+    let mut kana = String::new();
+    let mut romaji = String::new();
+    let mut last = Last::Start;
+
+    while kana.chars().count() < length {
+        let remaining = length - kana.chars().count();
+        let roll: f64 = rng.random();
+
+        match last {
+            Last::Syllable(_) | Last::LongVowel if roll < 0.15 => {
+                kana.push('ん');
+                romaji.push('n');
+                last = Last::N;
+                continue;
+            }
+            Last::Syllable(vowel) if roll < 0.3 => {
+                let (k, r) = long_vowel(vowel);
+                kana.push_str(k);
+                romaji.push_str(r);
+                last = Last::LongVowel;
+                continue;
+            }
+            _ => {}
+        }
+
+        let sokuon = matches!(last, Last::Syllable(_)) && roll < 0.45 && remaining >= 2;
+        let budget = if sokuon { remaining - 1 } else { remaining };
+        let candidates: Vec<&StudyItem> = syllables
+            .iter()
+            .filter(|s| s.front.chars().count() <= budget)
+            .filter(|s| {
+                !sokuon
+                    || ["k", "s", "t", "p", "c"]
+                        .iter()
+                        .any(|c| s.back.starts_with(c))
+            })
+            .collect();
+        let Some(syllable) = candidates.choose(rng) else {
+            break;
+        };
+
+        if sokuon {
+            kana.push('っ');
+            if syllable.back.starts_with("ch") {
+                romaji.push('t');
+            } else {
+                romaji.push_str(&syllable.back[..1]);
+            }
+        } else if last == Last::N && syllable.back.starts_with(['a', 'i', 'u', 'e', 'o', 'y']) {
+            romaji.push('\'');
+        }
+
+        kana.push_str(&syllable.front);
+        romaji.push_str(&syllable.back);
+        last = Last::Syllable(syllable.back.chars().last().unwrap_or('a'));
+    }
+
+    StudyItem::new(kana, romaji)
+}
+
+impl StudySetLoader for StringsStudySet {
+    fn name(&self) -> String {
+        "strings".to_string()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        Some("Romaji?".to_string())
+    }
+
+    fn reverse_prompt(&self) -> Option<String> {
+        Some("Kana?".to_string())
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        let syllables: Vec<StudyItem> = HiraganaStudySet
+            .load(rng)?
+            .into_iter()
+            .filter(|item| !EXCLUDED.contains(&item.front.as_str()))
+            .collect();
+
+        Ok((0..COUNT).map(|_| generate(&syllables, rng)).collect())
+    }
+}
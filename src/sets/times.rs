@@ -0,0 +1,53 @@
+use crate::{error::Result, StudyItem, StudySetLoader};
+use rand::prelude::*;
+
+use super::reading;
+
+const COUNT: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct TimesStudySet;
+
+impl StudySetLoader for TimesStudySet {
+    fn name(&self) -> String {
+        "times".to_string()
+    }
+
+    fn prompt(&self) -> Option<String> {
+        Some("Reading?".to_string())
+    }
+
+    fn load(&self, rng: &mut dyn RngCore) -> Result<Vec<StudyItem>> {
+        let mut items = Vec::new();
+
+        // Every hour appears once so よじ, しちじ and くじ are always covered.
+        for hour in 1..=12 {
+            let minute = rng.random_range(1..60);
+            items.push(StudyItem::new(
+                format!("{}:{:02}", hour, minute),
+                format!("{}{}", reading::hour(hour), reading::minutes(minute)),
+            ));
+        }
+
+        while items.len() < COUNT {
+            let hour = rng.random_range(1..=12);
+            let item = match rng.random_range(0..3) {
+                0 => StudyItem::new(
+                    format!("{}時半", hour),
+                    format!("{}はん", reading::hour(hour)),
+                ),
+                1 => {
+                    let minute = rng.random_range(1..60);
+                    StudyItem::new(
+                        format!("{}時{}分", hour, minute),
+                        format!("{}{}", reading::hour(hour), reading::minutes(minute)),
+                    )
+                }
+                _ => StudyItem::new(format!("{}:00", hour), reading::hour(hour).to_string()),
+            };
+            items.push(item);
+        }
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use std::{env, ffi::OsStr, path::Path, process::Command};
+
+use crate::StudyItem;
+
+/// Speaks prompts aloud.
+pub trait Speaker {
+    fn speak(&mut self, text: &str) -> Result<()>;
+
+    /// Speaks `item`'s front; speakers with recordings of items override
+    /// this to play them.
+    fn speak_item(&mut self, item: &StudyItem) -> Result<()> {
+        self.speak(&item.front)
+    }
+}
+
+/// Speaks by running a text-to-speech program with the text as its last
+/// argument, e.g. `say -v Kyoko` or `espeak-ng -v ja`.
+#[derive(Debug, Clone)]
+pub struct CommandSpeaker {
+    program: String,
+    args: Vec<String>,
+}
+
+/// Programs tried, in order, when no command is given.
+const DEFAULT_COMMANDS: [&str; 2] = ["say -v Kyoko", "espeak-ng -v ja"];
+/// Audio players tried, in order, when none is given.
+const DEFAULT_PLAYERS: [&str; 4] = [
+    "afplay",
+    "mpv --really-quiet --no-video",
+    "ffplay -nodisp -autoexit -loglevel quiet",
+    "mpg123 -q",
+];
+
+impl CommandSpeaker {
+    /// Parses a whitespace-separated command line. Returns `None` if it is
+    /// empty.
+    pub fn new(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        Some(Self {
+            program: words.next()?,
+            args: words.collect(),
+        })
+    }
+
+    /// The first of the usual text-to-speech programs that is installed.
+    pub fn detect() -> Option<Self> {
+        Self::first_installed(&DEFAULT_COMMANDS)
+    }
+
+    fn first_installed(commands: &[&str]) -> Option<Self> {
+        commands
+            .iter()
+            .filter_map(|command| Self::new(command))
+            .find(|speaker| on_path(&speaker.program))
+    }
+
+    fn run(&self, arg: impl AsRef<OsStr>) -> Result<()> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(arg)
+            .status()
+            .with_context(|| format!("Failed to run {}", self.program))?;
+        if !status.success() {
+            anyhow::bail!("{} exited with {}", self.program, status);
+        }
+        Ok(())
+    }
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file())
+    })
+}
+
+impl Speaker for CommandSpeaker {
+    fn speak(&mut self, text: &str) -> Result<()> {
+        self.run(text)
+    }
+}
+
+/// Plays audio files by running a program with the file as its last
+/// argument, e.g. `afplay` or `mpv --really-quiet`.
+#[derive(Debug, Clone)]
+pub struct Player(CommandSpeaker);
+
+impl Player {
+    /// Parses a whitespace-separated command line. Returns `None` if it is
+    /// empty.
+    pub fn new(command: &str) -> Option<Self> {
+        CommandSpeaker::new(command).map(Self)
+    }
+
+    /// The first of the usual audio players that is installed.
+    pub fn detect() -> Option<Self> {
+        CommandSpeaker::first_installed(&DEFAULT_PLAYERS).map(Self)
+    }
+
+    pub fn play(&mut self, path: &Path) -> Result<()> {
+        self.0.run(path)
+    }
+}
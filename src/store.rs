@@ -0,0 +1,662 @@
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    backup,
+    commands::{self, CommandRegistry},
+    engine::Render,
+    events::{Answer, Answers, Event, Listener},
+    fsrs::Memory,
+    profiles,
+    schedule::ItemSchedule,
+    StudyItem, StudySession,
+};
+
+/// Schema changes in order; the database's `user_version` counts how many
+/// have been applied.
+const MIGRATIONS: [&str; 4] = [
+    "CREATE TABLE IF NOT EXISTS items (
+        front TEXT NOT NULL,
+        back TEXT NOT NULL,
+        set_name TEXT NOT NULL DEFAULT '',
+        reviews INTEGER NOT NULL DEFAULT 0,
+        lapses INTEGER NOT NULL DEFAULT 0,
+        weight INTEGER,
+        last_review TEXT,
+        PRIMARY KEY (front, back)
+    );",
+    "ALTER TABLE items ADD COLUMN response_ms INTEGER NOT NULL DEFAULT 0;",
+    "CREATE TABLE IF NOT EXISTS days (
+        day TEXT PRIMARY KEY,
+        reviews INTEGER NOT NULL DEFAULT 0
+    );",
+    "ALTER TABLE items ADD COLUMN stability REAL;
+     ALTER TABLE items ADD COLUMN difficulty REAL;
+     ALTER TABLE items ADD COLUMN memory_review INTEGER;
+     ALTER TABLE items ADD COLUMN leitner_box INTEGER;",
+];
+
+/// Items reviewed at least this often with at least `MASTERED_ACCURACY`
+/// count as mastered.
+const MASTERED_REVIEWS: u32 = 3;
+const MASTERED_ACCURACY: f64 = 0.9;
+/// Reviewed items answered correctly less often than this are struggling.
+const STRUGGLING_ACCURACY: f64 = 0.6;
+
+/// Long-term totals for one set.
+#[derive(Debug, Clone, Default)]
+pub struct SetStats {
+    pub set: String,
+    /// Items reviewed at least once.
+    pub items: u32,
+    pub reviews: u32,
+    pub lapses: u32,
+    pub mastered: u32,
+    pub struggling: u32,
+    /// Total time spent answering, in milliseconds.
+    pub response_ms: u64,
+}
+
+impl SetStats {
+    pub fn accuracy(&self) -> f64 {
+        if self.reviews == 0 {
+            0.0
+        } else {
+            f64::from(self.reviews - self.lapses) / f64::from(self.reviews)
+        }
+    }
+
+    /// Mean seconds per answer.
+    pub fn average_response(&self) -> f64 {
+        if self.reviews == 0 {
+            0.0
+        } else {
+            self.response_ms as f64 / 1000.0 / f64::from(self.reviews)
+        }
+    }
+}
+
+/// Long-term totals for one item.
+#[derive(Debug, Clone, Default)]
+pub struct ItemStats {
+    pub reviews: u32,
+    pub lapses: u32,
+    /// When it was last answered, in UTC.
+    pub last_review: Option<NaiveDateTime>,
+}
+
+impl ItemStats {
+    pub fn accuracy(&self) -> f64 {
+        if self.reviews == 0 {
+            0.0
+        } else {
+            f64::from(self.reviews - self.lapses) / f64::from(self.reviews)
+        }
+    }
+}
+
+/// An item's progress from reviews done elsewhere, e.g. in Anki.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedProgress {
+    pub reviews: u32,
+    pub lapses: u32,
+    /// When it was last reviewed, in UTC.
+    pub last_review: Option<NaiveDateTime>,
+    pub memory: Option<Memory>,
+    pub leitner_box: u8,
+}
+
+/// Returns the default location of the progress database for `profile`.
+pub fn default_path(profile: Option<&str>) -> Result<PathBuf> {
+    Ok(profiles::state_dir(profile)?.join("progress.db"))
+}
+
+/// Long-term progress for every item ever studied, keyed by front and
+/// back: how often it was reviewed and missed, and the weight, FSRS memory
+/// or Leitner box it had at the end of the last session.
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open progress store {}", path.display()))?;
+        let store = Self { conn };
+        store.migrate(path)?;
+        Ok(store)
+    }
+
+    /// Brings the schema up to date, backing the store at `path` up first
+    /// if it has progress in an older schema. The migrations are applied
+    /// together or not at all.
+    fn migrate(&self, path: &Path) -> Result<()> {
+        let version: u32 = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .context("Failed to read progress store version")?;
+        if version as usize >= MIGRATIONS.len() {
+            return Ok(());
+        }
+        if version > 0 {
+            backup::before_migration(path, version)
+                .context("Failed to back up the progress store before migrating it")?;
+        }
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("Failed to migrate progress store")?;
+        for migration in MIGRATIONS.iter().skip(version as usize) {
+            tx.execute_batch(migration)
+                .context("Failed to migrate progress store")?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len() as u32)
+            .context("Failed to update progress store version")?;
+        tx.commit().context("Failed to migrate progress store")
+    }
+
+    /// The weight `item` had when it was last saved, if it was ever
+    /// studied.
+    pub fn weight(&self, item: &StudyItem) -> Result<Option<u32>> {
+        let weight = self
+            .conn
+            .query_row(
+                "SELECT weight FROM items WHERE front = ?1 AND back = ?2",
+                params![item.front, item.back],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to read item weight")?;
+        Ok(weight.flatten())
+    }
+
+    /// The FSRS memory `item` had when it was last saved, if any.
+    pub fn memory(&self, item: &StudyItem) -> Result<Option<Memory>> {
+        let memory = self
+            .conn
+            .query_row(
+                "SELECT stability, difficulty, memory_review FROM items
+                 WHERE front = ?1 AND back = ?2 AND stability IS NOT NULL",
+                params![item.front, item.back],
+                |row| {
+                    Ok(Memory {
+                        stability: row.get(0)?,
+                        difficulty: row.get(1)?,
+                        last_review: row.get::<_, i64>(2)? as u64,
+                    })
+                },
+            )
+            .optional()
+            .context("Failed to read item memory")?;
+        Ok(memory)
+    }
+
+    /// The Leitner box `item` was in when it was last saved, if any.
+    pub fn leitner_box(&self, item: &StudyItem) -> Result<Option<u8>> {
+        let leitner_box = self
+            .conn
+            .query_row(
+                "SELECT leitner_box FROM items WHERE front = ?1 AND back = ?2",
+                params![item.front, item.back],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Failed to read item box")?;
+        Ok(leitner_box.flatten())
+    }
+
+    /// Returns the totals for `item`, if it was ever answered.
+    pub fn item_stats(&self, item: &StudyItem) -> Result<Option<ItemStats>> {
+        let stats = self
+            .conn
+            .query_row(
+                "SELECT reviews, lapses, last_review FROM items
+                 WHERE front = ?1 AND back = ?2 AND reviews > 0",
+                params![item.front, item.back],
+                |row| {
+                    Ok(ItemStats {
+                        reviews: row.get(0)?,
+                        lapses: row.get(1)?,
+                        last_review: row.get::<_, Option<String>>(2)?.and_then(|time| {
+                            NaiveDateTime::parse_from_str(&time, "%Y-%m-%d %H:%M:%S").ok()
+                        }),
+                    })
+                },
+            )
+            .optional()
+            .context("Failed to read item stats")?;
+        Ok(stats)
+    }
+
+    /// Counts a review of `item` that took `response_ms`, and a lapse if
+    /// it was missed.
+    pub fn record(&self, item: &StudyItem, correct: bool, response_ms: u64) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO items (front, back, set_name, reviews, lapses, last_review, response_ms)
+                 VALUES (?1, ?2, ?3, 1, ?4, datetime('now'), ?5)
+                 ON CONFLICT (front, back) DO UPDATE SET
+                     set_name = excluded.set_name,
+                     reviews = reviews + 1,
+                     lapses = lapses + excluded.lapses,
+                     last_review = excluded.last_review,
+                     response_ms = response_ms + excluded.response_ms",
+                params![
+                    item.front,
+                    item.back,
+                    item.set,
+                    u32::from(!correct),
+                    response_ms as i64
+                ],
+            )
+            .context("Failed to record review")?;
+        self.conn
+            .execute(
+                "INSERT INTO days (day, reviews) VALUES (date('now', 'localtime'), 1)
+                 ON CONFLICT (day) DO UPDATE SET reviews = reviews + 1",
+                [],
+            )
+            .context("Failed to record daily reviews")?;
+        Ok(())
+    }
+
+    /// Returns how many reviews were done on each day with any, oldest
+    /// first.
+    pub fn daily_reviews(&self) -> Result<Vec<(NaiveDate, u32)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT day, reviews FROM days ORDER BY day")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+        })?;
+        let mut days = Vec::new();
+        for row in rows {
+            let (day, reviews) = row.context("Failed to read daily reviews")?;
+            let day = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                .with_context(|| format!("Invalid day '{}' in progress store", day))?;
+            days.push((day, reviews));
+        }
+        Ok(days)
+    }
+
+    /// Saves the current weight of every item in `session`.
+    pub fn save_weights(&mut self, session: &StudySession) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO items (front, back, set_name, weight) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (front, back) DO UPDATE SET weight = excluded.weight",
+            )?;
+            for (item, weight) in session.items().iter().zip(session.weights()) {
+                stmt.execute(params![item.front, item.back, item.set, weight])?;
+            }
+        }
+        tx.commit().context("Failed to save weights")
+    }
+
+    /// Saves the FSRS memory or Leitner box of every item in `session`
+    /// that has one.
+    pub fn save_schedule(&mut self, session: &StudySession) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut memories = tx.prepare(
+                "INSERT INTO items (front, back, set_name, stability, difficulty, memory_review)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (front, back) DO UPDATE SET
+                     stability = excluded.stability,
+                     difficulty = excluded.difficulty,
+                     memory_review = excluded.memory_review",
+            )?;
+            let mut boxes = tx.prepare(
+                "INSERT INTO items (front, back, set_name, leitner_box) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (front, back) DO UPDATE SET leitner_box = excluded.leitner_box",
+            )?;
+            for (index, item) in session.items().iter().enumerate() {
+                if let Some(memory) = session.memory(index) {
+                    memories.execute(params![
+                        item.front,
+                        item.back,
+                        item.set,
+                        memory.stability,
+                        memory.difficulty,
+                        memory.last_review as i64
+                    ])?;
+                }
+                if let Some(leitner_box) = session.boxes().map(|boxes| boxes[index]) {
+                    boxes.execute(params![item.front, item.back, item.set, leitner_box])?;
+                }
+            }
+        }
+        tx.commit().context("Failed to save schedule")
+    }
+
+    /// Returns the weight, schedule and lapses of every item answered or
+    /// scheduled, by set and front.
+    pub fn schedules(&self) -> Result<Vec<ItemSchedule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT front, back, weight, stability, difficulty, memory_review, lapses, leitner_box
+             FROM items
+             WHERE reviews > 0 OR stability IS NOT NULL OR leitner_box IS NOT NULL
+             ORDER BY set_name, front",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let memory = match (row.get(3)?, row.get(4)?, row.get::<_, Option<i64>>(5)?) {
+                (Some(stability), Some(difficulty), Some(last_review)) => Some(Memory {
+                    stability,
+                    difficulty,
+                    last_review: last_review as u64,
+                }),
+                _ => None,
+            };
+            Ok(ItemSchedule {
+                front: row.get(0)?,
+                back: row.get(1)?,
+                weight: row.get(2)?,
+                lapses: row.get(6)?,
+                leitner_box: row.get(7)?,
+                ..ItemSchedule::default()
+            }
+            .with_memory(memory))
+        })?;
+        rows.collect::<Result<_, _>>()
+            .context("Failed to read item schedules")
+    }
+
+    /// Replaces the progress of every item in `progress`, and raises the
+    /// reviews of each day in `days` to at least the given count, so
+    /// importing the same reviews twice changes nothing.
+    pub fn import(
+        &mut self,
+        progress: &[(StudyItem, ImportedProgress)],
+        days: &[(NaiveDate, u32)],
+    ) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut items = tx.prepare(
+                "INSERT INTO items (front, back, set_name, reviews, lapses, last_review,
+                     stability, difficulty, memory_review, leitner_box)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                 ON CONFLICT (front, back) DO UPDATE SET
+                     set_name = excluded.set_name,
+                     reviews = excluded.reviews,
+                     lapses = excluded.lapses,
+                     last_review = excluded.last_review,
+                     stability = excluded.stability,
+                     difficulty = excluded.difficulty,
+                     memory_review = excluded.memory_review,
+                     leitner_box = excluded.leitner_box",
+            )?;
+            for (item, progress) in progress {
+                let memory = progress.memory.as_ref();
+                items.execute(params![
+                    item.front,
+                    item.back,
+                    item.set,
+                    progress.reviews,
+                    progress.lapses,
+                    progress
+                        .last_review
+                        .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string()),
+                    memory.map(|memory| memory.stability),
+                    memory.map(|memory| memory.difficulty),
+                    memory.map(|memory| memory.last_review as i64),
+                    progress.leitner_box
+                ])?;
+            }
+            let mut reviews = tx.prepare(
+                "INSERT INTO days (day, reviews) VALUES (?1, ?2)
+                 ON CONFLICT (day) DO UPDATE SET reviews = MAX(reviews, excluded.reviews)",
+            )?;
+            for (day, count) in days {
+                reviews.execute(params![day.format("%Y-%m-%d").to_string(), count])?;
+            }
+        }
+        tx.commit().context("Failed to import progress")
+    }
+
+    /// Returns up to `n` items as `(front, back, mean seconds)`, slowest
+    /// to answer first.
+    pub fn slowest(&self, n: usize) -> Result<Vec<(String, String, f64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT front, back, response_ms / 1000.0 / reviews AS mean
+             FROM items
+             WHERE reviews > 0 AND response_ms > 0
+             ORDER BY mean DESC
+             LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![n as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        rows.collect::<Result<_, _>>()
+            .context("Failed to read slowest items")
+    }
+
+    /// Returns totals for every set with reviewed items, by set name.
+    pub fn set_stats(&self) -> Result<Vec<SetStats>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT set_name,
+                 COUNT(*),
+                 SUM(reviews),
+                 SUM(lapses),
+                 SUM(reviews >= ?1 AND reviews - lapses >= ?2 * reviews),
+                 SUM(reviews - lapses < ?3 * reviews),
+                 SUM(response_ms)
+             FROM items
+             WHERE reviews > 0
+             GROUP BY set_name
+             ORDER BY set_name",
+        )?;
+        let rows = stmt.query_map(
+            params![MASTERED_REVIEWS, MASTERED_ACCURACY, STRUGGLING_ACCURACY],
+            |row| {
+                Ok(SetStats {
+                    set: row.get(0)?,
+                    items: row.get(1)?,
+                    reviews: row.get(2)?,
+                    lapses: row.get(3)?,
+                    mastered: row.get(4)?,
+                    struggling: row.get(5)?,
+                    response_ms: row.get::<_, i64>(6)? as u64,
+                })
+            },
+        )?;
+        rows.collect::<Result<_, _>>()
+            .context("Failed to read progress stats")
+    }
+}
+
+/// Makes `\i` also show the lifetime totals of the current item from
+/// `store`.
+pub fn register_commands(registry: &mut CommandRegistry, store: Store) {
+    registry.register(
+        "i",
+        "Show what is known about the current item",
+        move |engine, _| {
+            let Some(item) = engine.current().cloned() else {
+                return Ok(Vec::new());
+            };
+            let mut lines = commands::item_info(engine, &item);
+            match store.item_stats(&item)? {
+                Some(stats) => {
+                    lines.push(format!(
+                        "Lifetime: {} reviews, {:.0}% correct",
+                        stats.reviews,
+                        stats.accuracy() * 100.0
+                    ));
+                    if let Some(last_review) = stats.last_review {
+                        lines.push(format!(
+                            "Last seen: {}",
+                            Local
+                                .from_utc_datetime(&last_review)
+                                .format("%Y-%m-%d %H:%M")
+                        ));
+                    }
+                }
+                None => lines.push("Lifetime: never answered before".to_string()),
+            }
+            Ok(vec![Render::Notice(lines.join("\n"))])
+        },
+    );
+}
+
+/// Records every answer in the progress store, along with how long it
+/// took, once it can no longer be undone.
+pub struct StoreListener {
+    store: Store,
+    answers: Answers,
+}
+
+impl StoreListener {
+    pub fn new(store: Store) -> Self {
+        Self {
+            store,
+            answers: Answers::default(),
+        }
+    }
+
+    fn record(&mut self, answer: Answer) {
+        let response_ms = answer.response.as_millis() as u64;
+        if let Err(err) = self.store.record(&answer.item, answer.correct, response_ms) {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+}
+
+impl Listener for StoreListener {
+    fn on_event(&mut self, event: &Event) {
+        for answer in self.answers.follow(event) {
+            self.record(answer);
+        }
+    }
+}
+
+impl Drop for StoreListener {
+    fn drop(&mut self) {
+        for answer in self.answers.flush() {
+            self.record(answer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Algorithm, Grade};
+
+    /// A fresh database path for `name`, removed when dropped.
+    struct TempDb(PathBuf);
+
+    impl TempDb {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("ljp-{}-{}.db", name, std::process::id()));
+            let _ = fs::remove_file(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn version(store: &Store) -> u32 {
+        store
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn new_stores_get_every_migration() {
+        let db = TempDb::new("new");
+        let store = Store::open(&db.0).unwrap();
+        assert_eq!(version(&store) as usize, MIGRATIONS.len());
+        drop(store);
+        assert_eq!(
+            version(&Store::open(&db.0).unwrap()) as usize,
+            MIGRATIONS.len()
+        );
+    }
+
+    #[test]
+    fn old_stores_are_migrated_keeping_their_data() {
+        let db = TempDb::new("old");
+        let conn = Connection::open(&db.0).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.execute(
+            "INSERT INTO items (front, back, reviews, lapses, weight) VALUES ('あ', 'a', 4, 1, 3)",
+            [],
+        )
+        .unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        drop(conn);
+
+        let store = Store::open(&db.0).unwrap();
+        assert_eq!(version(&store) as usize, MIGRATIONS.len());
+        // The store as it was before migrating is kept beside it.
+        let backup = PathBuf::from(format!("{}.pre-migration-v1.bak", db.0.display()));
+        let old = Connection::open(&backup).unwrap();
+        let old_version: u32 = old
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(old_version, 1);
+        drop(old);
+        fs::remove_file(&backup).unwrap();
+        let item = StudyItem::new("あ", "a");
+        assert_eq!(store.weight(&item).unwrap(), Some(3));
+        store.record(&item, true, 1500).unwrap();
+        let stats = store.item_stats(&item).unwrap().unwrap();
+        assert_eq!((stats.reviews, stats.lapses), (5, 1));
+        assert!(stats.last_review.is_some());
+        assert_eq!(store.daily_reviews().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn schedules_carry_over_between_sessions() {
+        let db = TempDb::new("schedule");
+        let mut store = Store::open(&db.0).unwrap();
+        let session = |algorithm| {
+            StudySession::builder()
+                .set("hiragana")
+                .algorithm(algorithm)
+                .build()
+                .unwrap()
+        };
+
+        let mut fsrs = session(Algorithm::Fsrs);
+        let first = fsrs.items()[0].clone();
+        fsrs.grade_item(&first, Grade::Good).unwrap();
+        store.save_schedule(&fsrs).unwrap();
+        assert_eq!(store.memory(&first).unwrap(), fsrs.memory(0));
+        let mut boxes = session(Algorithm::Leitner);
+        boxes.grade_item(&first, Grade::Easy).unwrap();
+        store.save_schedule(&boxes).unwrap();
+
+        let mut resumed = session(Algorithm::Leitner);
+        resumed.seed_boxes(|item| store.leitner_box(item)).unwrap();
+        assert_eq!(resumed.boxes().unwrap()[..2], [3, 1]);
+        let rows = store.schedules().unwrap();
+        let row = rows.iter().find(|row| row.front == first.front).unwrap();
+        assert_eq!(row.leitner_box, Some(3));
+        assert_eq!(row.interval, fsrs.memory(0).map(|memory| memory.stability));
+    }
+
+    #[test]
+    fn unanswered_items_have_no_stats() {
+        let db = TempDb::new("unanswered");
+        let store = Store::open(&db.0).unwrap();
+        let item = StudyItem::new("い", "i");
+        assert!(store.item_stats(&item).unwrap().is_none());
+        assert_eq!(store.weight(&item).unwrap(), None);
+    }
+}
@@ -0,0 +1,145 @@
+//! When to review next, for `ljp stats`: how many items the schedule has
+//! due today, or when the next one is due, and the hour of day the review
+//! history shows the learner answers best at.
+use chrono::{DateTime, Local, Timelike, Utc};
+
+use crate::{history::Review, schedule::ItemSchedule};
+
+/// How many reviews an hour needs before its accuracy is trusted.
+const MIN_REVIEWS: u32 = 20;
+
+/// How many items are due by the end of `now`'s day, and the earliest due
+/// date after it, if any.
+pub fn due(schedules: &[ItemSchedule], now: DateTime<Local>) -> (usize, Option<DateTime<Utc>>) {
+    let today = now.date_naive();
+    let mut due_today = 0;
+    let mut next = None;
+    for due in schedules.iter().filter_map(|schedule| schedule.due) {
+        if due.with_timezone(&Local).date_naive() <= today {
+            due_today += 1;
+        } else if next.is_none_or(|next| due < next) {
+            next = Some(due);
+        }
+    }
+    (due_today, next)
+}
+
+/// The hour of day, in local time, with the best accuracy among hours
+/// with at least [`MIN_REVIEWS`] reviews; the busier hour wins a tie.
+pub fn best_hour(reviews: &[Review]) -> Option<u32> {
+    let mut hours = [(0u32, 0u32); 24];
+    for review in reviews {
+        if let Some(at) = review.at() {
+            let (answered, correct) = &mut hours[at.hour() as usize];
+            *answered += 1;
+            *correct += u32::from(review.correct);
+        }
+    }
+    let accuracy = |(answered, correct): (u32, u32)| f64::from(correct) / f64::from(answered);
+    (0..24u32)
+        .filter(|&hour| hours[hour as usize].0 >= MIN_REVIEWS)
+        .max_by(|&a, &b| {
+            let (a, b) = (hours[a as usize], hours[b as usize]);
+            accuracy(a).total_cmp(&accuracy(b)).then(a.0.cmp(&b.0))
+        })
+}
+
+/// A line suggesting when to review, or `None` if nothing is scheduled.
+pub fn suggestion(
+    due_today: usize,
+    next: Option<DateTime<Utc>>,
+    best_hour: Option<u32>,
+    now: DateTime<Local>,
+) -> Option<String> {
+    if due_today > 0 {
+        let cards = match due_today {
+            1 => "1 card".to_string(),
+            n => format!("{} cards", n),
+        };
+        return Some(match best_hour {
+            Some(hour) if hour > now.hour() => format!(
+                "Review {} around {:02}:00, when you answer best.",
+                cards, hour
+            ),
+            _ => format!("Review {} due today.", cards),
+        });
+    }
+    next.map(|next| {
+        format!(
+            "Nothing is due today; the next review is due {}.",
+            next.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Days, TimeZone};
+
+    fn at(day: u32, hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 3, day, hour, 0, 0).unwrap()
+    }
+
+    fn review(at: DateTime<Local>, correct: bool) -> Review {
+        Review {
+            timestamp: at.to_rfc3339(),
+            set: "hiragana".to_string(),
+            front: "あ".to_string(),
+            back: "a".to_string(),
+            answer: "a".to_string(),
+            correct,
+            latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn items_due_by_tonight_count_as_due_today() {
+        let now = at(10, 9);
+        let schedule = |due: DateTime<Local>| ItemSchedule {
+            due: Some(due.with_timezone(&Utc)),
+            ..ItemSchedule::default()
+        };
+        let schedules = [
+            schedule(at(8, 12)),
+            schedule(at(10, 22)),
+            schedule(at(12, 7)),
+            schedule(at(11, 18)),
+            ItemSchedule::default(),
+        ];
+        assert_eq!(
+            due(&schedules, now),
+            (2, Some(at(11, 18).with_timezone(&Utc)))
+        );
+    }
+
+    #[test]
+    fn the_best_hour_needs_enough_reviews() {
+        let mut reviews = Vec::new();
+        for day in 1..=20 {
+            reviews.push(review(at(day, 8), day % 2 == 0));
+            reviews.push(review(at(day, 18), day != 1));
+        }
+        reviews.push(review(at(1, 3), true));
+        assert_eq!(best_hour(&reviews), Some(18));
+        assert_eq!(best_hour(&reviews[..10]), None);
+    }
+
+    #[test]
+    fn suggestions_name_the_best_hour_still_ahead() {
+        let now = at(10, 9);
+        assert_eq!(
+            suggestion(23, None, Some(18), now).unwrap(),
+            "Review 23 cards around 18:00, when you answer best."
+        );
+        assert_eq!(
+            suggestion(1, None, Some(8), now).unwrap(),
+            "Review 1 card due today."
+        );
+        let tomorrow = now.checked_add_days(Days::new(1)).unwrap();
+        assert!(suggestion(0, Some(tomorrow.with_timezone(&Utc)), None, now)
+            .unwrap()
+            .ends_with("due 2026-03-11 09:00."));
+        assert_eq!(suggestion(0, None, Some(18), now), None);
+    }
+}
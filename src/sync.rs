@@ -0,0 +1,523 @@
+//! `ljp sync`: keeping a profile's progress in step across devices.
+//!
+//! What is synced is the review history, which only ever grows: each side
+//! keeps every review either has, keyed by item and the time it was
+//! graded, so no device's reviews are lost to another's. The progress
+//! store is then rebuilt from the merged history, replaying each item's
+//! reviews through FSRS and Leitner boxes in order, so every device that
+//! has the same history ends up with the same schedule. The history only
+//! records right and wrong, so self-graded Hard and Easy answers replay as
+//! Good; weights, which depend on whole sessions, are left as they are.
+//!
+//! Where the history is kept is a [`SyncBackend`], chosen in the config:
+//!
+//! ```toml
+//! [sync]
+//! backend = "git"        # or "dir" or "s3"
+//! path = "~/ljp-sync"    # a clone, for "git"; any directory, for "dir"
+//! after_session = true   # sync after every study session
+//!
+//! # For "s3", instead of path; the keys default to $AWS_ACCESS_KEY_ID
+//! # and $AWS_SECRET_ACCESS_KEY.
+//! endpoint = "https://s3.eu-central-1.amazonaws.com"
+//! bucket = "my-ljp"
+//! region = "eu-central-1"
+//! ```
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    anki::{self, Card},
+    history::Review,
+    store::Store,
+    Grade, StudyItem,
+};
+
+/// How long an S3 request may take before it is given up on.
+const TIMEOUT_SECS: &str = "60";
+
+/// Somewhere the history is kept between devices.
+pub trait SyncBackend {
+    /// Where it is, for messages.
+    fn describe(&self) -> String;
+
+    /// The stored copy of `name`, or `None` if there is none yet.
+    fn fetch(&mut self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Replaces the stored copy of `name` with `data`.
+    fn store(&mut self, name: &str, data: &[u8]) -> Result<()>;
+}
+
+/// The `[sync]` section of the config.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SyncConfig {
+    #[serde(flatten)]
+    pub backend: BackendConfig,
+    /// Sync after every study session.
+    #[serde(default)]
+    pub after_session: bool,
+}
+
+/// Which backend to use, by its `backend` key.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum BackendConfig {
+    /// A directory, e.g. one a file-syncing service keeps in step.
+    Dir { path: PathBuf },
+    /// A clone of a Git repository; changes are pulled before and
+    /// committed and pushed after.
+    Git { path: PathBuf },
+    /// A bucket of S3-compatible storage, reached with curl.
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: String,
+        #[serde(default)]
+        prefix: String,
+        access_key: Option<String>,
+        secret_key: Option<String>,
+    },
+}
+
+impl BackendConfig {
+    /// Makes the backend, with `~` in paths expanded by `expand`.
+    pub fn open(&self, expand: impl Fn(&Path) -> PathBuf) -> Result<Box<dyn SyncBackend>> {
+        Ok(match self {
+            BackendConfig::Dir { path } => Box::new(DirBackend { dir: expand(path) }),
+            BackendConfig::Git { path } => Box::new(GitBackend { repo: expand(path) }),
+            BackendConfig::S3 {
+                endpoint,
+                bucket,
+                region,
+                prefix,
+                access_key,
+                secret_key,
+            } => {
+                let key = |value: &Option<String>, var: &str| {
+                    value
+                        .clone()
+                        .or_else(|| env::var(var).ok())
+                        .with_context(|| format!("S3 sync needs a key in the config or ${}", var))
+                };
+                Box::new(S3Backend {
+                    url: format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, prefix),
+                    region: region.clone(),
+                    user: format!(
+                        "{}:{}",
+                        key(access_key, "AWS_ACCESS_KEY_ID")?,
+                        key(secret_key, "AWS_SECRET_ACCESS_KEY")?
+                    ),
+                })
+            }
+        })
+    }
+}
+
+/// Writes `data` to `path` through a temporary file, so a failed write
+/// leaves the old contents.
+fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, data).with_context(|| format!("Failed to write {}", path.display()))?;
+    fs::rename(&temp, path).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Keeps files in a directory.
+pub struct DirBackend {
+    pub dir: PathBuf,
+}
+
+impl SyncBackend for DirBackend {
+    fn describe(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    fn fetch(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        read_if_exists(&self.dir.join(name))
+    }
+
+    fn store(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        write_atomically(&self.dir.join(name), data)
+    }
+}
+
+/// Keeps files in a Git clone, pulling before reading and pushing after
+/// writing if the clone has a remote.
+pub struct GitBackend {
+    pub repo: PathBuf,
+}
+
+impl GitBackend {
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.repo)
+            .args(args)
+            .output()
+            .context("Failed to run git, which syncing with Git needs")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git {} failed in {}: {}",
+                args.join(" "),
+                self.repo.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// The clone's first remote, if it has one.
+    fn remote(&self) -> Result<Option<String>> {
+        Ok(self.git(&["remote"])?.lines().next().map(str::to_string))
+    }
+
+    /// Whether the branch tracks one on the remote that exists yet.
+    fn has_upstream(&self) -> bool {
+        self.git(&["rev-parse", "--verify", "--quiet", "@{upstream}"])
+            .is_ok()
+    }
+}
+
+impl SyncBackend for GitBackend {
+    fn describe(&self) -> String {
+        format!("Git repository {}", self.repo.display())
+    }
+
+    fn fetch(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        if self.remote()?.is_some() {
+            self.git(&["fetch", "--quiet"])?;
+            if self.has_upstream() {
+                self.git(&["merge", "--ff-only", "--quiet", "@{upstream}"])?;
+            }
+        }
+        read_if_exists(&self.repo.join(name))
+    }
+
+    fn store(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        write_atomically(&self.repo.join(name), data)?;
+        self.git(&["add", "--", name])?;
+        if self
+            .git(&["status", "--porcelain", "--", name])?
+            .trim()
+            .is_empty()
+        {
+            return Ok(());
+        }
+        self.git(&[
+            "commit",
+            "--quiet",
+            "-m",
+            &format!("Sync {}", name),
+            "--",
+            name,
+        ])?;
+        match self.remote()? {
+            Some(_) if self.has_upstream() => self.git(&["push", "--quiet"]).map(drop),
+            // The first push to an empty remote has nothing to track yet.
+            Some(remote) => self
+                .git(&["push", "--quiet", "--set-upstream", &remote, "HEAD"])
+                .map(drop),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Keeps files in an S3-compatible bucket, signing requests with curl's
+/// `--aws-sigv4`. Credentials are handed to curl on stdin, not on its
+/// command line.
+pub struct S3Backend {
+    url: String,
+    region: String,
+    user: String,
+}
+
+impl S3Backend {
+    /// Runs curl on `name`'s object with `args`, returning the HTTP status
+    /// and the body.
+    fn curl(&self, name: &str, args: &[&str]) -> Result<(String, Vec<u8>)> {
+        let mut child = Command::new("curl")
+            .args(["--silent", "--max-time", TIMEOUT_SECS, "--config", "-"])
+            .args(["--aws-sigv4", &format!("aws:amz:{}:s3", self.region)])
+            .args(["--write-out", "\n%{http_code}"])
+            .args(args)
+            .arg(format!("{}{}", self.url, name))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to run curl, which syncing with S3 needs")?;
+        let mut stdin = child.stdin.take().context("Failed to run curl")?;
+        writeln!(stdin, "user = \"{}\"", self.user.replace('"', "\\\""))
+            .context("Failed to run curl")?;
+        drop(stdin);
+        let output = child.wait_with_output().context("Failed to run curl")?;
+        if !output.status.success() {
+            match output.status.code() {
+                Some(code) => anyhow::bail!("Syncing with S3 failed (curl error {})", code),
+                None => anyhow::bail!("Syncing with S3 was interrupted"),
+            }
+        }
+        let mut body = output.stdout;
+        let split = body.iter().rposition(|&b| b == b'\n').unwrap_or(0);
+        let status = String::from_utf8_lossy(&body[split..]).trim().to_string();
+        body.truncate(split);
+        Ok((status, body))
+    }
+}
+
+impl SyncBackend for S3Backend {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn fetch(&mut self, name: &str) -> Result<Option<Vec<u8>>> {
+        match self.curl(name, &[])? {
+            (status, body) if status == "200" => Ok(Some(body)),
+            (status, _) if status == "404" => Ok(None),
+            (status, _) => anyhow::bail!("Fetching {} from S3 failed (HTTP {})", name, status),
+        }
+    }
+
+    fn store(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let upload = env::temp_dir().join(format!("ljp-sync-{}", std::process::id()));
+        fs::write(&upload, data).context("Failed to prepare the upload")?;
+        let result = self.curl(name, &["--upload-file", &upload.to_string_lossy()]);
+        let _ = fs::remove_file(&upload);
+        match result? {
+            (status, _) if status.starts_with('2') => Ok(()),
+            (status, _) => anyhow::bail!("Storing {} in S3 failed (HTTP {})", name, status),
+        }
+    }
+}
+
+/// What a review is known by: when it was graded and its item, so the
+/// same review read on two devices has the same key and keys sort oldest
+/// first. Reviews with unreadable timestamps are told apart by the text.
+fn key(review: &Review) -> (Option<DateTime<Utc>>, String, String, String) {
+    let at = review.at().map(|at| at.with_timezone(&Utc));
+    let text = match at {
+        Some(_) => String::new(),
+        None => review.timestamp.clone(),
+    };
+    (at, review.front.clone(), review.back.clone(), text)
+}
+
+/// Merges two histories of JSON lines into one holding every review of
+/// either once, oldest first, with how many came from `theirs` only.
+/// Lines are kept as they were written, and merging is symmetric.
+pub fn merge(ours: &str, theirs: &str) -> (String, usize) {
+    let mut reviews = BTreeMap::new();
+    let mut received = 0;
+    for (text, is_ours) in [(ours, true), (theirs, false)] {
+        for line in text.lines() {
+            let Ok(review) = serde_json::from_str::<Review>(line) else {
+                continue;
+            };
+            // The same review may be written differently, e.g. in another
+            // time zone; the smaller line is kept so either side merges
+            // to the same text.
+            match reviews.entry(key(&review)) {
+                Entry::Vacant(entry) => {
+                    received += usize::from(!is_ours);
+                    entry.insert(line);
+                }
+                Entry::Occupied(mut entry) => {
+                    if line < *entry.get() {
+                        entry.insert(line);
+                    }
+                }
+            }
+        }
+    }
+    let mut merged = reviews.into_values().collect::<Vec<_>>().join("\n");
+    if !merged.is_empty() {
+        merged.push('\n');
+    }
+    (merged, received)
+}
+
+/// Replays every item's reviews in `history`, oldest first, into the
+/// progress `store`, replacing what it had for those items.
+pub fn rederive(store: &mut Store, history: &str) -> Result<usize> {
+    let mut items: BTreeMap<(String, String), (String, Card)> = BTreeMap::new();
+    for line in history.lines() {
+        let Ok(review) = serde_json::from_str::<Review>(line) else {
+            continue;
+        };
+        let Some(at) = review.at() else {
+            continue;
+        };
+        let (_, card) = items
+            .entry((review.front.clone(), review.back.clone()))
+            .or_insert_with(|| {
+                (
+                    review.set.clone(),
+                    Card {
+                        front: review.front.clone(),
+                        back: review.back.clone(),
+                        reviews: Vec::new(),
+                    },
+                )
+            });
+        card.reviews.push(anki::Review {
+            at: at.timestamp().max(0) as u64,
+            grade: if review.correct {
+                Grade::Good
+            } else {
+                Grade::Again
+            },
+        });
+    }
+    let mut progress = Vec::new();
+    let mut cards = Vec::new();
+    for (set, mut card) in items.into_values() {
+        card.reviews.sort_by_key(|review| review.at);
+        let item = StudyItem {
+            set,
+            ..StudyItem::new(card.front.clone(), card.back.clone())
+        };
+        progress.push((item, card.progress()));
+        cards.push(card);
+    }
+    store.import(&progress, &anki::days(&cards))?;
+    Ok(progress.len())
+}
+
+/// The outcome of a sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Synced {
+    /// Reviews that came from the other side.
+    pub received: usize,
+    /// Items whose progress was rebuilt.
+    pub items: usize,
+}
+
+/// Syncs the history at `history` with its copy `name` in `backend`, then
+/// rebuilds the progress `store` from the merged history.
+pub fn sync(
+    backend: &mut dyn SyncBackend,
+    name: &str,
+    history: &Path,
+    store: &mut Store,
+) -> Result<Synced> {
+    let ours = read_if_exists(history)?.unwrap_or_default();
+    let theirs = backend.fetch(name)?.unwrap_or_default();
+    let (merged, received) = merge(
+        &String::from_utf8_lossy(&ours),
+        &String::from_utf8_lossy(&theirs),
+    );
+    if merged.as_bytes() != ours.as_slice() {
+        write_atomically(history, merged.as_bytes())?;
+    }
+    if merged.as_bytes() != theirs.as_slice() {
+        backend.store(name, merged.as_bytes())?;
+    }
+    let items = rederive(store, &merged)?;
+    Ok(Synced { received, items })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(front: &str, timestamp: &str, correct: bool) -> String {
+        format!(
+            r#"{{"timestamp":"{}","set":"hiragana","front":"{}","back":"x","answer":"x","correct":{},"latency_ms":0}}"#,
+            timestamp, front, correct
+        )
+    }
+
+    #[test]
+    fn merging_keeps_every_review_once_in_order() {
+        let shared = line("あ", "2026-03-01T10:00:00+01:00", true);
+        // The same moment, written in another time zone.
+        let shared_utc = line("あ", "2026-03-01T09:00:00+00:00", true);
+        let ours = format!(
+            "{}\n{}\n",
+            shared,
+            line("い", "2026-03-02T10:00:00+01:00", false)
+        );
+        let theirs = format!(
+            "{}\n{}\nnot json\n",
+            line("う", "2026-03-01T12:00:00+01:00", true),
+            shared_utc
+        );
+
+        let (merged, received) = merge(&ours, &theirs);
+        let fronts: Vec<String> = merged
+            .lines()
+            .map(|l| serde_json::from_str::<Review>(l).unwrap().front)
+            .collect();
+        assert_eq!(fronts, ["あ", "う", "い"]);
+        assert_eq!(received, 1);
+        // Merging again, either way round, changes nothing.
+        assert_eq!(merge(&merged, &theirs), (merged.clone(), 0));
+        assert_eq!(merge(&theirs, &ours).0, merged);
+    }
+
+    #[test]
+    fn syncing_two_devices_gives_both_the_same_progress() {
+        let root = env::temp_dir().join(format!("ljp-sync-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let mut backend = DirBackend {
+            dir: root.join("remote"),
+        };
+        let history = |device: &str| root.join(format!("{}.jsonl", device));
+        fs::write(
+            history("a"),
+            line("あ", "2026-03-01T10:00:00+00:00", true) + "\n",
+        )
+        .unwrap();
+        fs::write(
+            history("b"),
+            line("あ", "2026-03-03T10:00:00+00:00", false) + "\n",
+        )
+        .unwrap();
+        let mut a = Store::open(&root.join("a.db")).unwrap();
+        let mut b = Store::open(&root.join("b.db")).unwrap();
+
+        sync(&mut backend, "history.jsonl", &history("a"), &mut a).unwrap();
+        let synced = sync(&mut backend, "history.jsonl", &history("b"), &mut b).unwrap();
+        assert_eq!(
+            synced,
+            Synced {
+                received: 1,
+                items: 1
+            }
+        );
+        let synced = sync(&mut backend, "history.jsonl", &history("a"), &mut a).unwrap();
+        assert_eq!(synced.received, 1);
+
+        assert_eq!(
+            fs::read_to_string(history("a")).unwrap(),
+            fs::read_to_string(history("b")).unwrap()
+        );
+        let item = StudyItem::new("あ", "x");
+        for store in [&a, &b] {
+            let stats = store.item_stats(&item).unwrap().unwrap();
+            assert_eq!((stats.reviews, stats.lapses), (2, 1));
+        }
+        assert_eq!(a.memory(&item).unwrap(), b.memory(&item).unwrap());
+        drop((a, b));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    env,
+    io::{stdout, IsTerminal},
+    str::FromStr,
+    sync::OnceLock,
+};
+
+pub const THEME_NAMES: [&str; 6] = [
+    "default",
+    "plain",
+    "dark",
+    "light",
+    "solarized",
+    "high-contrast",
+];
+
+/// Themes defined by the user, looked up after the built-in ones.
+static USER_THEMES: OnceLock<Vec<(String, Theme)>> = OnceLock::new();
+
+/// Makes `themes` available by name, e.g. from the config file. Only the
+/// first call has any effect.
+pub fn add_user_themes(themes: impl IntoIterator<Item = (String, Theme)>) {
+    let _ = USER_THEMES.set(themes.into_iter().collect());
+}
+
+/// ANSI SGR parameters (e.g. `1;32`) for each kind of output. An empty
+/// style leaves the text uncolored.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub front: String,
+    pub prompt: String,
+    pub correct: String,
+    pub incorrect: String,
+    pub answer: String,
+}
+
+impl Theme {
+    fn from_styles(styles: [&str; 5]) -> Self {
+        let [front, prompt, correct, incorrect, answer] = styles.map(String::from);
+        Self {
+            front,
+            prompt,
+            correct,
+            incorrect,
+            answer,
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Theme::from_styles(["1", "", "32", "31", "1;31"])),
+            "plain" => Ok(Theme::default()),
+            "dark" => Ok(Theme::from_styles(["1;97", "36", "32", "31", "1;33"])),
+            "light" => Ok(Theme::from_styles(["1;30", "34", "32", "31", "1;35"])),
+            "solarized" => Ok(Theme::from_styles([
+                "1;38;5;33",
+                "38;5;37",
+                "38;5;64",
+                "38;5;160",
+                "1;38;5;136",
+            ])),
+            "high-contrast" => Ok(Theme::from_styles([
+                "1;97;40", "1;93", "1;92", "1;91", "1;4;93",
+            ])),
+            _ => {
+                let user_themes = USER_THEMES.get().map(Vec::as_slice).unwrap_or_default();
+                if let Some((_, theme)) = user_themes.iter().find(|(name, _)| name == s) {
+                    return Ok(theme.clone());
+                }
+                let names: Vec<&str> = THEME_NAMES
+                    .iter()
+                    .copied()
+                    .chain(user_themes.iter().map(|(name, _)| name.as_str()))
+                    .collect();
+                Err(format!(
+                    "unknown theme '{}' (available: {})",
+                    s,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+/// Wraps `text` in the escape codes for `style`.
+pub fn paint(style: &str, text: &str) -> String {
+    if style.is_empty() {
+        text.to_string()
+    } else {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    }
+}
+
+/// Whether output is colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && stdout().is_terminal()
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+const client = Math.random().toString(36).slice(2);
+
+const front = document.getElementById("front");
+const prompt = document.getElementById("prompt");
+const form = document.getElementById("answer-form");
+const input = document.getElementById("answer");
+const feedback = document.getElementById("feedback");
+const weights = document.getElementById("weights");
+const toggle = document.getElementById("toggle-weights");
+
+async function api(path, options) {
+  const sep = path.includes("?") ? "&" : "?";
+  const response = await fetch(`${path}${sep}client=${client}`, options);
+  return response.json();
+}
+
+async function nextCard() {
+  const card = await api("/api/card");
+  if (card.front === undefined) {
+    front.textContent = "";
+    feedback.textContent = "No items available for study.";
+    return;
+  }
+  front.textContent = card.front;
+  prompt.textContent = card.prompt ?? "";
+  input.placeholder = card.label ?? "answer";
+  input.value = "";
+  input.focus();
+}
+
+async function showWeights() {
+  const rows = await api("/api/weights");
+  weights.replaceChildren(
+    ...rows.map((row) => {
+      const tr = document.createElement("tr");
+      for (const value of [row.front, row.back, row.weight]) {
+        const td = document.createElement("td");
+        td.textContent = value;
+        tr.appendChild(td);
+      }
+      return tr;
+    }),
+  );
+}
+
+form.addEventListener("submit", async (event) => {
+  event.preventDefault();
+  const result = await api("/api/answer", {
+    method: "POST",
+    body: JSON.stringify({ answer: input.value.trim() }),
+  });
+  if (result.correct) {
+    feedback.textContent = "Correct!";
+    feedback.className = "feedback correct";
+  } else {
+    feedback.textContent = `Incorrect. The correct answer is: ${result.expected}`;
+    feedback.className = "feedback incorrect";
+  }
+  if (!weights.hidden) {
+    await showWeights();
+  }
+  await nextCard();
+});
+
+toggle.addEventListener("click", async () => {
+  weights.hidden = !weights.hidden;
+  if (!weights.hidden) {
+    await showWeights();
+  }
+});
+
+nextCard();